@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use slings::sync::{mpsc, oneshot};
+use slings::time::delay_for;
+
+fn main() {
+    slings::block_on(async {
+        let (tx, mut rx) = mpsc::channel::<(u32, oneshot::Sender<u32>)>(8);
+
+        // a worker squaring the numbers it is sent.
+        slings::spawn_local(async move {
+            while let Some((n, reply)) = rx.recv().await {
+                delay_for(Duration::from_millis(10)).await.unwrap();
+                let _ = reply.send(n * n);
+            }
+            println!("all senders dropped, worker exits");
+        })
+        .detach();
+
+        for n in 1..=3 {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            tx.send((n, reply_tx)).await.unwrap();
+            println!("{} squared is {}", n, reply_rx.await.unwrap());
+        }
+        drop(tx);
+        delay_for(Duration::from_millis(1)).await.unwrap();
+    });
+}
@@ -4,8 +4,5 @@ use std::time::Duration;
 use slings::time::delay_for;
 
 fn main() -> io::Result<()> {
-    slings::block_on(async {
-        delay_for(Duration::from_secs(1)).await;
-    });
-    Ok(())
+    slings::block_on(delay_for(Duration::from_secs(1)))
 }
@@ -0,0 +1,23 @@
+use std::future::poll_fn;
+use std::io;
+use std::time::Duration;
+
+use slings::time::DelayQueue;
+
+fn main() -> io::Result<()> {
+    slings::block_on(async {
+        let mut queue = DelayQueue::new();
+        queue.insert("session-1", Duration::from_millis(300));
+        let key = queue.insert("session-2", Duration::from_millis(100));
+        let removed = queue.insert("session-3", Duration::from_millis(200));
+
+        // session-2 is refreshed and session-3 logs out.
+        queue.reset(&key, Duration::from_millis(500));
+        queue.remove(&removed);
+
+        while let Some(expired) = poll_fn(|cx| queue.poll_expired(cx)).await {
+            println!("{} expired", expired?.into_inner());
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,30 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use slings::runtime::Runtime;
+use slings::task::JoinError;
+use slings::time::delay_for;
+
+fn main() -> Result<(), JoinError> {
+    let (handle_tx, handle_rx) = mpsc::channel();
+    let runtime_thread = thread::spawn(move || {
+        let runtime = Runtime::new().unwrap();
+        handle_tx.send(runtime.handle()).unwrap();
+        runtime
+            .block_on(delay_for(Duration::from_millis(200)))
+            .unwrap();
+    });
+    let handle = handle_rx.recv().unwrap();
+
+    // timers are `!Send`, the future is built on the runtime thread.
+    let task = handle.spawn_with(|| async {
+        delay_for(Duration::from_millis(50)).await.unwrap();
+        "woke up on the runtime"
+    });
+    println!("{}", handle.block_on(task)??);
+    println!("{}", handle.block_on(async { 6 * 7 })?);
+
+    runtime_thread.join().unwrap();
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::sync::mpsc;
+use std::thread;
+
+use slings::msg_ring::{self, Message, RuntimeHandle};
+
+fn main() {
+    // a runtime per worker thread, each handing its handle back.
+    let (handle_tx, handle_rx) = mpsc::channel();
+    let workers: Vec<_> = (0..2)
+        .map(|id| {
+            let handle_tx = handle_tx.clone();
+            thread::spawn(move || {
+                slings::block_on(async move {
+                    let mut messages = msg_ring::channel();
+                    handle_tx.send(RuntimeHandle::current()).unwrap();
+                    while let Some(Message::Data(n)) = messages.recv().await {
+                        if n == 0 {
+                            break;
+                        }
+                        println!("worker {} got {}", id, n);
+                    }
+                })
+            })
+        })
+        .collect();
+    let handles: Vec<RuntimeHandle> = handle_rx.iter().take(2).collect();
+
+    slings::block_on(async move {
+        for n in 1..=6 {
+            handles[n % 2].send(n as u64).await.unwrap();
+        }
+        for handle in &handles {
+            handle.send(0).await.unwrap();
+        }
+    });
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
@@ -0,0 +1,28 @@
+use std::process::Stdio;
+
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use slings::process::Command;
+
+fn main() {
+    slings::block_on(async {
+        let output = Command::new("uname").arg("-sr").output().await.unwrap();
+        print!("uname: {}", String::from_utf8_lossy(&output.stdout));
+
+        // feed a child through its stdin and read its stdout back.
+        let mut child = Command::new("tr")
+            .arg("a-z")
+            .arg("A-Z")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"hello from slings\n").await.unwrap();
+        stdin.close().await.unwrap();
+        let mut upper = String::new();
+        let mut stdout = child.stdout.take().unwrap();
+        stdout.read_to_string(&mut upper).await.unwrap();
+        print!("tr: {}", upper);
+        println!("exit: {}", child.wait().await.unwrap());
+    });
+}
@@ -0,0 +1,34 @@
+use std::thread;
+use std::time::Duration;
+
+use slings::sync::remote;
+
+fn main() {
+    let (tx, mut rx) = remote::channel::<u32>(4);
+
+    // plain threads feeding the runtime, the sends wake it up while it is
+    // parked in the driver.
+    let producers: Vec<_> = (0..2)
+        .map(|id| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for n in 0..5 {
+                    thread::sleep(Duration::from_millis(20));
+                    tx.blocking_send(id * 100 + n).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    slings::block_on(async {
+        while let Some(n) = rx.recv().await {
+            println!("received {}", n);
+        }
+        println!("all producers done");
+    });
+
+    for producer in producers {
+        producer.join().unwrap();
+    }
+}
@@ -0,0 +1,17 @@
+use slings::signal::{self, SignalKind};
+
+fn main() {
+    slings::block_on(async {
+        let mut hangup = signal::signal(SignalKind::hangup()).unwrap();
+        slings::spawn_local(async move {
+            while hangup.recv().await.is_some() {
+                println!("SIGHUP, reloading config");
+            }
+        })
+        .detach();
+
+        println!("pid {}, waiting for ctrl-c", std::process::id());
+        signal::ctrl_c().await.unwrap();
+        println!("shutting down");
+    });
+}
@@ -0,0 +1,16 @@
+use std::thread;
+use std::time::Duration;
+
+use slings::task::spawn_blocking;
+
+fn main() {
+    slings::block_on(async {
+        let sum = spawn_blocking(|| {
+            thread::sleep(Duration::from_secs(1));
+            (0..1_000_000u64).sum::<u64>()
+        })
+        .await
+        .unwrap();
+        println!("sum computed on blocking pool: {}", sum);
+    });
+}
@@ -0,0 +1,22 @@
+use futures_util::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, StreamExt};
+
+fn main() {
+    slings::block_on(async {
+        // round-trip through an anonymous pipe.
+        let (mut tx, mut rx) = slings::pipe::pipe().unwrap();
+        tx.write_all(b"through a pipe\n").await.unwrap();
+        tx.close().await.unwrap();
+        let mut msg = String::new();
+        rx.read_to_string(&mut msg).await.unwrap();
+
+        let mut stdout = slings::io::stdout();
+        stdout.write_all(msg.as_bytes()).await.unwrap();
+
+        // echo stdin line by line until end of file.
+        let mut lines = slings::io::stdin().lines();
+        while let Some(line) = lines.next().await {
+            let line = format!("echo: {}\n", line.unwrap());
+            stdout.write_all(line.as_bytes()).await.unwrap();
+        }
+    });
+}
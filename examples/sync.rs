@@ -0,0 +1,31 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use slings::sync::{Mutex, Notify};
+use slings::time::delay_for;
+
+fn main() {
+    slings::block_on(async {
+        let counter = Rc::new(Mutex::new(0));
+        let done = Rc::new(Notify::new());
+
+        for i in 0..3 {
+            let counter = counter.clone();
+            let done = done.clone();
+            slings::spawn_local(async move {
+                let mut n = counter.lock().await;
+                // the guard is held across the await point.
+                delay_for(Duration::from_millis(10)).await.unwrap();
+                *n += 1;
+                println!("task {} incremented counter to {}", i, *n);
+                if *n == 3 {
+                    done.notify_one();
+                }
+            })
+            .detach();
+        }
+
+        done.notified().await;
+        println!("counter: {}", *counter.lock().await);
+    });
+}
@@ -12,7 +12,7 @@ fn main() -> io::Result<()> {
         loop {
             stream.read_exact(&mut buf).await?;
             println!("read bytes: {:?}", buf);
-            delay_for(Duration::from_secs(1)).await;
+            delay_for(Duration::from_secs(1)).await?;
         }
     })
 }
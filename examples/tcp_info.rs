@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use slings::net::{TcpListener, TcpStream, UdpSocket};
+
+fn main() {
+    slings::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (mut conn, _) = listener.accept().await.unwrap();
+
+        client.set_keepalive_idle(Duration::from_secs(30)).unwrap();
+        client
+            .set_keepalive_interval(Duration::from_secs(5))
+            .unwrap();
+        client.set_keepalive_retries(3).unwrap();
+        client
+            .set_user_timeout(Some(Duration::from_secs(20)))
+            .unwrap();
+        client.set_tos(0x2e << 2).unwrap();
+        client.set_notsent_lowat(16 * 1024).unwrap();
+        println!(
+            "keepalive {} idle {:?} interval {:?} retries {}",
+            client.keepalive().unwrap(),
+            client.keepalive_idle().unwrap(),
+            client.keepalive_interval().unwrap(),
+            client.keepalive_retries().unwrap(),
+        );
+        println!(
+            "user timeout {:?} tos {:#x} ttl {} congestion {}",
+            client.user_timeout().unwrap(),
+            client.tos().unwrap(),
+            client.ttl().unwrap(),
+            String::from_utf8_lossy(&client.congestion().unwrap()),
+        );
+
+        client.write_all(&[0; 64 * 1024]).await.unwrap();
+        let mut buf = vec![0; 64 * 1024];
+        conn.read_exact(&mut buf).await.unwrap();
+        let info = client.tcp_info().unwrap();
+        println!(
+            "rtt {:?} cwnd {} retrans {} acked {}",
+            info.rtt, info.snd_cwnd, info.total_retrans, info.bytes_acked
+        );
+
+        let udp = UdpSocket::bind("[::1]:0").unwrap();
+        udp.set_ttl(8).unwrap();
+        udp.set_tos(0xb8).unwrap();
+        println!(
+            "udp ttl {} tclass {:#x}",
+            udp.ttl().unwrap(),
+            udp.tos().unwrap()
+        );
+    });
+}
@@ -0,0 +1,26 @@
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use slings::net::TcpSocket;
+
+fn main() {
+    slings::block_on(async {
+        // sharing the port with other instances is opt-in.
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.set_reuseaddr(true).unwrap();
+        socket.set_reuseport(true).unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(128).unwrap();
+        let addr = listener.local_addr().unwrap();
+        println!("listening on {}", addr);
+
+        let client = TcpSocket::new_v4().unwrap();
+        client.set_recv_buffer_size(64 * 1024).unwrap();
+        let mut client = client.connect(addr).await.unwrap();
+        let (mut conn, peer) = listener.accept().await.unwrap();
+        println!("accepted {}", peer);
+
+        conn.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        println!("client got {:?}", String::from_utf8_lossy(&buf));
+    });
+}
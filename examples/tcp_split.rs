@@ -0,0 +1,46 @@
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use slings::net::{TcpListener, TcpStream};
+use slings::sync::mpsc;
+
+fn main() {
+    slings::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // echo server, reading and writing from different tasks.
+        slings::spawn_local(async move {
+            let (conn, _) = listener.accept().await.unwrap();
+            let (mut read, mut write) = conn.into_split();
+            let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+            slings::spawn_local(async move {
+                while let Some(buf) = rx.recv().await {
+                    write.write_all(&buf).await.unwrap();
+                }
+                // dropping the write half sends EOF.
+            })
+            .detach();
+            let mut buf = [0; 1024];
+            loop {
+                let n = read.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                tx.send(buf[..n].to_vec()).unwrap();
+            }
+        })
+        .detach();
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut read, mut write) = stream.into_split();
+        write.write_all(b"hello split").await.unwrap();
+        let mut buf = [0; 11];
+        read.read_exact(&mut buf).await.unwrap();
+        println!("echoed {:?}", String::from_utf8_lossy(&buf));
+
+        let mut stream = read.reunite(write).unwrap();
+        stream.close().await.unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        println!("server closed after {} more bytes", rest.len());
+    });
+}
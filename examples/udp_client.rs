@@ -14,7 +14,7 @@ fn main() -> io::Result<()> {
         loop {
             let n = socket.send_to(buf, addr).await?;
             println!("send bytes: {:?}", &buf[..n]);
-            delay_for(Duration::from_secs(1)).await;
+            delay_for(Duration::from_secs(1)).await?;
         }
     })
 }
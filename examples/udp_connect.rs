@@ -18,7 +18,7 @@ fn main() -> io::Result<()> {
             let mut buf = vec![0; 10];
             let n = socket.recv2(&mut buf).await?;
             println!("recv {} bytes", n);
-            delay_for(Duration::from_secs(1)).await;
+            delay_for(Duration::from_secs(1)).await?;
         }
     })
 }
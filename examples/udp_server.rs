@@ -15,7 +15,7 @@ fn main() -> io::Result<()> {
             let buf = b"helloworld";
             let n = socket.send_to(buf, addr).await?;
             println!("send {} bytes", n);
-            delay_for(Duration::from_secs(1)).await;
+            delay_for(Duration::from_secs(1)).await?;
         }
     })
 }
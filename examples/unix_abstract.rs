@@ -0,0 +1,26 @@
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use slings::net::unix::SocketAddr;
+use slings::net::{UnixListener, UnixStream};
+
+fn main() {
+    slings::block_on(async {
+        // no file is left behind by an abstract address.
+        let addr = SocketAddr::from_abstract_name("slings-example").unwrap();
+        let listener = UnixListener::bind_addr(&addr).unwrap();
+        println!("listening on {:?}", listener.local_addr().unwrap());
+
+        let mut client = UnixStream::connect_addr(&addr).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        println!("server got {:?}", String::from_utf8_lossy(&buf));
+
+        // a pathname socket cleaning up after itself, even after a crash.
+        let path = std::env::temp_dir().join("slings_example.sock");
+        let listener = UnixListener::bind_unlink(&path).unwrap();
+        println!("listening on {:?}", listener.local_addr().unwrap());
+        drop(listener);
+        println!("socket file left: {}", path.exists());
+    });
+}
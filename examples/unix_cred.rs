@@ -0,0 +1,28 @@
+use slings::net::UnixDatagram;
+
+fn main() {
+    slings::block_on(async {
+        let path = std::env::temp_dir().join("slings_cred.sock");
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        server.set_passcred(true).unwrap();
+
+        let client = UnixDatagram::unbound().unwrap();
+        client.send_to(b"status", &path).await.unwrap();
+
+        let mut buf = [0; 64];
+        let (n, cred) = server.recv_with_cred(&mut buf).await.unwrap();
+        let cred = cred.unwrap();
+        println!(
+            "{:?} from pid {:?} uid {} gid {}",
+            String::from_utf8_lossy(&buf[..n]),
+            cred.pid(),
+            cred.uid(),
+            cred.gid()
+        );
+
+        let (a, _b) = UnixDatagram::pair().unwrap();
+        println!("pair peer: {:?}", a.peer_cred().unwrap());
+        let _ = std::fs::remove_file(&path);
+    });
+}
@@ -0,0 +1,24 @@
+use slings::net::UnixDatagram;
+
+fn main() {
+    slings::block_on(async {
+        let path = std::env::temp_dir().join("slings_datagram.sock");
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        // an unbound client shows up as an unnamed peer.
+        let client = UnixDatagram::unbound().unwrap();
+        client.send_to(b"cpu=0.42", &path).await.unwrap();
+        let mut buf = [0; 64];
+        let (n, addr) = server.recv_from(&mut buf).await.unwrap();
+        println!("{:?} from {:?}", String::from_utf8_lossy(&buf[..n]), addr);
+
+        // a connected pair needs no addresses.
+        let (a, b) = UnixDatagram::pair().unwrap();
+        a.send(b"ping").await.unwrap();
+        let n = b.recv(&mut buf).await.unwrap();
+        println!("pair: {:?}", String::from_utf8_lossy(&buf[..n]));
+
+        let _ = std::fs::remove_file(&path);
+    });
+}
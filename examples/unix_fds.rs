@@ -0,0 +1,45 @@
+use std::io::{Read, Seek, Write};
+use std::os::unix::io::AsRawFd;
+
+use slings::net::UnixDatagram;
+
+fn main() {
+    slings::block_on(async {
+        let (supervisor, worker) = UnixDatagram::pair().unwrap();
+
+        let mut file = tempfile();
+        file.write_all(b"shared state").unwrap();
+        supervisor
+            .send_with_fds(b"take this", &[file.as_raw_fd()])
+            .await
+            .unwrap();
+
+        let mut buf = [0; 32];
+        let mut fds = Vec::new();
+        let n = worker.recv_with_fds(&mut buf, &mut fds).await.unwrap();
+        println!(
+            "{:?} with {} fd",
+            String::from_utf8_lossy(&buf[..n]),
+            fds.len()
+        );
+
+        let mut received = std::fs::File::from(fds.pop().unwrap());
+        received.rewind().unwrap();
+        let mut content = String::new();
+        received.read_to_string(&mut content).unwrap();
+        println!("content: {:?}", content);
+    });
+}
+
+fn tempfile() -> std::fs::File {
+    let path = std::env::temp_dir().join("slings_fds.txt");
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    file
+}
@@ -0,0 +1,34 @@
+use slings::net::{UnixSeqpacket, UnixSeqpacketListener};
+
+fn main() {
+    slings::block_on(async {
+        let path = std::env::temp_dir().join("slings_seqpacket.sock");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixSeqpacketListener::bind(&path).unwrap();
+
+        slings::spawn_local({
+            let path = path.clone();
+            async move {
+                let client = UnixSeqpacket::connect(&path).await.unwrap();
+                client.send(b"hello").await.unwrap();
+                client
+                    .send(b"a message longer than the buffer")
+                    .await
+                    .unwrap();
+            }
+        })
+        .detach();
+
+        let (conn, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 16];
+        loop {
+            let (n, truncated) = conn.recv_msg(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            let data = String::from_utf8_lossy(&buf[..n]);
+            println!("message: {:?}, truncated: {}", data, truncated);
+        }
+        let _ = std::fs::remove_file(&path);
+    });
+}
@@ -24,7 +24,10 @@ fn main() -> io::Result<()> {
                             break;
                         }
                     }
-                    delay_for(Duration::from_secs(1)).await;
+                    if let Err(e) = delay_for(Duration::from_secs(1)).await {
+                        println!("timer fail {}", e);
+                        break;
+                    }
                 }
             })
             .detach();
@@ -0,0 +1,51 @@
+use std::io::{IoSlice, IoSliceMut};
+
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use slings::net::{TcpListener, TcpStream, UdpSocket};
+
+fn main() {
+    slings::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (mut conn, _) = listener.accept().await.unwrap();
+
+        // a length prefixed frame, header and body go out in one writev.
+        let body = b"hello vectored";
+        let header = (body.len() as u32).to_be_bytes();
+        let n = client
+            .write_vectored(&[IoSlice::new(&header), IoSlice::new(body)])
+            .await
+            .unwrap();
+        println!("wrote {} bytes", n);
+
+        let mut len = [0; 4];
+        let mut buf = [0; 64];
+        let n = conn
+            .read_vectored(&mut [IoSliceMut::new(&mut len), IoSliceMut::new(&mut buf)])
+            .await
+            .unwrap();
+        let len = u32::from_be_bytes(len) as usize;
+        println!(
+            "read {} bytes, frame of {}: {:?}",
+            n,
+            len,
+            String::from_utf8_lossy(&buf[..len])
+        );
+        client.close().await.unwrap();
+        conn.read_to_end(&mut Vec::new()).await.unwrap();
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .send_vectored_to(
+                &[IoSlice::new(&header), IoSlice::new(body)],
+                server.local_addr().unwrap(),
+            )
+            .await
+            .unwrap();
+        let mut datagram = [0; 64];
+        let (n, from) = server.recv_from(&mut datagram).await.unwrap();
+        println!("datagram of {} bytes from {}", n, from);
+    });
+}
@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use slings::task::yield_now;
+use slings::time::delay_for;
+
+fn main() {
+    slings::block_on(async {
+        slings::spawn_local(async {
+            let mut n = 0u64;
+            loop {
+                n += 1;
+                if n.is_multiple_of(1_000_000) {
+                    println!("busy task polled {} times", n);
+                }
+                yield_now().await;
+            }
+        })
+        .detach();
+
+        delay_for(Duration::from_secs(1)).await.unwrap();
+        println!("timer fired while busy task is running");
+    });
+}
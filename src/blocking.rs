@@ -0,0 +1,333 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+use scoped_tls::scoped_thread_local;
+
+pub(crate) const DEFAULT_MAX_THREADS: usize = 512;
+pub(crate) const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+scoped_thread_local!(static CURRENT: BlockingPool);
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A pool of threads running blocking closures on behalf of a runtime.
+///
+/// Threads are spawned lazily up to `max_threads`, and exit after staying idle
+/// for `keep_alive`.
+pub(crate) struct BlockingPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    condvar: Condvar,
+    max_threads: usize,
+    keep_alive: Duration,
+}
+
+struct State {
+    queue: VecDeque<Job>,
+    num_threads: usize,
+    num_idle: usize,
+    // jobs queued or running.
+    num_pending: usize,
+    // idle threads woken up to run a job.
+    num_notify: usize,
+    shutdown: bool,
+}
+
+impl BlockingPool {
+    pub(crate) fn new(max_threads: usize, keep_alive: Duration) -> BlockingPool {
+        BlockingPool {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State {
+                    queue: VecDeque::new(),
+                    num_threads: 0,
+                    num_idle: 0,
+                    num_pending: 0,
+                    num_notify: 0,
+                    shutdown: false,
+                }),
+                condvar: Condvar::new(),
+                max_threads,
+                keep_alive,
+            }),
+        }
+    }
+
+    pub(crate) fn enter<T>(&self, f: impl FnOnce() -> T) -> T {
+        CURRENT.set(self, f)
+    }
+
+    /// Whether no closure is queued or running.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.inner.state.lock().unwrap().num_pending == 0
+    }
+
+    fn spawn(&self, job: Job) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.queue.push_back(job);
+        state.num_pending += 1;
+        if state.num_idle > 0 {
+            state.num_idle -= 1;
+            state.num_notify += 1;
+            self.inner.condvar.notify_one();
+        } else if state.num_threads < self.inner.max_threads {
+            state.num_threads += 1;
+            let inner = self.inner.clone();
+            thread::Builder::new()
+                .name("slings-blocking".to_string())
+                .spawn(move || inner.run())
+                .expect("failed to spawn blocking thread");
+        }
+    }
+}
+
+impl Drop for BlockingPool {
+    fn drop(&mut self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.shutdown = true;
+        state.num_pending -= state.queue.len();
+        state.queue.clear();
+        self.inner.condvar.notify_all();
+    }
+}
+
+impl Inner {
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            while let Some(job) = state.queue.pop_front() {
+                drop(state);
+                job();
+                state = self.state.lock().unwrap();
+                state.num_pending -= 1;
+            }
+            if state.shutdown {
+                break;
+            }
+
+            state.num_idle += 1;
+            loop {
+                let (guard, res) = self.condvar.wait_timeout(state, self.keep_alive).unwrap();
+                state = guard;
+                if state.num_notify > 0 {
+                    state.num_notify -= 1;
+                    break;
+                }
+                if state.shutdown || res.timed_out() {
+                    state.num_idle -= 1;
+                    state.num_threads -= 1;
+                    return;
+                }
+            }
+        }
+        state.num_threads -= 1;
+    }
+}
+
+/// Runs the provided closure on a thread where blocking is acceptable.
+///
+/// The closure runs on the blocking pool of the current runtime, the returned
+/// `JoinHandle` resolves to its result and resumes its panic if it panicked.
+/// It fails if the runtime is dropped before the closure runs.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime context.
+pub fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    if !CURRENT.is_set() {
+        panic!("`spawn_blocking` called from outside of a runtime context");
+    }
+    let (tx, handle) = JoinHandle::new();
+    let job = Box::new(move || tx.send(panic::catch_unwind(AssertUnwindSafe(f))));
+    CURRENT.with(|pool| pool.spawn(job));
+    handle
+}
+
+/// A handle to a closure running on the blocking pool, or to a task spawned
+/// through a runtime `Handle`.
+///
+/// Awaiting it fails with a `JoinError` if the closure or the task was dropped
+/// before completing, with its runtime.
+pub struct JoinHandle<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+/// Returned by a `JoinHandle` whose closure or task was dropped before
+/// completing, because its runtime was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinError(());
+
+impl JoinError {
+    /// Whether the task was cancelled, which is always the case: a panic is
+    /// resumed by the `JoinHandle` rather than returned.
+    pub fn is_cancelled(&self) -> bool {
+        true
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "task was cancelled before completion")
+    }
+}
+
+impl Error for JoinError {}
+
+impl From<JoinError> for io::Error {
+    fn from(err: JoinError) -> io::Error {
+        io::Error::other(err)
+    }
+}
+
+/// Completes a `JoinHandle` from any thread, dropping it unsent cancels it.
+pub(crate) struct JoinSender<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+    sent: bool,
+}
+
+struct Shared<T> {
+    output: Option<thread::Result<T>>,
+    cancelled: bool,
+    waker: Option<Waker>,
+}
+
+impl<T> JoinHandle<T> {
+    pub(crate) fn new() -> (JoinSender<T>, JoinHandle<T>) {
+        let shared = Arc::new(Mutex::new(Shared {
+            output: None,
+            cancelled: false,
+            waker: None,
+        }));
+        let tx = JoinSender {
+            shared: shared.clone(),
+            sent: false,
+        };
+        (tx, JoinHandle { shared })
+    }
+}
+
+impl<T> JoinSender<T> {
+    pub(crate) fn send(mut self, output: thread::Result<T>) {
+        self.sent = true;
+        self.complete(|shared| shared.output = Some(output));
+    }
+
+    fn complete(&self, f: impl FnOnce(&mut Shared<T>)) {
+        let waker = {
+            let mut shared = self.shared.lock().unwrap();
+            f(&mut shared);
+            shared.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for JoinSender<T> {
+    fn drop(&mut self) {
+        if !self.sent {
+            self.complete(|shared| shared.cancelled = true);
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.output.take() {
+            Some(Ok(output)) => Poll::Ready(Ok(output)),
+            Some(Err(err)) => panic::resume_unwind(err),
+            None if shared.cancelled => Poll::Ready(Err(JoinError(()))),
+            None => {
+                match shared.waker.as_ref() {
+                    Some(waker) if waker.will_wake(cx.waker()) => {}
+                    _ => shared.waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn returns_closure_output() {
+        let runtime = Runtime::new().unwrap();
+        let output = runtime.block_on(async { spawn_blocking(|| 6 * 7).await });
+        assert_eq!(output, Ok(42));
+    }
+
+    #[test]
+    fn fails_once_runtime_dropped() {
+        let runtime = Runtime::new().unwrap();
+        let handle = runtime.handle().spawn(async { 1 });
+        drop(runtime);
+        assert_eq!(poll(pin!(handle)), Poll::Ready(Err(JoinError(()))));
+    }
+
+    #[test]
+    fn queued_closure_fails_once_runtime_dropped() {
+        let runtime = crate::runtime::Builder::new()
+            .max_blocking_threads(1)
+            .build()
+            .unwrap();
+        let (tx, rx) = mpsc::channel::<()>();
+        let (started_tx, started) = mpsc::channel();
+        let (running, queued) = runtime.block_on(async {
+            (
+                spawn_blocking(move || {
+                    started_tx.send(()).unwrap();
+                    rx.recv().unwrap()
+                }),
+                spawn_blocking(|| 1),
+            )
+        });
+        // the pool thread is busy with the first closure.
+        started.recv().unwrap();
+        drop(runtime);
+        tx.send(()).unwrap();
+        let runtime = Runtime::new().unwrap();
+        assert_eq!(runtime.block_on(queued), Err(JoinError(())));
+        assert_eq!(runtime.block_on(running), Ok(()));
+    }
+
+    #[test]
+    fn handle_block_on_fails_once_runtime_dropped() {
+        let runtime = Runtime::new().unwrap();
+        let handle = runtime.handle();
+        drop(runtime);
+        let res = thread::spawn(move || handle.block_on(async { 1 }))
+            .join()
+            .unwrap();
+        assert_eq!(res, Err(JoinError(())));
+    }
+}
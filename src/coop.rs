@@ -0,0 +1,79 @@
+use std::cell::Cell;
+use std::task::{Context, Poll};
+
+// Number of leaf operations a task may complete in a single poll before it is
+// forced to yield back to the executor.
+const INITIAL_BUDGET: u8 = 128;
+
+thread_local! {
+    static CURRENT: Cell<Budget> = const { Cell::new(Budget::unconstrained()) };
+}
+
+#[derive(Clone, Copy)]
+struct Budget(Option<u8>);
+
+impl Budget {
+    const fn initial() -> Budget {
+        Budget(Some(INITIAL_BUDGET))
+    }
+
+    const fn unconstrained() -> Budget {
+        Budget(None)
+    }
+}
+
+/// Run `f` with a fresh budget, restoring the previous one when `f` returns.
+pub(crate) fn budget<R>(f: impl FnOnce() -> R) -> R {
+    struct ResetGuard {
+        prev: Budget,
+    }
+
+    impl Drop for ResetGuard {
+        fn drop(&mut self) {
+            CURRENT.with(|cell| cell.set(self.prev));
+        }
+    }
+
+    let prev = CURRENT.with(|cell| cell.replace(Budget::initial()));
+    let _guard = ResetGuard { prev };
+    f()
+}
+
+/// Consume one unit of the current budget.
+///
+/// Returns `Pending` and schedules the task to be polled again once the budget
+/// is spent. The returned guard gives the unit back unless `made_progress` is
+/// called, so an operation that ends up `Pending` is not charged.
+pub(crate) fn poll_proceed(cx: &mut Context<'_>) -> Poll<RestoreOnPending> {
+    CURRENT.with(|cell| {
+        let budget = cell.get();
+        match budget.0 {
+            Some(0) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Some(n) => {
+                cell.set(Budget(Some(n - 1)));
+                Poll::Ready(RestoreOnPending(Cell::new(budget)))
+            }
+            None => Poll::Ready(RestoreOnPending(Cell::new(budget))),
+        }
+    })
+}
+
+pub(crate) struct RestoreOnPending(Cell<Budget>);
+
+impl RestoreOnPending {
+    pub(crate) fn made_progress(&self) {
+        self.0.set(Budget::unconstrained());
+    }
+}
+
+impl Drop for RestoreOnPending {
+    fn drop(&mut self) {
+        let budget = self.0.get();
+        if budget.0.is_some() {
+            CURRENT.with(|cell| cell.set(budget));
+        }
+    }
+}
@@ -1,27 +1,37 @@
 use std::any::Any;
 use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
 use std::future::Future;
 use std::io;
 use std::mem;
+use std::os::unix::io::AsRawFd;
 use std::pin::Pin;
 use std::rc::Rc;
-use std::task::{Context, Poll, Waker};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll, Waker};
+use std::time::Duration;
 
 use io_uring::squeue::Entry;
-use io_uring::{cqueue, opcode, IoUring};
+use io_uring::{cqueue, opcode, types, IoUring};
 use scoped_tls::scoped_thread_local;
 use slab::Slab;
 
 use crate::buffer::{Buf, BufRing, Builder};
+use crate::coop;
+use crate::msg_ring::{self, Message, Ring, RuntimeHandle};
 
 mod op;
+mod unpark;
 
 pub(crate) use op::*;
+pub(crate) use unpark::Unpark;
 
 pub const BUF_BGID: u16 = 666;
 const DEFAULT_RING_ENTRIES: u16 = 128;
 const DEFAULT_BUF_CNT: u16 = 128;
 const DEFAULT_BUF_LEN: usize = 4096;
+// user_data of the read armed on the unpark eventfd.
+const UNPARK_KEY: u64 = u64::MAX - 1;
 
 scoped_thread_local!(static CURRENT: Driver);
 
@@ -41,6 +51,16 @@ struct Inner {
     buf_ring: BufRing,
     ring: IoUring,
     ops: Slab<Lifecycle>,
+    // keys of the ops in flight that hold back a paused clock, see
+    // `Op::submit_untracked`.
+    tracked: HashSet<usize>,
+    unpark: Arc<Unpark>,
+    unpark_buf: Box<u64>,
+    unpark_armed: bool,
+    ring_handle: Arc<Ring>,
+    // messages from other runtimes not dispatched yet.
+    messages: VecDeque<Message>,
+    msg_handler: Option<Box<dyn FnMut(Message)>>,
 }
 
 impl Inner {
@@ -51,10 +71,18 @@ impl Inner {
             .buf_cnt(DEFAULT_BUF_CNT)
             .buf_len(DEFAULT_BUF_LEN)
             .build()?;
+        let ring_handle = Arc::new(Ring::new(ring.as_raw_fd()));
         let mut inner = Inner {
             ring,
             ops: Slab::with_capacity(256),
+            tracked: HashSet::new(),
             buf_ring,
+            unpark: Arc::new(Unpark::new()?),
+            unpark_buf: Box::new(0),
+            unpark_armed: false,
+            ring_handle,
+            messages: VecDeque::new(),
+            msg_handler: None,
         };
         inner.register_buf_ring()?;
         Ok(inner)
@@ -76,33 +104,28 @@ impl Inner {
             match e.raw_os_error() {
                 Some(libc::EINVAL) => {
                     // using buf_ring requires kernel 5.19 or greater.
-                    return Err(io::Error::new(
-                            io::ErrorKind::Other, format!(
-                                "buf_ring.register returned {}, most likely indicating this kernel is not 5.19+", e),
-                            ));
+                    return Err(io::Error::other(format!(
+                        "buf_ring.register returned {}, most likely indicating this kernel is not 5.19+",
+                        e
+                    )));
                 }
                 Some(libc::EEXIST) => {
                     // Registering a duplicate bgid is not allowed. There is an `unregister`
                     // operations that can remove the first, but care must be taken that there
                     // are no outstanding operations that will still return a buffer from that
                     // one.
-                    return Err(io::Error::new(
-                            io::ErrorKind::Other,
-                            format!(
-                                "buf_ring.register returned `{}`, indicating the attempted buffer group id {} was already registered",
-                            e,
-                            self.buf_ring.bgid()),
-                        ));
+                    return Err(io::Error::other(format!(
+                        "buf_ring.register returned `{}`, indicating the attempted buffer group id {} was already registered",
+                        e,
+                        self.buf_ring.bgid()
+                    )));
                 }
                 _ => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        format!(
-                            "buf_ring.register returned `{}` for group id {}",
-                            e,
-                            self.buf_ring.bgid()
-                        ),
-                    ));
+                    return Err(io::Error::other(format!(
+                        "buf_ring.register returned `{}` for group id {}",
+                        e,
+                        self.buf_ring.bgid()
+                    )));
                 }
             }
         };
@@ -122,35 +145,115 @@ impl Inner {
     }
 
     fn wait(&mut self) -> io::Result<()> {
-        if let Err(e) = self.ring.submit_and_wait(1) {
+        self.arm_unpark()?;
+        self.submit_and_reap(1)?;
+        Ok(())
+    }
+
+    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<usize> {
+        self.arm_unpark()?;
+        let ts = types::Timespec::from(timeout);
+        let args = types::SubmitArgs::new().timespec(&ts);
+        match self.ring.submitter().submit_with_args(1, &args) {
+            Ok(_) => {}
+            Err(e) if e.raw_os_error() == Some(libc::ETIME) => {}
+            Err(e) if e.raw_os_error() == Some(libc::EBUSY) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+        Ok(self.reap())
+    }
+
+    fn arm_unpark(&mut self) -> io::Result<()> {
+        if self.unpark_armed {
+            return Ok(());
+        }
+        let sqe = opcode::Read::new(
+            types::Fd(self.unpark.as_raw_fd()),
+            self.unpark_buf.as_mut() as *mut u64 as *mut u8,
+            mem::size_of::<u64>() as u32,
+        )
+        .build()
+        .user_data(UNPARK_KEY);
+        self.submit(sqe)?;
+        self.unpark_armed = true;
+        Ok(())
+    }
+
+    fn poll(&mut self) -> io::Result<usize> {
+        // entries are submitted as soon as they are pushed, only enter the
+        // kernel if some are left behind.
+        if self.ring.submission().is_empty() {
+            return Ok(self.reap());
+        }
+        self.submit_and_reap(0)
+    }
+
+    fn submit_and_reap(&mut self, want: usize) -> io::Result<usize> {
+        if let Err(e) = self.ring.submit_and_wait(want) {
             if e.raw_os_error() == Some(libc::EBUSY) {
-                return Ok(());
+                return Ok(0);
             }
             if e.kind() == io::ErrorKind::Interrupted {
-                return Ok(());
+                return Ok(0);
             }
             return Err(e);
         }
+        Ok(self.reap())
+    }
 
+    // Dispatch the available completions, returns how many were reaped.
+    fn reap(&mut self) -> usize {
+        let mut reaped = 0;
         let mut cq = self.ring.completion();
         cq.sync();
         for cqe in cq {
             if cqe.user_data() == u64::MAX {
                 continue;
             }
+            reaped += 1;
+            if cqe.user_data() == UNPARK_KEY {
+                self.unpark_armed = false;
+                continue;
+            }
+            if cqe.user_data() == msg_ring::DATA_KEY || cqe.user_data() == msg_ring::FD_KEY {
+                let msg = Message::from_cqe(
+                    &self.ring_handle,
+                    cqe.user_data(),
+                    cqe.result(),
+                    cqe.flags(),
+                );
+                // dropped past the limit, with the fd they carry.
+                if self.msg_handler.is_some() || self.messages.len() < msg_ring::MAX_BUFFERED {
+                    self.messages.extend(msg);
+                }
+                continue;
+            }
             let index = cqe.user_data() as _;
+            if !cqueue::more(cqe.flags()) {
+                self.tracked.remove(&index);
+            }
             let op = &mut self.ops[index];
             if op.complete(cqe, &self.buf_ring) {
                 self.ops.remove(index);
             }
         }
-        Ok(())
+        reaped
     }
 
-    fn submit_op<T>(&mut self, driver: Driver, op: T, sqe: Entry) -> io::Result<Op<T>> {
+    fn submit_op<T>(
+        &mut self,
+        driver: Driver,
+        op: T,
+        sqe: Entry,
+        tracked: bool,
+    ) -> io::Result<Op<T>> {
         let key = self.ops.insert(Lifecycle::Submitted);
         let sqe = sqe.user_data(key as u64);
         self.submit(sqe)?;
+        if tracked {
+            self.tracked.insert(key);
+        }
         Ok(Op {
             driver,
             op: Some(op),
@@ -159,6 +262,14 @@ impl Inner {
     }
 }
 
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // handles stop posting before the ring is closed, the fds of the
+        // messages left in the completion queue are closed with it.
+        self.ring_handle.close();
+    }
+}
+
 impl Driver {
     pub(crate) fn new() -> io::Result<Driver> {
         Ok(Driver {
@@ -167,15 +278,95 @@ impl Driver {
     }
 
     pub(crate) fn wait(&self) -> io::Result<()> {
-        self.inner.borrow_mut().wait()
+        self.inner.borrow_mut().wait()?;
+        self.dispatch_messages();
+        Ok(())
+    }
+
+    /// Reap the available completions without blocking, returns how many
+    /// were reaped.
+    pub(crate) fn poll(&self) -> io::Result<usize> {
+        let n = self.inner.borrow_mut().poll()?;
+        self.dispatch_messages();
+        Ok(n)
+    }
+
+    /// Wait for a completion for at most `timeout`, returns how many were
+    /// reaped.
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> io::Result<usize> {
+        let n = self.inner.borrow_mut().wait_timeout(timeout)?;
+        self.dispatch_messages();
+        Ok(n)
+    }
+
+    /// Whether an op that holds back a paused clock is in flight.
+    pub(crate) fn has_tracked_ops(&self) -> bool {
+        !self.inner.borrow().tracked.is_empty()
+    }
+
+    pub(crate) fn unpark(&self) -> Arc<Unpark> {
+        self.inner.borrow().unpark.clone()
+    }
+
+    pub(crate) fn runtime_handle(&self) -> RuntimeHandle {
+        RuntimeHandle::new(self.inner.borrow().ring_handle.clone())
+    }
+
+    pub(crate) fn set_msg_handler(&self, handler: Box<dyn FnMut(Message)>) {
+        self.inner.borrow_mut().msg_handler = Some(handler);
+        self.dispatch_messages();
     }
 
     pub(crate) fn with<T>(&self, f: impl FnOnce() -> T) -> T {
         CURRENT.set(self, f)
     }
 
-    pub(crate) fn submit<T>(&self, op: T, sqe: Entry) -> io::Result<Op<T>> {
-        self.inner.borrow_mut().submit_op(self.clone(), op, sqe)
+    pub(crate) fn submit<T>(&self, op: T, sqe: Entry, tracked: bool) -> io::Result<Op<T>> {
+        self.inner
+            .borrow_mut()
+            .submit_op(self.clone(), op, sqe, tracked)
+    }
+
+    // Hand the buffered messages to the handler, which is taken out of the
+    // driver while called so it can submit ops or be replaced.
+    fn dispatch_messages(&self) {
+        let mut handler = {
+            let mut inner = self.inner.borrow_mut();
+            if inner.messages.is_empty() {
+                return;
+            }
+            match inner.msg_handler.take() {
+                Some(handler) => handler,
+                None => return,
+            }
+        };
+        loop {
+            let msg = {
+                let mut inner = self.inner.borrow_mut();
+                if inner.msg_handler.is_some() {
+                    // replaced by the handler itself.
+                    return;
+                }
+                match inner.messages.pop_front() {
+                    Some(msg) => msg,
+                    None => break,
+                }
+            };
+            handler(msg);
+        }
+        let mut inner = self.inner.borrow_mut();
+        if inner.msg_handler.is_none() {
+            inner.msg_handler = Some(handler);
+        }
+    }
+}
+
+/// Run `f` with the driver of the current runtime, if any.
+pub(crate) fn with_current<T>(f: impl FnOnce(&Driver) -> T) -> Option<T> {
+    if CURRENT.is_set() {
+        Some(CURRENT.with(f))
+    } else {
+        None
     }
 }
 
@@ -188,8 +379,8 @@ enum Lifecycle {
     Completed(CqeResult),
     /// The operations list.
     CompletionList(Vec<CqeResult>),
-    /// Ignored
-    Ignored(Box<dyn Any>),
+    /// Ignored, the op data is kept alive until the kernel is done with it.
+    Ignored(#[allow(dead_code)] Box<dyn Any>),
 }
 
 impl Lifecycle {
@@ -256,21 +447,52 @@ impl<T> Op<T> {
         self.op.as_mut().unwrap()
     }
 
+    pub(crate) fn is_completed(&self) -> bool {
+        let inner = self.driver.inner.borrow();
+        matches!(inner.ops.get(self.key), Some(Lifecycle::Completed(..)))
+    }
+
     pub(crate) fn submit(op: T, entry: Entry) -> io::Result<Op<T>> {
-        CURRENT.with(|driver| driver.submit(op, entry))
+        CURRENT.with(|driver| driver.submit(op, entry, true))
     }
 
-    pub(crate) fn reset(&self, waker: Waker) {
-        let mut inner = self.driver.inner.borrow_mut();
-        if let Some(lifecycle) = inner.ops.get_mut(self.key) {
-            *lifecycle = Lifecycle::Waiting(waker);
-        }
+    /// Submit an op that does not hold back a paused clock: a timer, or a
+    /// multishot op waiting for events for as long as it lives.
+    pub(crate) fn submit_untracked(op: T, entry: Entry) -> io::Result<Op<T>> {
+        CURRENT.with(|driver| driver.submit(op, entry, false))
     }
 
     fn poll2(&mut self, cx: &mut Context) -> Poll<T::Output>
     where
         T: Completable,
     {
+        match ready!(self.poll_inner(cx)) {
+            Polled::Done(output) => Poll::Ready(output),
+            Polled::Updated => {
+                // because we update internal state, wake and rerun the task.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Poll a multishot op, returns `Ready(None)` once some of its updates
+    /// were applied, so the caller can act on them without being polled again.
+    pub(crate) fn poll_multishot(&mut self, cx: &mut Context) -> Poll<Option<T::Output>>
+    where
+        T: Completable,
+    {
+        match ready!(self.poll_inner(cx)) {
+            Polled::Done(output) => Poll::Ready(Some(output)),
+            Polled::Updated => Poll::Ready(None),
+        }
+    }
+
+    fn poll_inner(&mut self, cx: &mut Context) -> Poll<Polled<T::Output>>
+    where
+        T: Completable,
+    {
+        let coop = ready!(coop::poll_proceed(cx));
         let mut inner = self.driver.inner.borrow_mut();
         let lifecycle = inner.ops.get_mut(self.key).expect("invalid key");
 
@@ -289,7 +511,8 @@ impl<T> Op<T> {
             }
             Lifecycle::Completed(cqe) => {
                 inner.ops.remove(self.key);
-                Poll::Ready(self.op.take().unwrap().complete(cqe))
+                coop.made_progress();
+                Poll::Ready(Polled::Done(self.op.take().unwrap().complete(cqe)))
             }
             Lifecycle::CompletionList(list) => {
                 let data = self.op.as_mut().unwrap();
@@ -304,10 +527,6 @@ impl<T> Op<T> {
                         break;
                     }
                 }
-                if updated {
-                    // because we update internal state, wake and rerun the task.
-                    cx.waker().wake_by_ref();
-                }
                 match status {
                     None => {
                         *lifecycle = Lifecycle::Waiting(cx.waker().clone());
@@ -316,6 +535,10 @@ impl<T> Op<T> {
                         *lifecycle = Lifecycle::Completed(cqe);
                     }
                 }
+                if updated {
+                    coop.made_progress();
+                    return Poll::Ready(Polled::Updated);
+                }
                 Poll::Pending
             }
             Lifecycle::Ignored(..) => unreachable!(),
@@ -323,6 +546,13 @@ impl<T> Op<T> {
     }
 }
 
+enum Polled<T> {
+    /// Updates of a multishot op were applied.
+    Updated,
+    /// The op completed with its output.
+    Done(T),
+}
+
 impl<T> Drop for Op<T> {
     fn drop(&mut self) {
         let mut inner = self.driver.inner.borrow_mut();
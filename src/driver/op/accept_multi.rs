@@ -21,7 +21,7 @@ impl Op<AcceptMulti> {
         let entry = opcode::AcceptMulti::new(types::Fd(fd))
             .flags(libc::SOCK_CLOEXEC)
             .build();
-        Op::submit(
+        Op::submit_untracked(
             AcceptMulti {
                 results: VecDeque::new(),
             },
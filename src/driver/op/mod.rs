@@ -1,7 +1,10 @@
 mod accept;
 mod accept_multi;
 mod connect;
+mod msg_ring;
+mod poll_add;
 mod read;
+mod readv;
 mod recv;
 mod recv_multi;
 mod recvmsg;
@@ -9,17 +12,24 @@ mod send;
 mod sendmsg;
 mod shutdown;
 mod timeout;
+mod timeout_multi;
 mod write;
+mod writev;
 
 pub(crate) use accept::Accept;
 pub(crate) use accept_multi::AcceptMulti;
 pub(crate) use connect::Connect;
+pub(crate) use msg_ring::MsgRing;
+pub(crate) use poll_add::PollAdd;
 pub(crate) use read::Read;
+pub(crate) use readv::Readv;
 pub(crate) use recv::Recv;
 pub(crate) use recv_multi::RecvMulti;
-pub(crate) use recvmsg::RecvMsg;
+pub(crate) use recvmsg::{check_ctrunc, Ancillary, RecvMsg};
 pub(crate) use send::Send;
 pub(crate) use sendmsg::SendMsg;
 pub(crate) use shutdown::Shutdown;
 pub(crate) use timeout::Timeout;
+pub(crate) use timeout_multi::TimeoutMulti;
 pub(crate) use write::Write;
+pub(crate) use writev::Writev;
@@ -0,0 +1,47 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+
+use io_uring::{opcode, types};
+
+use crate::driver::{Completable, CqeResult, Op};
+use crate::msg_ring::Ring;
+
+pub(crate) struct MsgRing {
+    ring: Arc<Ring>,
+    // token of the fd carried by the message, closed if it is not posted.
+    token: Option<i32>,
+}
+
+impl Op<MsgRing> {
+    /// Post a completion with `key` as user data, `result` and `flags` to the
+    /// target ring, whose fd must stay open until submitted.
+    ///
+    /// `flags` are passed with `IORING_MSG_RING_FLAGS_PASS`, Linux 6.3 fails
+    /// them with `EINVAL` before.
+    pub(crate) fn msg_ring(
+        ring: Arc<Ring>,
+        ring_fd: RawFd,
+        key: u64,
+        result: i32,
+        flags: Option<u32>,
+        token: Option<i32>,
+    ) -> io::Result<Op<MsgRing>> {
+        let entry = opcode::MsgRingData::new(types::Fd(ring_fd), result, key, flags).build();
+        Op::submit(MsgRing { ring, token }, entry)
+    }
+}
+
+impl Completable for MsgRing {
+    type Output = io::Result<()>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        if let Err(err) = cqe.result {
+            if let Some(token) = self.token {
+                drop(self.ring.take_fd(token));
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+}
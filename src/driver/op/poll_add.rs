@@ -0,0 +1,24 @@
+use std::io;
+use std::os::unix::io::RawFd;
+
+use io_uring::{opcode, types};
+
+use crate::driver::{Completable, CqeResult, Op};
+
+pub(crate) struct PollAdd;
+
+impl Op<PollAdd> {
+    /// Completes once `fd` is ready for the `poll(2)` events in `flags`.
+    pub(crate) fn poll_add(fd: RawFd, flags: u32) -> io::Result<Op<PollAdd>> {
+        let entry = opcode::PollAdd::new(types::Fd(fd), flags).build();
+        Op::submit(PollAdd, entry)
+    }
+}
+
+impl Completable for PollAdd {
+    type Output = io::Result<u32>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        cqe.result
+    }
+}
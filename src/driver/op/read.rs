@@ -10,7 +10,9 @@ pub(crate) struct Read;
 
 impl Op<Read> {
     pub(crate) fn read(fd: RawFd, len: u32) -> io::Result<Op<Read>> {
+        // an offset of -1 reads at the file position, for regular files.
         let entry = opcode::Read::new(types::Fd(fd), ptr::null_mut(), len)
+            .offset(u64::MAX)
             .buf_group(BUF_BGID)
             .build()
             .flags(squeue::Flags::BUFFER_SELECT);
@@ -25,7 +27,7 @@ impl Completable for Read {
         let _ = cqe.result?;
         match cqe.buf {
             Some(buf) => Ok(buf),
-            None => Err(io::Error::new(io::ErrorKind::Other, "buf not found")),
+            None => Err(io::Error::other("buf not found")),
         }
     }
 }
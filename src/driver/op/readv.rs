@@ -0,0 +1,52 @@
+use std::io::{self, IoSliceMut};
+use std::os::unix::io::RawFd;
+
+use io_uring::{opcode, types};
+
+use super::writev::IOV_MAX;
+use crate::driver::{Completable, CqeResult, Op};
+
+#[allow(dead_code)]
+pub(crate) struct Readv {
+    buf: Vec<u8>,
+    iovecs: Vec<libc::iovec>,
+}
+
+impl Op<Readv> {
+    /// Reads with one `readv` into a buffer laid out like `bufs`, the data
+    /// is copied out once the op completes.
+    pub(crate) fn readv(fd: RawFd, bufs: &[IoSliceMut<'_>]) -> io::Result<Op<Readv>> {
+        let bufs = &bufs[..bufs.len().min(IOV_MAX)];
+        let len: usize = bufs.iter().map(|slice| slice.len()).sum();
+        let mut buf: Vec<u8> = Vec::with_capacity(len);
+        let mut iovecs = Vec::with_capacity(bufs.len());
+        let mut pos = 0;
+        for slice in bufs {
+            iovecs.push(libc::iovec {
+                iov_base: unsafe { buf.as_mut_ptr().add(pos) }.cast(),
+                iov_len: slice.len(),
+            });
+            pos += slice.len();
+        }
+        let readv = Readv { buf, iovecs };
+        // an offset of -1 reads at the file position, for regular files.
+        let entry = opcode::Readv::new(
+            types::Fd(fd),
+            readv.iovecs.as_ptr(),
+            readv.iovecs.len() as u32,
+        )
+        .offset(u64::MAX)
+        .build();
+        Op::submit(readv, entry)
+    }
+}
+
+impl Completable for Readv {
+    type Output = io::Result<Vec<u8>>;
+
+    fn complete(mut self, cqe: CqeResult) -> Self::Output {
+        let n = cqe.result? as usize;
+        unsafe { self.buf.set_len(n) };
+        Ok(self.buf)
+    }
+}
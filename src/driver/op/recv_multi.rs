@@ -19,7 +19,7 @@ impl RecvMulti {
 impl Op<RecvMulti> {
     pub(crate) fn recv_multi(fd: RawFd) -> io::Result<Op<RecvMulti>> {
         let entry = opcode::RecvMulti::new(types::Fd(fd), BUF_BGID).build();
-        Op::submit(
+        Op::submit_untracked(
             RecvMulti {
                 results: VecDeque::new(),
             },
@@ -35,14 +35,14 @@ impl Completable for RecvMulti {
         let _ = cqe.result?;
         match cqe.buf {
             Some(buf) => Ok(buf),
-            None => Err(io::Error::new(io::ErrorKind::Other, "buf not found")),
+            None => Err(io::Error::other("buf not found")),
         }
     }
 
     fn update(&mut self, cqe: CqeResult) {
         let buf = cqe.result.and_then(|_| match cqe.buf {
             Some(buf) => Ok(buf),
-            None => Err(io::Error::new(io::ErrorKind::Other, "buf not found")),
+            None => Err(io::Error::other("buf not found")),
         });
         self.results.push_back(buf);
     }
@@ -1,53 +1,122 @@
 use std::io::{self, IoSliceMut};
-use std::net::SocketAddr;
-use std::os::unix::io::RawFd;
+use std::mem;
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
+use std::ptr;
 
 use io_uring::{opcode, types};
 use socket2::SockAddr;
 
 use crate::driver::{Completable, CqeResult, Op};
 
+// the most fds the kernel passes in one message.
+const SCM_MAX_FD: u32 = 253;
+
+/// The control messages received along with the data.
+#[derive(Default)]
+pub(crate) struct Ancillary {
+    /// `SCM_RIGHTS`
+    pub(crate) fds: Vec<OwnedFd>,
+    /// `SCM_CREDENTIALS`, sent with each message once `SO_PASSCRED` is set.
+    pub(crate) cred: Option<libc::ucred>,
+}
+
+/// Fails if the kernel truncated the control messages, closing the fds passed
+/// along.
+pub(crate) fn check_ctrunc(msg_flags: libc::c_int) -> io::Result<()> {
+    if msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "control message truncated, passed fds were lost",
+        ));
+    }
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub(crate) struct RecvMsg {
     socket_addr: Box<SockAddr>,
     io_slices: Vec<IoSliceMut<'static>>,
     buf: Vec<u8>,
+    // u64 keeps the control messages aligned.
+    control: Vec<u64>,
     msghdr: Box<libc::msghdr>,
 }
 
 impl Op<RecvMsg> {
-    pub(crate) fn recvmsg(fd: RawFd, len: usize) -> io::Result<Op<RecvMsg>> {
+    /// Receives up to `len` bytes, and the control messages along if
+    /// `ancillary`. Received fds are opened with `O_CLOEXEC`.
+    pub(crate) fn recvmsg(fd: RawFd, len: usize, ancillary: bool) -> io::Result<Op<RecvMsg>> {
         let mut buf = Vec::with_capacity(len);
         let mut io_slices = vec![IoSliceMut::new(unsafe {
             std::slice::from_raw_parts_mut(buf.as_mut_ptr(), len)
         })];
         let socket_addr = Box::new(unsafe { SockAddr::try_init(|_, _| Ok(()))?.1 });
-        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { std::mem::zeroed() });
+        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { mem::zeroed() });
         msghdr.msg_iov = io_slices.as_mut_ptr().cast();
         msghdr.msg_iovlen = io_slices.len() as _;
         msghdr.msg_name = socket_addr.as_ptr() as *mut libc::c_void;
         msghdr.msg_namelen = socket_addr.len();
+        let mut control = Vec::new();
+        if ancillary {
+            let space = unsafe {
+                libc::CMSG_SPACE(SCM_MAX_FD * mem::size_of::<RawFd>() as u32)
+                    + libc::CMSG_SPACE(mem::size_of::<libc::ucred>() as u32)
+            } as usize;
+            control = vec![0u64; space.div_ceil(mem::size_of::<u64>())];
+            msghdr.msg_control = control.as_mut_ptr().cast();
+            msghdr.msg_controllen = space as _;
+        }
         let mut recv_msg = RecvMsg {
             socket_addr,
             buf,
+            control,
             msghdr,
             io_slices,
         };
-        let entry = opcode::RecvMsg::new(types::Fd(fd), recv_msg.msghdr.as_mut() as *mut _).build();
+        let entry = opcode::RecvMsg::new(types::Fd(fd), recv_msg.msghdr.as_mut() as *mut _)
+            .flags(libc::MSG_CMSG_CLOEXEC as u32)
+            .build();
         Op::submit(recv_msg, entry)
     }
 }
 
 impl Completable for RecvMsg {
-    type Output = io::Result<(Vec<u8>, SocketAddr)>;
+    /// The data, where it came from and the `msg_flags` set by the kernel,
+    /// with `MSG_TRUNC` if the message was truncated.
+    ///
+    /// It does not fail on `MSG_CTRUNC`, the fds passed along are lost but only
+    /// a receiver asking for them can tell.
+    type Output = io::Result<(Vec<u8>, SockAddr, Ancillary, libc::c_int)>;
 
     fn complete(mut self, cqe: CqeResult) -> Self::Output {
         let n = cqe.result? as usize;
         unsafe { self.buf.set_len(n) };
-        let socket_addr = self
-            .socket_addr
-            .as_socket()
-            .ok_or(io::ErrorKind::InvalidInput)?;
-        Ok((self.buf, socket_addr))
+        // the kernel wrote the actual length of the address to the msghdr.
+        unsafe { self.socket_addr.set_length(self.msghdr.msg_namelen) };
+        let mut ancillary = Ancillary::default();
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(self.msghdr.as_ref());
+            while !cmsg.is_null() {
+                let data = libc::CMSG_DATA(cmsg);
+                let len = (*cmsg).cmsg_len as usize - (data as usize - cmsg as usize);
+                match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                    (libc::SOL_SOCKET, libc::SCM_RIGHTS) => {
+                        for i in 0..len / mem::size_of::<RawFd>() {
+                            let fd = ptr::read_unaligned(data.cast::<RawFd>().add(i));
+                            ancillary.fds.push(OwnedFd::from_raw_fd(fd));
+                        }
+                    }
+                    (libc::SOL_SOCKET, libc::SCM_CREDENTIALS)
+                        if len >= mem::size_of::<libc::ucred>() =>
+                    {
+                        ancillary.cred = Some(ptr::read_unaligned(data.cast()));
+                    }
+                    _ => {}
+                }
+                cmsg = libc::CMSG_NXTHDR(self.msghdr.as_ref(), cmsg);
+            }
+        }
+        let msg_flags = self.msghdr.msg_flags;
+        Ok((self.buf, *self.socket_addr, ancillary, msg_flags))
     }
 }
@@ -1,42 +1,75 @@
-use std::io::{self, IoSliceMut};
-use std::net::SocketAddr;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::mem;
 use std::os::unix::io::RawFd;
+use std::ptr;
 
 use io_uring::{opcode, types};
 use socket2::SockAddr;
 
+use super::writev::IOV_MAX;
 use crate::driver::{Completable, CqeResult, Op};
 
 #[allow(dead_code)]
 pub(crate) struct SendMsg {
-    socket_addr: Box<SockAddr>,
+    socket_addr: Option<Box<SockAddr>>,
     buf: Vec<u8>,
     io_slices: Vec<IoSliceMut<'static>>,
+    // u64 keeps the control messages aligned.
+    control: Vec<u64>,
     msghdr: Box<libc::msghdr>,
 }
 
 impl Op<SendMsg> {
+    /// Sends `bufs` as one message to `socket_addr`, or to the peer if `None`,
+    /// passing `fds` along in a `SCM_RIGHTS` control message.
     pub(crate) fn sendmsg(
         fd: RawFd,
-        buf: &[u8],
-        socket_addr: SocketAddr,
+        bufs: &[IoSlice<'_>],
+        socket_addr: Option<SockAddr>,
+        fds: &[RawFd],
     ) -> io::Result<Op<SendMsg>> {
-        let len = buf.len();
-        let mut buf = buf.to_vec();
-        let mut io_slices = vec![IoSliceMut::new(unsafe {
-            std::slice::from_raw_parts_mut(buf.as_mut_ptr(), len)
-        })];
-        let socket_addr = Box::new(SockAddr::from(socket_addr));
-        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { std::mem::zeroed() });
+        let bufs = &bufs[..bufs.len().min(IOV_MAX)];
+        let mut buf = Vec::with_capacity(bufs.iter().map(|slice| slice.len()).sum());
+        for slice in bufs {
+            buf.extend_from_slice(slice);
+        }
+        let mut io_slices = Vec::with_capacity(bufs.len());
+        let mut pos = 0;
+        for slice in bufs {
+            io_slices.push(IoSliceMut::new(unsafe {
+                std::slice::from_raw_parts_mut(buf.as_mut_ptr().add(pos), slice.len())
+            }));
+            pos += slice.len();
+        }
+        let socket_addr = socket_addr.map(Box::new);
+        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { mem::zeroed() });
         msghdr.msg_iov = io_slices.as_mut_ptr().cast();
         msghdr.msg_iovlen = io_slices.len() as _;
-        msghdr.msg_name = socket_addr.as_ptr() as *mut libc::c_void;
-        msghdr.msg_namelen = socket_addr.len();
+        if let Some(socket_addr) = &socket_addr {
+            msghdr.msg_name = socket_addr.as_ptr() as *mut libc::c_void;
+            msghdr.msg_namelen = socket_addr.len();
+        }
+        let mut control = Vec::new();
+        if !fds.is_empty() {
+            let fds_len = mem::size_of_val(fds) as u32;
+            let space = unsafe { libc::CMSG_SPACE(fds_len) } as usize;
+            control = vec![0u64; space.div_ceil(mem::size_of::<u64>())];
+            msghdr.msg_control = control.as_mut_ptr().cast();
+            msghdr.msg_controllen = space as _;
+            unsafe {
+                let cmsg = libc::CMSG_FIRSTHDR(msghdr.as_ref());
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len) as _;
+                ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg).cast(), fds.len());
+            }
+        }
         let mut send_msg = SendMsg {
             buf,
             msghdr,
             socket_addr,
             io_slices,
+            control,
         };
         let entry = opcode::SendMsg::new(types::Fd(fd), send_msg.msghdr.as_mut() as *mut _).build();
         Op::submit(send_msg, entry)
@@ -1,4 +1,5 @@
 use std::io;
+use std::time::Duration;
 
 use io_uring::{opcode, types};
 
@@ -9,12 +10,22 @@ pub(crate) struct Timeout {
 }
 
 impl Op<Timeout> {
-    pub(crate) fn timeout(sec: u64, nsec: u32) -> io::Result<Op<Timeout>> {
+    /// A timeout firing once the kernel clock selected by `clock` reaches
+    /// `deadline`, measured from the epoch of that clock.
+    ///
+    /// `clock` is empty for `CLOCK_MONOTONIC`, or one of `TimeoutFlags::REALTIME`
+    /// and `TimeoutFlags::BOOTTIME`.
+    pub(crate) fn timeout_at(
+        deadline: Duration,
+        clock: types::TimeoutFlags,
+    ) -> io::Result<Op<Timeout>> {
         let timeout = Timeout {
-            spec: types::Timespec::new().sec(sec).nsec(nsec),
+            spec: types::Timespec::from(deadline),
         };
-        let entry = opcode::Timeout::new(&timeout.spec as *const _).build();
-        Op::submit(timeout, entry)
+        let entry = opcode::Timeout::new(&timeout.spec as *const _)
+            .flags(types::TimeoutFlags::ABS | clock)
+            .build();
+        Op::submit_untracked(timeout, entry)
     }
 }
 
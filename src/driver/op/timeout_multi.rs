@@ -0,0 +1,42 @@
+use std::io;
+use std::time::Duration;
+
+use io_uring::{opcode, types};
+
+use crate::driver::{Completable, CqeResult, Op};
+
+// `IORING_TIMEOUT_MULTISHOT`, io-uring 0.6 predates it and its
+// `types::TimeoutFlags` has no such flag.
+const TIMEOUT_MULTISHOT: u32 = 1 << 6;
+
+/// A timeout firing every `period` until it is cancelled, requires kernel 6.4+.
+pub(crate) struct TimeoutMulti {
+    spec: types::Timespec,
+}
+
+impl Op<TimeoutMulti> {
+    pub(crate) fn timeout_multi(period: Duration) -> io::Result<Op<TimeoutMulti>> {
+        let timeout = TimeoutMulti {
+            spec: types::Timespec::from(period),
+        };
+        // Safety: the kernel checks the flags, unknown ones are rejected with `EINVAL`.
+        let flags = unsafe { types::TimeoutFlags::from_bits_unchecked(TIMEOUT_MULTISHOT) };
+        let entry = opcode::Timeout::new(&timeout.spec as *const _)
+            .flags(flags)
+            .build();
+        Op::submit_untracked(timeout, entry)
+    }
+}
+
+impl Completable for TimeoutMulti {
+    type Output = io::Result<()>;
+
+    // Each expiration is an update, the final completion only happens when the
+    // timeout is cancelled or rejected.
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        match cqe.result {
+            Err(err) => Err(err),
+            Ok(n) => Err(io::Error::other(format!("result {}", n))),
+        }
+    }
+}
@@ -13,8 +13,10 @@ impl Op<Write> {
     pub(crate) fn write(fd: RawFd, buf: &[u8]) -> io::Result<Op<Write>> {
         let buf = buf.to_vec();
         let write = Write { buf };
-        let entry =
-            opcode::Write::new(types::Fd(fd), write.buf.as_ptr(), write.buf.len() as u32).build();
+        // an offset of -1 writes at the file position, for regular files.
+        let entry = opcode::Write::new(types::Fd(fd), write.buf.as_ptr(), write.buf.len() as u32)
+            .offset(u64::MAX)
+            .build();
         Op::submit(write, entry)
     }
 }
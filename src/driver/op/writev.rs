@@ -0,0 +1,55 @@
+use std::io::{self, IoSlice};
+use std::os::unix::io::RawFd;
+
+use io_uring::{opcode, types};
+
+use crate::driver::{Completable, CqeResult, Op};
+
+// UIO_MAXIOV, the most slices the kernel takes in one call.
+pub(crate) const IOV_MAX: usize = 1024;
+
+#[allow(dead_code)]
+pub(crate) struct Writev {
+    buf: Vec<u8>,
+    iovecs: Vec<libc::iovec>,
+}
+
+impl Op<Writev> {
+    /// Writes the slices with one `writev`, past `IOV_MAX` of them only the
+    /// first are written.
+    pub(crate) fn writev(fd: RawFd, bufs: &[IoSlice<'_>]) -> io::Result<Op<Writev>> {
+        let bufs = &bufs[..bufs.len().min(IOV_MAX)];
+        let mut buf = Vec::with_capacity(bufs.iter().map(|slice| slice.len()).sum());
+        for slice in bufs {
+            buf.extend_from_slice(slice);
+        }
+        let mut iovecs = Vec::with_capacity(bufs.len());
+        let mut pos = 0;
+        for slice in bufs {
+            iovecs.push(libc::iovec {
+                iov_base: unsafe { buf.as_mut_ptr().add(pos) }.cast(),
+                iov_len: slice.len(),
+            });
+            pos += slice.len();
+        }
+        let writev = Writev { buf, iovecs };
+        // an offset of -1 writes at the file position, for regular files.
+        let entry = opcode::Writev::new(
+            types::Fd(fd),
+            writev.iovecs.as_ptr(),
+            writev.iovecs.len() as u32,
+        )
+        .offset(u64::MAX)
+        .build();
+        Op::submit(writev, entry)
+    }
+}
+
+impl Completable for Writev {
+    type Output = io::Result<usize>;
+
+    fn complete(self, cqe: CqeResult) -> Self::Output {
+        let n = cqe.result? as usize;
+        Ok(n)
+    }
+}
@@ -0,0 +1,36 @@
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+/// Wakes up a driver blocked in `wait` from any thread.
+///
+/// The driver keeps a read armed on the eventfd, writing to it completes the
+/// read and makes `submit_and_wait` return.
+pub(crate) struct Unpark {
+    fd: OwnedFd,
+}
+
+impl Unpark {
+    pub(crate) fn new() -> io::Result<Unpark> {
+        // the fd must stay blocking, io_uring fails reads on a O_NONBLOCK fd
+        // with EAGAIN instead of waiting for them.
+        let fd = syscall!(eventfd(0, libc::EFD_CLOEXEC))?;
+        Ok(Unpark {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    pub(crate) fn unpark(&self) {
+        let buf = 1u64.to_ne_bytes();
+        let _ = syscall!(write(
+            self.fd.as_raw_fd(),
+            buf.as_ptr() as *const libc::c_void,
+            buf.len(),
+        ));
+    }
+}
+
+impl AsRawFd for Unpark {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
@@ -0,0 +1,182 @@
+use std::future::Future;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use crate::buffer::Buf;
+use crate::coop;
+use crate::driver::{self, Op};
+
+const DEFAULT_BUFFER_SIZE: u32 = 4096;
+
+/// Reads and writes a pipe, a tty or a regular file through the ring.
+///
+/// The fd may be in non-blocking mode, which io_uring honors by failing with
+/// `EAGAIN`: the fd is then polled before the operation is retried.
+pub(crate) struct FdIo<F> {
+    fd: F,
+    buf: Option<Buf>,
+    pos: usize,
+    read: ReadState,
+    write: WriteState,
+}
+
+enum ReadState {
+    Idle,
+    Reading(Op<driver::Read>),
+    Polling(Op<driver::PollAdd>),
+}
+
+enum WriteState {
+    Idle,
+    Writing(Op<driver::Write>),
+    Polling(Op<driver::PollAdd>),
+}
+
+impl<F: AsRawFd> FdIo<F> {
+    pub(crate) fn new(fd: F) -> FdIo<F> {
+        FdIo {
+            fd,
+            buf: None,
+            pos: 0,
+            read: ReadState::Idle,
+            write: WriteState::Idle,
+        }
+    }
+
+    pub(crate) fn poll_read(
+        &mut self,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let src = ready!(self.poll_fill_buf(cx))?;
+        let n = buf.len().min(src.len());
+        buf[..n].copy_from_slice(&src[..n]);
+        self.consume(n);
+        Poll::Ready(Ok(n))
+    }
+
+    pub(crate) fn poll_fill_buf(&mut self, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        let fd = self.fd.as_raw_fd();
+        loop {
+            match &mut self.read {
+                ReadState::Idle => {
+                    if self.buf.as_ref().is_some_and(|buf| self.pos < buf.len()) {
+                        // buffered data does not hit the driver, charge the budget here.
+                        ready!(coop::poll_proceed(cx)).made_progress();
+                        return Poll::Ready(Ok(&self.buf.as_ref().unwrap()[self.pos..]));
+                    }
+                    self.pos = 0;
+                    self.buf = None;
+                    self.read = ReadState::Reading(Op::read(fd, DEFAULT_BUFFER_SIZE)?);
+                }
+                ReadState::Reading(op) => match ready!(Pin::new(op).poll(cx)) {
+                    Ok(buf) => {
+                        self.read = ReadState::Idle;
+                        // if length of buf is zero, means EOF.
+                        if buf.is_empty() {
+                            return Poll::Ready(Ok(&[]));
+                        }
+                        self.buf = Some(buf);
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        self.read = ReadState::Polling(poll(fd, libc::POLLIN)?);
+                    }
+                    Err(err) => {
+                        self.read = ReadState::Idle;
+                        return Poll::Ready(Err(err));
+                    }
+                },
+                ReadState::Polling(op) => {
+                    let res = ready!(Pin::new(op).poll(cx));
+                    self.read = ReadState::Idle;
+                    res?;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+
+    pub(crate) fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let fd = self.fd.as_raw_fd();
+        loop {
+            match &mut self.write {
+                WriteState::Idle => {
+                    self.write = WriteState::Writing(Op::write(fd, buf)?);
+                }
+                WriteState::Writing(op) => match ready!(Pin::new(op).poll(cx)) {
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        self.write = WriteState::Polling(poll(fd, libc::POLLOUT)?);
+                    }
+                    res => {
+                        self.write = WriteState::Idle;
+                        return Poll::Ready(res);
+                    }
+                },
+                WriteState::Polling(op) => {
+                    let res = ready!(Pin::new(op).poll(cx));
+                    self.write = WriteState::Idle;
+                    res?;
+                }
+            }
+        }
+    }
+}
+
+impl<F: AsRawFd> AsRawFd for FdIo<F> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+fn poll(fd: RawFd, events: libc::c_short) -> io::Result<Op<driver::PollAdd>> {
+    Op::poll_add(fd, events as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::future::poll_fn;
+    use std::io::{Seek, SeekFrom, Write};
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn reads_regular_file_at_its_position() {
+        let path = std::env::temp_dir().join(format!("slings-fd-io-{}", std::process::id()));
+        let data: Vec<u8> = (0..3 * DEFAULT_BUFFER_SIZE + 100)
+            .map(|i| i as u8)
+            .collect();
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        file.write_all(&data).unwrap();
+        file.seek(SeekFrom::Start(10)).unwrap();
+
+        let rt = Runtime::new().unwrap();
+        let received = rt.block_on(async {
+            let mut io = FdIo::new(file);
+            let mut received = Vec::new();
+            loop {
+                let mut buf = [0; 1000];
+                let n = poll_fn(|cx| io.poll_read(cx, &mut buf)).await.unwrap();
+                if n == 0 {
+                    break received;
+                }
+                received.extend_from_slice(&buf[..n]);
+            }
+        });
+        // each read continues from the file position, rather than offset 0.
+        assert_eq!(received, data[10..]);
+    }
+}
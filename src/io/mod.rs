@@ -0,0 +1,5 @@
+mod fd;
+mod stdio;
+
+pub(crate) use fd::FdIo;
+pub use stdio::{stderr, stdin, stdout, Stderr, Stdin, Stdout};
@@ -0,0 +1,130 @@
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+use super::FdIo;
+
+/// The standard input of the process, read through the ring.
+///
+/// Each handle buffers what it reads, data buffered by a dropped handle is
+/// lost.
+pub struct Stdin {
+    inner: FdIo<RawFd>,
+}
+
+/// The standard output of the process, written through the ring.
+///
+/// It is not buffered and does not share the buffer of `std::io::stdout`.
+pub struct Stdout {
+    inner: FdIo<RawFd>,
+}
+
+/// The standard error of the process, written through the ring.
+pub struct Stderr {
+    inner: FdIo<RawFd>,
+}
+
+pub fn stdin() -> Stdin {
+    Stdin {
+        inner: FdIo::new(libc::STDIN_FILENO),
+    }
+}
+
+pub fn stdout() -> Stdout {
+    Stdout {
+        inner: FdIo::new(libc::STDOUT_FILENO),
+    }
+}
+
+pub fn stderr() -> Stderr {
+    Stderr {
+        inner: FdIo::new(libc::STDERR_FILENO),
+    }
+}
+
+impl AsyncRead for Stdin {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_read(cx, buf)
+    }
+}
+
+impl AsyncBufRead for Stdin {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.get_mut().inner.poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().inner.consume(amt);
+    }
+}
+
+impl AsRawFd for Stdin {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+macro_rules! impl_write_stdio {
+    ($ty: ident) => {
+        impl AsyncWrite for $ty {
+            fn poll_write(
+                self: Pin<&mut Self>,
+                cx: &mut Context,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                self.get_mut().inner.poll_write(cx, buf)
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            // the fd of the process is left open.
+            fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        impl AsRawFd for $ty {
+            fn as_raw_fd(&self) -> RawFd {
+                self.inner.as_raw_fd()
+            }
+        }
+    };
+}
+
+impl_write_stdio!(Stdout);
+impl_write_stdio!(Stderr);
+
+#[cfg(test)]
+mod tests {
+    use futures_util::AsyncWriteExt;
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn wraps_the_process_fds() {
+        assert_eq!(stdin().as_raw_fd(), libc::STDIN_FILENO);
+        assert_eq!(stdout().as_raw_fd(), libc::STDOUT_FILENO);
+        assert_eq!(stderr().as_raw_fd(), libc::STDERR_FILENO);
+    }
+
+    #[test]
+    fn close_leaves_the_fd_open() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut err = stderr();
+            err.write_all(b"").await.unwrap();
+            err.close().await.unwrap();
+        });
+        assert!(syscall!(fcntl(libc::STDERR_FILENO, libc::F_GETFD)).is_ok());
+    }
+}
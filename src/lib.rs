@@ -10,12 +10,21 @@ macro_rules! syscall {
     }};
 }
 
+mod blocking;
 mod buffer;
+mod coop;
 pub(crate) mod driver;
+pub mod io;
 mod local_executor;
+pub mod msg_ring;
 pub mod net;
+pub mod pipe;
+pub mod process;
 pub mod runtime;
+pub mod signal;
 mod socket;
+pub mod sync;
+pub mod task;
 pub mod time;
 mod waker_fn;
 
@@ -23,14 +32,22 @@ use std::future::Future;
 
 pub use local_executor::spawn_local;
 use runtime::Runtime;
+use task::LocalSet;
 
 thread_local! {
     static RUNTIME: Runtime = Runtime::new().expect("new runtime fail");
 }
 
+/// Run `future` to completion on the runtime of the current thread.
+///
+/// Tasks spawned with `spawn_local` from within `future` are scoped to this
+/// call, the ones still pending are dropped when it returns.
 pub fn block_on<F>(future: F) -> F::Output
 where
     F: Future,
 {
-    RUNTIME.with(|runtime| runtime.block_on(future))
+    RUNTIME.with(|runtime| {
+        let local = LocalSet::new();
+        runtime.block_on(local.run_until(future))
+    })
 }
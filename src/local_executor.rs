@@ -1,37 +1,232 @@
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, ThreadId};
 
 use async_task::{Runnable, Task};
+use pin_project_lite::pin_project;
+use scoped_tls::scoped_thread_local;
+use slab::Slab;
+
+use crate::coop;
 
 const MAX_TASKS_PER_TICK: usize = 64;
 
-thread_local! {
-    static GLOBAL_QUEUE: RefCell<VecDeque<Runnable>> = RefCell::new(VecDeque::with_capacity(64));
+scoped_thread_local!(static CURRENT: Rc<Shared>);
+
+/// A set of `!Send` tasks that are executed on the same thread.
+///
+/// Tasks spawned into the set only make progress while the set is driven,
+/// either by `run_until` or by the `Runtime` owning it. When the set is
+/// dropped, every task that has not completed yet is dropped with it.
+pub struct LocalSet {
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    // wakers of the live tasks, used to cancel them when the set is dropped.
+    tasks: RefCell<Slab<Option<Waker>>>,
+    queue: Arc<Queue>,
 }
 
-pub(crate) fn tick() -> bool {
-    for _ in 0..MAX_TASKS_PER_TICK {
-        match next_task() {
-            Some(task) => {
-                task.run();
+// The run queue, shared with the schedule functions which may be called from
+// any thread when a task is woken up.
+struct Queue {
+    owner: ThreadId,
+    // `None` once the set is dropped.
+    runnables: Mutex<Option<VecDeque<Runnable>>>,
+    // waker of whoever is driving the set, woken when a task is scheduled.
+    waker: Mutex<Option<Waker>>,
+}
+
+impl LocalSet {
+    pub fn new() -> LocalSet {
+        LocalSet {
+            shared: Rc::new(Shared {
+                tasks: RefCell::new(Slab::new()),
+                queue: Arc::new(Queue {
+                    owner: thread::current().id(),
+                    runnables: Mutex::new(Some(VecDeque::with_capacity(64))),
+                    waker: Mutex::new(None),
+                }),
+            }),
+        }
+    }
+
+    /// Spawn a `!Send` future onto this set.
+    pub fn spawn_local<T: 'static>(&self, future: impl Future<Output = T> + 'static) -> Task<T> {
+        spawn(&self.shared, future)
+    }
+
+    /// Run `future` to completion, driving the tasks of this set meanwhile.
+    ///
+    /// `spawn_local` called from within `future` spawns onto this set.
+    pub async fn run_until<F: Future>(&self, future: F) -> F::Output {
+        RunUntil {
+            local_set: self,
+            future,
+        }
+        .await
+    }
+
+    pub(crate) fn enter<T>(&self, f: impl FnOnce() -> T) -> T {
+        CURRENT.set(&self.shared, f)
+    }
+
+    pub(crate) fn set_waker(&self, waker: &Waker) {
+        let mut current = self.shared.queue.waker.lock().unwrap();
+        match current.as_ref() {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *current = Some(waker.clone()),
+        }
+    }
+
+    /// Run at most `max_tasks` runnables, returns `true` if the queue was not drained.
+    pub(crate) fn tick(&self, max_tasks: usize) -> bool {
+        for _ in 0..max_tasks {
+            let task = self.shared.queue.pop();
+            match task {
+                Some(task) => {
+                    coop::budget(|| task.run());
+                }
+                None => return false,
             }
-            None => return false,
         }
+        true
     }
-    true
 }
 
-fn next_task() -> Option<Runnable> {
-    GLOBAL_QUEUE.with(|queue| queue.borrow_mut().pop_front())
+impl Default for LocalSet {
+    fn default() -> LocalSet {
+        LocalSet::new()
+    }
 }
 
-pub fn spawn_local<T: 'static>(future: impl Future<Output = T> + 'static) -> Task<T> {
-    let schedule = move |runnable| {
-        GLOBAL_QUEUE.with(|queue| queue.borrow_mut().push_back(runnable));
+impl Drop for LocalSet {
+    fn drop(&mut self) {
+        // runnables dropped here drop their futures, which may touch `tasks`.
+        let runnables = self.shared.queue.runnables.lock().unwrap().take();
+        drop(runnables);
+        self.shared.queue.waker.lock().unwrap().take();
+        // waking a task of a dropped set drops it instead of queueing it.
+        let tasks = self.shared.tasks.take();
+        for (_, waker) in tasks {
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Queue {
+    fn schedule(&self, runnable: Runnable) {
+        let mut runnables = self.runnables.lock().unwrap();
+        match runnables.as_mut() {
+            Some(runnables) => runnables.push_back(runnable),
+            None => {
+                drop(runnables);
+                // a local task may only be dropped by the thread that spawned it.
+                if thread::current().id() != self.owner {
+                    mem::forget(runnable);
+                }
+                return;
+            }
+        }
+        drop(runnables);
+        let waker = self.waker.lock().unwrap().clone();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn pop(&self) -> Option<Runnable> {
+        self.runnables
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(VecDeque::pop_front)
+    }
+}
+
+pin_project! {
+    struct RunUntil<'a, F> {
+        local_set: &'a LocalSet,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> Future for RunUntil<'_, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let local_set = *this.local_set;
+        local_set.set_waker(cx.waker());
+
+        let mut future = this.future;
+        local_set.enter(|| {
+            if let Poll::Ready(output) = coop::budget(|| future.as_mut().poll(cx)) {
+                local_set.shared.queue.waker.lock().unwrap().take();
+                return Poll::Ready(output);
+            }
+            if local_set.tick(MAX_TASKS_PER_TICK) {
+                cx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+    }
+}
+
+// Removes the task from the set once its future is completed or dropped.
+struct Entry {
+    shared: Weak<Shared>,
+    key: usize,
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.tasks.borrow_mut().try_remove(self.key);
+        }
+    }
+}
+
+fn spawn<T: 'static>(shared: &Rc<Shared>, future: impl Future<Output = T> + 'static) -> Task<T> {
+    let key = shared.tasks.borrow_mut().insert(None);
+    let entry = Entry {
+        shared: Rc::downgrade(shared),
+        key,
     };
+    let future = async move {
+        let _entry = entry;
+        future.await
+    };
+
+    let queue = shared.queue.clone();
+    let schedule = move |runnable| queue.schedule(runnable);
 
     let (runnable, task) = async_task::spawn_local(future, schedule);
+    if let Some(waker) = shared.tasks.borrow_mut().get_mut(key) {
+        *waker = Some(runnable.waker());
+    }
     runnable.schedule();
     task
 }
+
+/// Spawn a `!Send` future onto the current `LocalSet`.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime or `LocalSet::run_until`.
+pub fn spawn_local<T: 'static>(future: impl Future<Output = T> + 'static) -> Task<T> {
+    if !CURRENT.is_set() {
+        panic!("`spawn_local` called from outside of a `LocalSet` or runtime context");
+    }
+    CURRENT.with(|shared| spawn(shared, future))
+}
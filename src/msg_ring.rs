@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::io::{OwnedFd, RawFd};
+use std::sync::{Arc, Mutex};
+
+use crate::driver::{self, MsgRing, Op};
+use crate::sync::mpsc;
+
+// user_data of the completions posted by `send` and `send_owned_fd`.
+pub(crate) const DATA_KEY: u64 = u64::MAX - 2;
+pub(crate) const FD_KEY: u64 = u64::MAX - 3;
+
+/// Messages buffered until `on_message` or `channel` is called, the ones
+/// received past that are dropped.
+pub const MAX_BUFFERED: usize = 1024;
+
+/// A message received from another runtime.
+#[derive(Debug)]
+pub enum Message {
+    /// Sent by `RuntimeHandle::send`.
+    Data(u64),
+    /// Sent by `RuntimeHandle::send_owned_fd`, with the data attached to it.
+    Fd(OwnedFd, u32),
+}
+
+impl Message {
+    // The message posted as a completion, `None` for an fd already closed.
+    pub(crate) fn from_cqe(ring: &Ring, key: u64, result: i32, flags: u32) -> Option<Message> {
+        if key == FD_KEY {
+            let (fd, data) = ring.take_fd(result)?;
+            Some(Message::Fd(fd, data))
+        } else {
+            Some(Message::Data((flags as u64) << 32 | result as u32 as u64))
+        }
+    }
+}
+
+/// A handle to message a runtime from other runtimes, it is `Send` and `Sync`.
+///
+/// Messages are posted with `IORING_OP_MSG_RING` by the runtime of the calling
+/// thread, straight into the completion queue of the target, which is woken up
+/// if parked. The target dispatches them to its `on_message` callback or its
+/// `channel`, and buffers up to `MAX_BUFFERED` of them until either is set.
+#[derive(Clone)]
+pub struct RuntimeHandle {
+    ring: Arc<Ring>,
+}
+
+/// The ring of a runtime, as seen by its handles.
+pub(crate) struct Ring {
+    state: Mutex<RingState>,
+}
+
+struct RingState {
+    // the ring fd, `None` once the runtime is dropped. Messages are submitted
+    // with the lock held, so that the fd is not closed under them.
+    fd: Option<RawFd>,
+    // fds sent to the runtime, keyed by the token posted in their message.
+    fds: HashMap<i32, (OwnedFd, u32)>,
+    next_token: i32,
+}
+
+impl Ring {
+    pub(crate) fn new(fd: RawFd) -> Ring {
+        Ring {
+            state: Mutex::new(RingState {
+                fd: Some(fd),
+                fds: HashMap::new(),
+                next_token: 0,
+            }),
+        }
+    }
+
+    /// Called by the runtime before closing its ring, closes the fds sent to
+    /// it and not received.
+    pub(crate) fn close(&self) {
+        let fds = {
+            let mut state = self.state.lock().unwrap();
+            state.fd = None;
+            mem::take(&mut state.fds)
+        };
+        drop(fds);
+    }
+
+    pub(crate) fn take_fd(&self, token: i32) -> Option<(OwnedFd, u32)> {
+        self.state.lock().unwrap().fds.remove(&token)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().fd.is_none()
+    }
+
+    // Submit the message built by `f` from the ring fd, with `fd` parked under
+    // the token passed to `f`. `fd` is closed if the message is not submitted.
+    fn post<T>(
+        &self,
+        fd: Option<(OwnedFd, u32)>,
+        f: impl FnOnce(RawFd, Option<i32>) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut state = self.state.lock().unwrap();
+        let ring_fd = match state.fd {
+            Some(ring_fd) => ring_fd,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "target runtime is gone",
+                ))
+            }
+        };
+        let token = fd.map(|fd| {
+            let mut token = state.next_token;
+            while state.fds.contains_key(&token) {
+                token = token.wrapping_add(1) & i32::MAX;
+            }
+            state.next_token = token.wrapping_add(1) & i32::MAX;
+            state.fds.insert(token, fd);
+            token
+        });
+        let res = f(ring_fd, token);
+        if let (Err(_), Some(token)) = (&res, token) {
+            state.fds.remove(&token);
+        }
+        res
+    }
+}
+
+impl RuntimeHandle {
+    pub(crate) fn new(ring: Arc<Ring>) -> RuntimeHandle {
+        RuntimeHandle { ring }
+    }
+
+    /// The handle of the runtime of the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub fn current() -> RuntimeHandle {
+        driver::with_current(|driver| driver.runtime_handle())
+            .expect("`RuntimeHandle::current` called from outside of a runtime context")
+    }
+
+    /// Posts `data` to the runtime.
+    ///
+    /// Values above `u32::MAX` pass their upper half in the flags of the
+    /// completion, with `IORING_MSG_RING_FLAGS_PASS`, which needs Linux 6.3:
+    /// older kernels fail them with `Unsupported`. Smaller values only need
+    /// `IORING_OP_MSG_RING`, from Linux 5.18.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub async fn send(&self, data: u64) -> io::Result<()> {
+        let flags = match (data >> 32) as u32 {
+            0 => None,
+            flags => Some(flags),
+        };
+        let op = self.ring.post(None, |ring_fd, _| {
+            Op::<MsgRing>::msg_ring(
+                self.ring.clone(),
+                ring_fd,
+                DATA_KEY,
+                data as u32 as i32,
+                flags,
+                None,
+            )
+        })?;
+        match op.await {
+            Err(err) if flags.is_some() && err.raw_os_error() == Some(libc::EINVAL) => {
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "data above u32::MAX needs IORING_MSG_RING_FLAGS_PASS, from Linux 6.3",
+                ))
+            }
+            res => res,
+        }
+    }
+
+    /// Moves the ownership of `fd` to the runtime, along with `data`.
+    ///
+    /// The runtimes of a process share its fd table, the fd is not duplicated
+    /// nor installed in the target ring: it is kept aside until the target
+    /// reaps the message and hands it over as `Message::Fd`. It is closed if
+    /// the message cannot be posted, or if the target is dropped before
+    /// receiving it.
+    ///
+    /// The runtimes do not register files, so the kernel's fd passing of
+    /// `IORING_OP_MSG_RING`, which moves registered files only, is not used.
+    /// The fd stays open while the message is not claimed: until the target
+    /// dispatches it, drops it past `MAX_BUFFERED`, or is dropped itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub async fn send_owned_fd(&self, fd: impl Into<OwnedFd>, data: u32) -> io::Result<()> {
+        self.ring
+            .post(Some((fd.into(), data)), |ring_fd, token| {
+                Op::<MsgRing>::msg_ring(
+                    self.ring.clone(),
+                    ring_fd,
+                    FD_KEY,
+                    token.unwrap(),
+                    None,
+                    token,
+                )
+            })?
+            .await
+    }
+
+    /// Whether the runtime has been dropped, messages sent to it are lost.
+    pub fn is_closed(&self) -> bool {
+        self.ring.is_closed()
+    }
+
+    /// Whether both handles refer to the same runtime.
+    pub fn same_runtime(&self, other: &RuntimeHandle) -> bool {
+        Arc::ptr_eq(&self.ring, &other.ring)
+    }
+}
+
+impl fmt::Debug for RuntimeHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeHandle")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// Dispatches the messages received by the runtime of the current thread to
+/// `f`, replacing the previous callback.
+///
+/// It is called by the runtime as the messages are reaped, the ones buffered
+/// until now are dispatched before returning. Until it is first called, up to
+/// `MAX_BUFFERED` messages are kept and the next ones are dropped.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime context.
+pub fn on_message(f: impl FnMut(Message) + 'static) {
+    driver::with_current(|driver| driver.set_msg_handler(Box::new(f)))
+        .expect("`on_message` called from outside of a runtime context")
+}
+
+/// Dispatches the messages received by the runtime of the current thread to
+/// the returned channel, replacing the previous callback.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime context.
+pub fn channel() -> mpsc::UnboundedReceiver<Message> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    on_message(move |msg| {
+        let _ = tx.send(msg);
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+    use std::os::unix::io::FromRawFd;
+
+    use super::*;
+    use crate::runtime::Runtime;
+    use crate::task::yield_now;
+
+    // (read end, write end)
+    fn pipe() -> (OwnedFd, File) {
+        let mut fds = [0; 2];
+        syscall!(pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC)).unwrap();
+        unsafe { (OwnedFd::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    fn is_read_end_closed(tx: &mut File) -> bool {
+        matches!(tx.write(b"x"), Err(err) if err.kind() == io::ErrorKind::BrokenPipe)
+    }
+
+    #[test]
+    fn sends_data_and_fds() {
+        let sender = Runtime::new().unwrap();
+        let target = Runtime::new().unwrap();
+        let handle = target.runtime_handle();
+        let (rx, mut tx) = pipe();
+        sender.block_on(async {
+            handle.send(7).await.unwrap();
+            handle.send_owned_fd(rx, 9).await.unwrap();
+        });
+        let messages = target.block_on(async {
+            let mut messages = channel();
+            vec![
+                messages.recv().await.unwrap(),
+                messages.recv().await.unwrap(),
+            ]
+        });
+        assert!(matches!(messages[0], Message::Data(7)));
+        match &messages[1] {
+            Message::Fd(_, data) => assert_eq!(*data, 9),
+            msg => panic!("unexpected message {:?}", msg),
+        }
+        assert!(!is_read_end_closed(&mut tx));
+        drop(messages);
+        assert!(is_read_end_closed(&mut tx));
+    }
+
+    #[test]
+    fn sends_data_above_u32() {
+        let sender = Runtime::new().unwrap();
+        let target = Runtime::new().unwrap();
+        let handle = target.runtime_handle();
+        let data = 5 << 40 | 3;
+        match sender.block_on(handle.send(data)) {
+            Ok(()) => {}
+            // before Linux 6.3.
+            Err(err) if err.kind() == io::ErrorKind::Unsupported => return,
+            Err(err) => panic!("send failed: {}", err),
+        }
+        let msg = target.block_on(async { channel().recv().await.unwrap() });
+        assert!(matches!(msg, Message::Data(n) if n == data));
+    }
+
+    #[test]
+    fn fails_once_target_is_dropped() {
+        let sender = Runtime::new().unwrap();
+        let target = Runtime::new().unwrap();
+        let handle = target.runtime_handle();
+        drop(target);
+        assert!(handle.is_closed());
+        let (rx, mut tx) = pipe();
+        sender.block_on(async {
+            let err = handle.send(1).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+            let err = handle.send_owned_fd(rx, 1).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        });
+        assert!(is_read_end_closed(&mut tx));
+    }
+
+    #[test]
+    fn fds_not_received_are_closed_with_target() {
+        let sender = Runtime::new().unwrap();
+        let target = Runtime::new().unwrap();
+        let handle = target.runtime_handle();
+        let (rx, mut tx) = pipe();
+        sender.block_on(handle.send_owned_fd(rx, 1)).unwrap();
+        // reaped and buffered, then left in the completion queue.
+        target.block_on(yield_now());
+        let (rx, mut tx2) = pipe();
+        sender.block_on(handle.send_owned_fd(rx, 2)).unwrap();
+        assert!(!is_read_end_closed(&mut tx));
+        assert!(!is_read_end_closed(&mut tx2));
+        drop(target);
+        assert!(is_read_end_closed(&mut tx));
+        assert!(is_read_end_closed(&mut tx2));
+    }
+
+    #[test]
+    fn fds_of_unclaimed_messages_past_the_buffer_are_closed() {
+        let sender = Runtime::new().unwrap();
+        let target = Runtime::new().unwrap();
+        let handle = target.runtime_handle();
+        let mut sent = 0;
+        while sent < MAX_BUFFERED {
+            sender.block_on(async {
+                for _ in 0..100.min(MAX_BUFFERED - sent) {
+                    handle.send(sent as u64).await.unwrap();
+                    sent += 1;
+                }
+            });
+            target.block_on(yield_now());
+        }
+        let (rx, mut tx) = pipe();
+        sender.block_on(handle.send_owned_fd(rx, 1)).unwrap();
+        assert!(!is_read_end_closed(&mut tx));
+        // reaped with the buffer full and never claimed, while the target is
+        // still alive.
+        target.block_on(yield_now());
+        assert!(is_read_end_closed(&mut tx));
+        assert_eq!(handle.ring.state.lock().unwrap().fds.len(), 0);
+    }
+
+    #[test]
+    fn buffers_a_bounded_number_of_messages() {
+        let sender = Runtime::new().unwrap();
+        let target = Runtime::new().unwrap();
+        let handle = target.runtime_handle();
+        let mut sent = 0;
+        while sent < MAX_BUFFERED + 10 {
+            sender.block_on(async {
+                for _ in 0..100 {
+                    handle.send(sent as u64).await.unwrap();
+                    sent += 1;
+                }
+            });
+            target.block_on(yield_now());
+        }
+        let received = target.block_on(async {
+            let mut messages = channel();
+            let mut received = Vec::new();
+            while let Ok(Message::Data(n)) = messages.try_recv() {
+                received.push(n);
+            }
+            received
+        });
+        let expected: Vec<u64> = (0..MAX_BUFFERED as u64).collect();
+        assert_eq!(received, expected);
+    }
+}
@@ -1,7 +1,10 @@
+#[macro_use]
+mod split;
+
 pub mod tcp;
 pub mod udp;
 pub mod unix;
 
-pub use tcp::{TcpListener, TcpStream};
+pub use tcp::{TcpInfo, TcpListener, TcpSocket, TcpStream};
 pub use udp::UdpSocket;
-pub use unix::{UnixListener, UnixStream};
+pub use unix::{UnixDatagram, UnixListener, UnixSeqpacket, UnixSeqpacketListener, UnixStream};
@@ -0,0 +1,221 @@
+/// Defines the halves of `$stream` returned by its `split` and `into_split`,
+/// with `ReuniteError`, over the halves of `socket::Stream`. `$local_addr` and
+/// `$peer_addr` take the `&Socket` of the stream.
+macro_rules! stream_halves {
+    ($stream: ident, $addr: ty, $local_addr: path, $peer_addr: path) => {
+        /// The read half of the stream, from its `split`.
+        pub struct ReadHalf<'a> {
+            inner: $crate::socket::stream::ReadHalf<'a>,
+        }
+
+        /// The write half of the stream, from its `split`.
+        pub struct WriteHalf<'a> {
+            inner: $crate::socket::stream::WriteHalf<'a>,
+        }
+
+        /// The read half of the stream, from its `into_split`.
+        pub struct OwnedReadHalf {
+            inner: $crate::socket::stream::OwnedReadHalf,
+        }
+
+        /// The write half of the stream, from its `into_split`.
+        ///
+        /// Dropping it shuts down the write direction, so the peer sees EOF even
+        /// though the read half keeps the socket open.
+        pub struct OwnedWriteHalf {
+            inner: $crate::socket::stream::OwnedWriteHalf,
+        }
+
+        /// Returned by `reunite` with the halves, when they come from different
+        /// streams.
+        pub struct ReuniteError(pub OwnedReadHalf, pub OwnedWriteHalf);
+
+        impl std::fmt::Debug for ReuniteError {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                fmt.debug_struct("ReuniteError").finish_non_exhaustive()
+            }
+        }
+
+        impl std::fmt::Display for ReuniteError {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(fmt, "the halves are not from the same stream")
+            }
+        }
+
+        impl std::error::Error for ReuniteError {}
+
+        pub(crate) fn split(stream: &mut $crate::socket::Stream) -> (ReadHalf<'_>, WriteHalf<'_>) {
+            let (read, write) = stream.split();
+            (ReadHalf { inner: read }, WriteHalf { inner: write })
+        }
+
+        pub(crate) fn into_split(
+            stream: $crate::socket::Stream,
+        ) -> (OwnedReadHalf, OwnedWriteHalf) {
+            let (read, write) = stream.into_split();
+            (
+                OwnedReadHalf { inner: read },
+                OwnedWriteHalf { inner: write },
+            )
+        }
+
+        #[allow(clippy::result_large_err)]
+        fn reunite(read: OwnedReadHalf, write: OwnedWriteHalf) -> Result<$stream, ReuniteError> {
+            match $crate::socket::Stream::reunite(read.inner, write.inner) {
+                Ok(stream) => Ok($stream::from_stream(stream)),
+                Err((read, write)) => Err(ReuniteError(
+                    OwnedReadHalf { inner: read },
+                    OwnedWriteHalf { inner: write },
+                )),
+            }
+        }
+
+        impl ReadHalf<'_> {
+            pub fn local_addr(&self) -> std::io::Result<$addr> {
+                $local_addr(self.inner.get_ref())
+            }
+
+            pub fn peer_addr(&self) -> std::io::Result<$addr> {
+                $peer_addr(self.inner.get_ref())
+            }
+        }
+
+        impl WriteHalf<'_> {
+            pub fn local_addr(&self) -> std::io::Result<$addr> {
+                $local_addr(self.inner.get_ref())
+            }
+
+            pub fn peer_addr(&self) -> std::io::Result<$addr> {
+                $peer_addr(self.inner.get_ref())
+            }
+        }
+
+        impl OwnedReadHalf {
+            /// Puts the stream back together, it fails if `other` was split off a
+            /// different stream.
+            #[allow(clippy::result_large_err)]
+            pub fn reunite(self, other: OwnedWriteHalf) -> Result<$stream, ReuniteError> {
+                reunite(self, other)
+            }
+
+            pub fn local_addr(&self) -> std::io::Result<$addr> {
+                $local_addr(self.inner.get_ref())
+            }
+
+            pub fn peer_addr(&self) -> std::io::Result<$addr> {
+                $peer_addr(self.inner.get_ref())
+            }
+        }
+
+        impl OwnedWriteHalf {
+            /// Puts the stream back together, it fails if `other` was split off a
+            /// different stream.
+            #[allow(clippy::result_large_err)]
+            pub fn reunite(self, other: OwnedReadHalf) -> Result<$stream, ReuniteError> {
+                reunite(other, self)
+            }
+
+            /// Whether dropping the half shuts down the write direction, which
+            /// it does by default.
+            pub fn set_shutdown_on_drop(&mut self, shutdown_on_drop: bool) {
+                self.inner.set_shutdown_on_drop(shutdown_on_drop)
+            }
+
+            pub fn local_addr(&self) -> std::io::Result<$addr> {
+                $local_addr(self.inner.get_ref())
+            }
+
+            pub fn peer_addr(&self) -> std::io::Result<$addr> {
+                $peer_addr(self.inner.get_ref())
+            }
+        }
+
+        impl_read_half!(ReadHalf<'_>);
+        impl_read_half!(OwnedReadHalf);
+        impl_write_half!(WriteHalf<'_>);
+        impl_write_half!(OwnedWriteHalf);
+    };
+}
+
+macro_rules! impl_read_half {
+    ($ty: ty) => {
+        impl futures_io::AsyncRead for $ty {
+            fn poll_read(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                buf: &mut [u8],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.get_mut().inner.poll_read(cx, buf)
+            }
+
+            fn poll_read_vectored(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                bufs: &mut [std::io::IoSliceMut<'_>],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.get_mut().inner.poll_read_vectored(cx, bufs)
+            }
+        }
+
+        impl futures_io::AsyncBufRead for $ty {
+            fn poll_fill_buf(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::io::Result<&[u8]>> {
+                self.get_mut().inner.poll_fill_buf(cx)
+            }
+
+            fn consume(self: std::pin::Pin<&mut Self>, amt: usize) {
+                self.get_mut().inner.consume(amt);
+            }
+        }
+
+        impl std::os::unix::io::AsRawFd for $ty {
+            fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+                std::os::unix::io::AsRawFd::as_raw_fd(self.inner.get_ref())
+            }
+        }
+    };
+}
+
+macro_rules! impl_write_half {
+    ($ty: ty) => {
+        impl futures_io::AsyncWrite for $ty {
+            fn poll_write(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                buf: &[u8],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.get_mut().inner.poll_write(cx, buf)
+            }
+
+            fn poll_write_vectored(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                bufs: &[std::io::IoSlice<'_>],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.get_mut().inner.poll_write_vectored(cx, bufs)
+            }
+
+            fn poll_flush(
+                self: std::pin::Pin<&mut Self>,
+                _: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::io::Result<()>> {
+                std::task::Poll::Ready(Ok(()))
+            }
+
+            fn poll_close(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::io::Result<()>> {
+                self.get_mut().inner.poll_shutdown(cx)
+            }
+        }
+
+        impl std::os::unix::io::AsRawFd for $ty {
+            fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+                std::os::unix::io::AsRawFd::as_raw_fd(self.inner.get_ref())
+            }
+        }
+    };
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+/// Statistics of a TCP connection from `TCP_INFO`, see `tcp(7)`.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpInfo {
+    /// The state of the connection, `TCP_ESTABLISHED` and so on.
+    pub state: u8,
+    /// Retransmissions of the segment at the head of the queue so far.
+    pub retransmits: u8,
+    /// Segments retransmitted over the connection.
+    pub total_retrans: u32,
+    /// Segments sent but not acknowledged.
+    pub unacked: u32,
+    /// Segments considered lost.
+    pub lost: u32,
+    /// Smoothed round trip time.
+    pub rtt: Duration,
+    /// Variation of the round trip time.
+    pub rtt_var: Duration,
+    /// Lowest round trip time seen.
+    pub min_rtt: Duration,
+    /// Retransmission timeout.
+    pub rto: Duration,
+    /// Congestion window, in segments.
+    pub snd_cwnd: u32,
+    /// Slow start threshold, in segments.
+    pub snd_ssthresh: u32,
+    pub snd_mss: u32,
+    pub rcv_mss: u32,
+    pub bytes_acked: u64,
+    pub bytes_received: u64,
+    /// Bytes written but not sent yet.
+    pub notsent_bytes: u32,
+    /// Pacing rate, in bytes per second.
+    pub pacing_rate: u64,
+}
+
+impl TcpInfo {
+    pub(crate) fn from_raw(info: libc::tcp_info) -> TcpInfo {
+        TcpInfo {
+            state: info.tcpi_state,
+            retransmits: info.tcpi_retransmits,
+            total_retrans: info.tcpi_total_retrans,
+            unacked: info.tcpi_unacked,
+            lost: info.tcpi_lost,
+            rtt: Duration::from_micros(info.tcpi_rtt as u64),
+            rtt_var: Duration::from_micros(info.tcpi_rttvar as u64),
+            min_rtt: Duration::from_micros(info.tcpi_min_rtt as u64),
+            rto: Duration::from_micros(info.tcpi_rto as u64),
+            snd_cwnd: info.tcpi_snd_cwnd,
+            snd_ssthresh: info.tcpi_snd_ssthresh,
+            snd_mss: info.tcpi_snd_mss,
+            rcv_mss: info.tcpi_rcv_mss,
+            bytes_acked: info.tcpi_bytes_acked,
+            bytes_received: info.tcpi_bytes_received,
+            notsent_bytes: info.tcpi_notsent_bytes,
+            pacing_rate: info.tcpi_pacing_rate,
+        }
+    }
+}
@@ -61,9 +61,9 @@ impl TcpListener {
                 Ok(())
             })?
         };
-        let socket_addr = addr.as_socket().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Could not get socket IP address")
-        })?;
+        let socket_addr = addr
+            .as_socket()
+            .ok_or_else(|| io::Error::other("Could not get socket IP address"))?;
         Poll::Ready(Ok((socket.into(), socket_addr)))
     }
 
@@ -78,6 +78,14 @@ impl TcpListener {
     }
 }
 
+impl From<Socket> for TcpListener {
+    fn from(socket: Socket) -> Self {
+        TcpListener {
+            inner: socket::Listener::new(socket),
+        }
+    }
+}
+
 impl AsRawFd for TcpListener {
     fn as_raw_fd(&self) -> RawFd {
         self.inner.get_ref().as_raw_fd()
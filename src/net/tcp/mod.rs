@@ -1,5 +1,11 @@
+pub mod info;
 pub mod listener;
+pub mod socket;
+pub mod split;
 pub mod stream;
 
+pub use info::TcpInfo;
 pub use listener::TcpListener;
+pub use socket::TcpSocket;
+pub use split::{OwnedReadHalf, OwnedWriteHalf, ReadHalf, ReuniteError, WriteHalf};
 pub use stream::TcpStream;
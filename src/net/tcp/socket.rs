@@ -0,0 +1,148 @@
+use std::future::poll_fn;
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+use socket2::SockAddr;
+
+use super::{TcpListener, TcpStream};
+use crate::socket::{self, Socket};
+
+/// A TCP socket not yet connected or listening, to set options that must be
+/// set before `bind`, `connect` or `listen`.
+///
+/// Unlike `TcpListener::bind`, no option is set by default, not even
+/// `SO_REUSEADDR`.
+pub struct TcpSocket {
+    inner: Socket,
+}
+
+impl TcpSocket {
+    pub fn new_v4() -> io::Result<TcpSocket> {
+        TcpSocket::new(libc::AF_INET)
+    }
+
+    pub fn new_v6() -> io::Result<TcpSocket> {
+        TcpSocket::new(libc::AF_INET6)
+    }
+
+    fn new(domain: libc::c_int) -> io::Result<TcpSocket> {
+        Ok(TcpSocket {
+            inner: Socket::new_domain(domain, libc::SOCK_STREAM)?,
+        })
+    }
+
+    /// `SO_REUSEADDR`, lets a listener bind while connections to its address
+    /// are in `TIME_WAIT`.
+    pub fn set_reuseaddr(&self, reuseaddr: bool) -> io::Result<()> {
+        self.inner.sock_ref().set_reuse_address(reuseaddr)
+    }
+
+    pub fn reuseaddr(&self) -> io::Result<bool> {
+        self.inner.sock_ref().reuse_address()
+    }
+
+    /// `SO_REUSEPORT`, lets sockets of the same user bind the same address,
+    /// the kernel balances incoming connections between them.
+    pub fn set_reuseport(&self, reuseport: bool) -> io::Result<()> {
+        self.inner.sock_ref().set_reuse_port(reuseport)
+    }
+
+    pub fn reuseport(&self) -> io::Result<bool> {
+        self.inner.sock_ref().reuse_port()
+    }
+
+    /// `IPV6_V6ONLY`, whether an IPv6 socket leaves IPv4 to other sockets.
+    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+        self.inner.sock_ref().set_only_v6(only_v6)
+    }
+
+    pub fn only_v6(&self) -> io::Result<bool> {
+        self.inner.sock_ref().only_v6()
+    }
+
+    /// `SO_SNDBUF`, the kernel doubles the size to leave room for bookkeeping.
+    pub fn set_send_buffer_size(&self, size: u32) -> io::Result<()> {
+        self.inner.sock_ref().set_send_buffer_size(size as usize)
+    }
+
+    pub fn send_buffer_size(&self) -> io::Result<u32> {
+        self.inner
+            .sock_ref()
+            .send_buffer_size()
+            .map(|size| size as u32)
+    }
+
+    /// `SO_RCVBUF`, the kernel doubles the size to leave room for bookkeeping.
+    pub fn set_recv_buffer_size(&self, size: u32) -> io::Result<()> {
+        self.inner.sock_ref().set_recv_buffer_size(size as usize)
+    }
+
+    pub fn recv_buffer_size(&self) -> io::Result<u32> {
+        self.inner
+            .sock_ref()
+            .recv_buffer_size()
+            .map(|size| size as u32)
+    }
+
+    /// `SO_BINDTODEVICE`, only uses the network interface named `interface`,
+    /// or any if `None`.
+    pub fn bind_device(&self, interface: Option<&[u8]>) -> io::Result<()> {
+        self.inner.sock_ref().bind_device(interface)
+    }
+
+    pub fn device(&self) -> io::Result<Option<Vec<u8>>> {
+        self.inner.sock_ref().device()
+    }
+
+    /// Binds to `addr`, before `listen` or to pick the local address of
+    /// `connect`.
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<()> {
+        self.inner.bind_addr(&SockAddr::from(addr))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    pub async fn connect(self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let mut stream = socket::Stream::new(self.inner);
+        poll_fn(|cx| stream.poll_connect(cx, &SockAddr::from(addr))).await?;
+        Ok(TcpStream::from_stream(stream))
+    }
+
+    /// Starts listening, with `backlog` clamped to `i32::MAX`. The kernel caps
+    /// it to `net.core.somaxconn` anyway.
+    pub fn listen(self, backlog: u32) -> io::Result<TcpListener> {
+        let backlog = backlog.min(libc::c_int::MAX as u32) as libc::c_int;
+        self.inner.listen(backlog)?;
+        Ok(TcpListener::from(self.inner))
+    }
+}
+
+impl AsRawFd for TcpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl FromRawFd for TcpSocket {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        TcpSocket {
+            inner: Socket::from_raw_fd(fd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listen_clamps_backlog() {
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(u32::MAX).unwrap();
+        assert!(listener.local_addr().unwrap().port() != 0);
+    }
+}
@@ -0,0 +1,118 @@
+use std::net::SocketAddr;
+
+use crate::net::TcpStream;
+use crate::socket::Socket;
+
+stream_halves!(TcpStream, SocketAddr, Socket::local_addr, Socket::peer_addr);
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use std::net;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    use futures_util::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn pair() -> (TcpStream, net::TcpStream) {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (
+            unsafe { TcpStream::from_raw_fd(server.into_raw_fd()) },
+            client,
+        )
+    }
+
+    #[test]
+    fn dropping_write_half_shuts_down() {
+        let (stream, mut peer) = pair();
+        let (_read, write) = stream.into_split();
+        drop(write);
+        assert_eq!(peer.read(&mut [0; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn dropping_write_half_without_shutdown() {
+        let (stream, mut peer) = pair();
+        let (_read, mut write) = stream.into_split();
+        write.set_shutdown_on_drop(false);
+        drop(write);
+        peer.set_nonblocking(true).unwrap();
+        let err = peer.read(&mut [0; 8]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn reunite_halves_of_the_same_stream() {
+        let (stream, mut peer) = pair();
+        let (read, write) = stream.into_split();
+        let stream = read.reunite(write).unwrap();
+        let (read, write) = stream.into_split();
+        let mut stream = write.reunite(read).unwrap();
+        // the write half went away without shutting the stream down.
+        Runtime::new()
+            .unwrap()
+            .block_on(stream.write_all(b"hello"))
+            .unwrap();
+        let mut buf = [0; 5];
+        peer.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn reunite_halves_of_different_streams() {
+        let (first, _first_peer) = pair();
+        let (second, mut second_peer) = pair();
+        let (first_read, first_write) = first.into_split();
+        let (second_read, second_write) = second.into_split();
+
+        let Err(ReuniteError(first_read, second_write)) = first_read.reunite(second_write) else {
+            panic!("reunited halves of different streams");
+        };
+        let Err(ReuniteError(second_read, first_write)) = first_write.reunite(second_read) else {
+            panic!("reunited halves of different streams");
+        };
+        // the halves given back still fit their own stream.
+        first_read.reunite(first_write).unwrap();
+        let mut second = second_write.reunite(second_read).unwrap();
+        Runtime::new()
+            .unwrap()
+            .block_on(second.write_all(b"hello"))
+            .unwrap();
+        let mut buf = [0; 5];
+        second_peer.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn read_and_write_in_separate_tasks() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, b) = pair();
+            let b = unsafe { TcpStream::from_raw_fd(b.into_raw_fd()) };
+            // more than the socket buffers hold, so that both ends block on
+            // writing until the other end reads.
+            let data: Vec<u8> = (0..4 << 20).map(|i| i as u8).collect();
+            let mut tasks = Vec::new();
+            for stream in [a, b] {
+                let (mut read, mut write) = stream.into_split();
+                let expected = data.clone();
+                tasks.push(crate::spawn_local(async move {
+                    let mut received = vec![0; expected.len()];
+                    read.read_exact(&mut received).await.unwrap();
+                    assert!(received == expected);
+                }));
+                let data = data.clone();
+                tasks.push(crate::spawn_local(async move {
+                    write.write_all(&data).await.unwrap();
+                }));
+            }
+            for task in tasks {
+                task.await;
+            }
+        });
+    }
+}
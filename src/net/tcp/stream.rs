@@ -1,13 +1,16 @@
 use std::future::poll_fn;
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut};
 use std::net::{self, SocketAddr, ToSocketAddrs};
 use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
-use socket2::SockAddr;
+use socket2::{SockAddr, TcpKeepalive};
 
+use super::split::{self, OwnedReadHalf, OwnedWriteHalf, ReadHalf, WriteHalf};
+use crate::net::TcpInfo;
 use crate::socket::{self, Socket};
 
 pub struct TcpStream {
@@ -29,6 +32,10 @@ impl TcpStream {
         Ok(TcpStream { inner: stream })
     }
 
+    pub(crate) fn from_stream(inner: socket::Stream) -> TcpStream {
+        TcpStream { inner }
+    }
+
     pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<TcpStream> {
         let addrs = addr.to_socket_addrs()?;
 
@@ -59,6 +66,191 @@ impl TcpStream {
     pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
         self.inner.get_ref().set_nodelay(nodelay)
     }
+
+    pub fn nodelay(&self) -> io::Result<bool> {
+        self.inner.get_ref().sock_ref().nodelay()
+    }
+
+    /// `SO_KEEPALIVE`, probes an idle connection to notice a dead peer.
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.inner.get_ref().sock_ref().set_keepalive(keepalive)
+    }
+
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.inner.get_ref().sock_ref().keepalive()
+    }
+
+    /// `TCP_KEEPIDLE`, how long the connection is idle before the first
+    /// probe. Turns keepalive on.
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        let keepalive = TcpKeepalive::new().with_time(idle);
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_tcp_keepalive(&keepalive)
+    }
+
+    pub fn keepalive_idle(&self) -> io::Result<Duration> {
+        self.inner.get_ref().sock_ref().keepalive_time()
+    }
+
+    /// `TCP_KEEPINTVL`, the time between probes. Turns keepalive on.
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        let keepalive = TcpKeepalive::new().with_interval(interval);
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_tcp_keepalive(&keepalive)
+    }
+
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        self.inner.get_ref().sock_ref().keepalive_interval()
+    }
+
+    /// `TCP_KEEPCNT`, the unanswered probes before the connection is dropped.
+    /// Turns keepalive on.
+    pub fn set_keepalive_retries(&self, retries: u32) -> io::Result<()> {
+        let keepalive = TcpKeepalive::new().with_retries(retries);
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_tcp_keepalive(&keepalive)
+    }
+
+    pub fn keepalive_retries(&self) -> io::Result<u32> {
+        self.inner.get_ref().sock_ref().keepalive_retries()
+    }
+
+    /// `SO_LINGER`, with `Some` closing waits up to the duration for unsent
+    /// data, and `Some(Duration::ZERO)` resets the connection instead.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        self.inner.get_ref().sock_ref().set_linger(linger)
+    }
+
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        self.inner.get_ref().sock_ref().linger()
+    }
+
+    /// `IP_TTL`, or `IPV6_UNICAST_HOPS` for IPv6.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.inner.get_ref().set_ttl(ttl)
+    }
+
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.inner.get_ref().ttl()
+    }
+
+    /// `IP_TOS`, or `IPV6_TCLASS` for IPv6. The DSCP is the upper six bits.
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.inner.get_ref().set_tos(tos)
+    }
+
+    pub fn tos(&self) -> io::Result<u32> {
+        self.inner.get_ref().tos()
+    }
+
+    /// `TCP_USER_TIMEOUT`, how long sent data may stay unacknowledged before
+    /// the connection is dropped. `None` uses the system default.
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_tcp_user_timeout(timeout)
+    }
+
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.get_ref().sock_ref().tcp_user_timeout()
+    }
+
+    /// `TCP_QUICKACK`, acknowledges right away instead of delaying. The
+    /// kernel may turn it off again by itself.
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        self.inner.get_ref().sock_ref().set_quickack(quickack)
+    }
+
+    pub fn quickack(&self) -> io::Result<bool> {
+        self.inner.get_ref().sock_ref().quickack()
+    }
+
+    /// `TCP_CONGESTION`, the congestion control algorithm, e.g. `b"bbr"`.
+    pub fn set_congestion(&self, algorithm: &[u8]) -> io::Result<()> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_tcp_congestion(algorithm)
+    }
+
+    pub fn congestion(&self) -> io::Result<Vec<u8>> {
+        let mut name = self.inner.get_ref().sock_ref().tcp_congestion()?;
+        // the kernel pads the name with nul bytes.
+        if let Some(end) = name.iter().position(|&b| b == 0) {
+            name.truncate(end);
+        }
+        Ok(name)
+    }
+
+    /// `SO_SNDBUF`, the kernel doubles the size to leave room for bookkeeping.
+    pub fn set_send_buffer_size(&self, size: u32) -> io::Result<()> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_send_buffer_size(size as usize)
+    }
+
+    pub fn send_buffer_size(&self) -> io::Result<u32> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .send_buffer_size()
+            .map(|size| size as u32)
+    }
+
+    /// `SO_RCVBUF`, the kernel doubles the size to leave room for bookkeeping.
+    pub fn set_recv_buffer_size(&self, size: u32) -> io::Result<()> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_recv_buffer_size(size as usize)
+    }
+
+    pub fn recv_buffer_size(&self) -> io::Result<u32> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .recv_buffer_size()
+            .map(|size| size as u32)
+    }
+
+    /// `TCP_NOTSENT_LOWAT`, writes wait until less than `lowat` bytes are
+    /// queued but not sent yet.
+    pub fn set_notsent_lowat(&self, lowat: u32) -> io::Result<()> {
+        self.inner.get_ref().set_notsent_lowat(lowat)
+    }
+
+    pub fn notsent_lowat(&self) -> io::Result<u32> {
+        self.inner.get_ref().notsent_lowat()
+    }
+
+    /// `TCP_INFO`, round trip times, congestion window and retransmissions
+    /// of the connection.
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        self.inner.get_ref().tcp_info().map(TcpInfo::from_raw)
+    }
+
+    /// Splits into halves that borrow the stream, so reads and writes can be
+    /// polled at the same time, e.g. in `join`.
+    ///
+    /// With `AsyncReadExt` in scope, `stream.split()` resolves to its `split`,
+    /// call `TcpStream::split(&mut stream)` instead.
+    pub fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        split::split(&mut self.inner)
+    }
+
+    /// Splits into halves that can be moved into different tasks, and put
+    /// back together with `reunite`.
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        split::into_split(self.inner)
+    }
 }
 
 impl AsyncBufRead for TcpStream {
@@ -79,6 +271,14 @@ impl AsyncRead for TcpStream {
     ) -> Poll<io::Result<usize>> {
         self.get_mut().inner.poll_read(cx, buf)
     }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_read_vectored(cx, bufs)
+    }
 }
 
 impl AsyncWrite for TcpStream {
@@ -86,6 +286,14 @@ impl AsyncWrite for TcpStream {
         self.get_mut().inner.poll_write(cx, buf)
     }
 
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_write_vectored(cx, bufs)
+    }
+
     fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
         Poll::Ready(Ok(()))
     }
@@ -117,3 +325,105 @@ impl FromRawFd for TcpStream {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::io::IntoRawFd;
+
+    use super::*;
+
+    // a connected stream over `addr`, `None` if the address family is not
+    // available.
+    fn pair(addr: &str) -> Option<(TcpStream, net::TcpStream)> {
+        let listener = net::TcpListener::bind(addr).ok()?;
+        let client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let stream = unsafe { TcpStream::from_raw_fd(server.into_raw_fd()) };
+        Some((stream, client))
+    }
+
+    #[test]
+    fn keepalive_setters_keep_each_other() {
+        let (stream, _peer) = pair("127.0.0.1:0").unwrap();
+        stream.set_keepalive_idle(Duration::from_secs(60)).unwrap();
+        stream
+            .set_keepalive_interval(Duration::from_secs(5))
+            .unwrap();
+        stream.set_keepalive_retries(3).unwrap();
+        assert!(stream.keepalive().unwrap());
+        assert_eq!(stream.keepalive_idle().unwrap(), Duration::from_secs(60));
+        assert_eq!(stream.keepalive_interval().unwrap(), Duration::from_secs(5));
+        assert_eq!(stream.keepalive_retries().unwrap(), 3);
+
+        stream.set_keepalive(false).unwrap();
+        assert!(!stream.keepalive().unwrap());
+    }
+
+    #[test]
+    fn options_round_trip() {
+        let (stream, _peer) = pair("127.0.0.1:0").unwrap();
+        stream.set_nodelay(true).unwrap();
+        assert!(stream.nodelay().unwrap());
+
+        stream.set_linger(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(stream.linger().unwrap(), Some(Duration::from_secs(5)));
+        stream.set_linger(None).unwrap();
+        assert_eq!(stream.linger().unwrap(), None);
+
+        stream.set_ttl(42).unwrap();
+        assert_eq!(stream.ttl().unwrap(), 42);
+        // EF, the ECN bits are left to the kernel.
+        stream.set_tos(0xb8).unwrap();
+        assert_eq!(stream.tos().unwrap(), 0xb8);
+
+        stream
+            .set_user_timeout(Some(Duration::from_secs(3)))
+            .unwrap();
+        assert_eq!(stream.user_timeout().unwrap(), Some(Duration::from_secs(3)));
+        stream.set_user_timeout(None).unwrap();
+        assert_eq!(stream.user_timeout().unwrap(), None);
+
+        stream.set_notsent_lowat(16384).unwrap();
+        assert_eq!(stream.notsent_lowat().unwrap(), 16384);
+
+        stream.set_send_buffer_size(64 * 1024).unwrap();
+        assert_eq!(stream.send_buffer_size().unwrap(), 2 * 64 * 1024);
+        stream.set_recv_buffer_size(64 * 1024).unwrap();
+        assert_eq!(stream.recv_buffer_size().unwrap(), 2 * 64 * 1024);
+    }
+
+    #[test]
+    fn congestion_strips_padding() {
+        let (stream, _peer) = pair("127.0.0.1:0").unwrap();
+        let name = stream.congestion().unwrap();
+        assert!(!name.is_empty());
+        assert!(!name.contains(&0));
+        // reno is always built in.
+        stream.set_congestion(b"reno").unwrap();
+        assert_eq!(stream.congestion().unwrap(), b"reno");
+    }
+
+    #[test]
+    fn tcp_info_of_established_connection() {
+        let (stream, _peer) = pair("127.0.0.1:0").unwrap();
+        // from `include/net/tcp_states.h`, libc does not define it.
+        const TCP_ESTABLISHED: u8 = 1;
+        let info = stream.tcp_info().unwrap();
+        assert_eq!(info.state, TCP_ESTABLISHED);
+        assert!(info.snd_mss > 0);
+    }
+
+    #[test]
+    fn ipv6_ttl_and_tos() {
+        let Some((stream, _peer)) = pair("[::1]:0") else {
+            return;
+        };
+        stream.set_ttl(17).unwrap();
+        assert_eq!(stream.ttl().unwrap(), 17);
+        let sock = stream.inner.get_ref().sock_ref();
+        assert_eq!(sock.unicast_hops_v6().unwrap(), 17);
+        stream.set_tos(0xb8).unwrap();
+        assert_eq!(stream.tos().unwrap(), 0xb8);
+        assert_eq!(sock.tclass_v6().unwrap(), 0xb8);
+    }
+}
@@ -1,7 +1,7 @@
 use std::future::poll_fn;
-use std::io;
+use std::io::{self, IoSlice};
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::task::{Context, Poll};
+use std::task::{ready, Context, Poll};
 
 use socket2::SockAddr;
 
@@ -41,6 +41,56 @@ impl UdpSocket {
         self.inner.get_ref().local_addr()
     }
 
+    /// `IP_TTL`, or `IPV6_UNICAST_HOPS` for IPv6.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.inner.get_ref().set_ttl(ttl)
+    }
+
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.inner.get_ref().ttl()
+    }
+
+    /// `IP_TOS`, or `IPV6_TCLASS` for IPv6. The DSCP is the upper six bits.
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.inner.get_ref().set_tos(tos)
+    }
+
+    pub fn tos(&self) -> io::Result<u32> {
+        self.inner.get_ref().tos()
+    }
+
+    /// `SO_SNDBUF`, the kernel doubles the size to leave room for bookkeeping.
+    pub fn set_send_buffer_size(&self, size: u32) -> io::Result<()> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_send_buffer_size(size as usize)
+    }
+
+    pub fn send_buffer_size(&self) -> io::Result<u32> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .send_buffer_size()
+            .map(|size| size as u32)
+    }
+
+    /// `SO_RCVBUF`, the kernel doubles the size to leave room for bookkeeping.
+    pub fn set_recv_buffer_size(&self, size: u32) -> io::Result<()> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .set_recv_buffer_size(size as usize)
+    }
+
+    pub fn recv_buffer_size(&self) -> io::Result<u32> {
+        self.inner
+            .get_ref()
+            .sock_ref()
+            .recv_buffer_size()
+            .map(|size| size as u32)
+    }
+
     pub async fn connect<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
         let addrs = addr.to_socket_addrs()?;
         let mut last_err = None;
@@ -72,12 +122,23 @@ impl UdpSocket {
     }
 
     pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        poll_fn(|cx| self.inner.poll_recv_from(cx, buf)).await
+        poll_fn(|cx| self.poll_recv_from(cx, buf)).await
     }
 
     pub async fn send_to<A: Into<SocketAddr>>(&self, buf: &[u8], target: A) -> io::Result<usize> {
-        let addr = target.into();
-        poll_fn(|cx| self.inner.poll_send_to(cx, buf, addr)).await
+        let addr = SockAddr::from(target.into());
+        poll_fn(|cx| self.inner.poll_send_to(cx, buf, &addr)).await
+    }
+
+    /// Sends the slices as one datagram with a single `sendmsg`, e.g. a
+    /// header and a body.
+    pub async fn send_vectored_to<A: Into<SocketAddr>>(
+        &self,
+        bufs: &[IoSlice<'_>],
+        target: A,
+    ) -> io::Result<usize> {
+        let addr = SockAddr::from(target.into());
+        poll_fn(|cx| self.inner.poll_send_vectored_to(cx, bufs, &addr)).await
     }
 
     pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
@@ -97,7 +158,9 @@ impl UdpSocket {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<(usize, SocketAddr)>> {
-        self.inner.poll_recv_from(cx, buf)
+        let (n, addr) = ready!(self.inner.poll_recv_from(cx, buf))?;
+        let addr = addr.as_socket().ok_or(io::ErrorKind::InvalidInput)?;
+        Poll::Ready(Ok((n, addr)))
     }
 
     pub fn poll_send_to<A: Into<SocketAddr>>(
@@ -106,7 +169,68 @@ impl UdpSocket {
         buf: &[u8],
         target: A,
     ) -> Poll<io::Result<usize>> {
-        let addr = target.into();
-        self.inner.poll_send_to(cx, buf, addr)
+        let addr = SockAddr::from(target.into());
+        self.inner.poll_send_to(cx, buf, &addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn options_round_trip() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_ttl(42).unwrap();
+        assert_eq!(socket.ttl().unwrap(), 42);
+        socket.set_tos(0xb8).unwrap();
+        assert_eq!(socket.tos().unwrap(), 0xb8);
+        socket.set_send_buffer_size(64 * 1024).unwrap();
+        assert_eq!(socket.send_buffer_size().unwrap(), 2 * 64 * 1024);
+        socket.set_recv_buffer_size(64 * 1024).unwrap();
+        assert_eq!(socket.recv_buffer_size().unwrap(), 2 * 64 * 1024);
+    }
+
+    #[test]
+    fn ipv6_ttl_and_tos() {
+        let Ok(socket) = UdpSocket::bind("[::1]:0") else {
+            return;
+        };
+        socket.set_ttl(17).unwrap();
+        assert_eq!(socket.ttl().unwrap(), 17);
+        let sock = socket.inner.get_ref().sock_ref();
+        assert_eq!(sock.unicast_hops_v6().unwrap(), 17);
+        socket.set_tos(0xb8).unwrap();
+        assert_eq!(socket.tos().unwrap(), 0xb8);
+        assert_eq!(sock.tclass_v6().unwrap(), 0xb8);
+    }
+
+    #[test]
+    fn send_vectored_to_sends_one_datagram() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let bufs = [IoSlice::new(b"header:"), IoSlice::new(b"body")];
+            let target = receiver.local_addr().unwrap();
+            assert_eq!(sender.send_vectored_to(&bufs, target).await.unwrap(), 11);
+            sender.send_to(b"next", target).await.unwrap();
+
+            let mut buf = [0; 64];
+            let (n, from) = receiver.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"header:body");
+            assert_eq!(from, sender.local_addr().unwrap());
+            let (n, _) = receiver.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"next");
+        });
+    }
+
+    #[test]
+    fn second_bind_on_same_address_fails() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let err = UdpSocket::bind(addr).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
     }
 }
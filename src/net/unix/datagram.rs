@@ -0,0 +1,306 @@
+use std::future::poll_fn;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::os::unix::net;
+use std::path::Path;
+use std::task::{ready, Context, Poll};
+
+use socket2::SockAddr;
+
+use super::socket_file::SocketFile;
+use super::UCred;
+use crate::socket::{socketaddr::SocketAddr, Packet, Socket};
+
+pub struct UnixDatagram {
+    inner: Packet,
+    // dropped after the socket is closed.
+    #[allow(dead_code)]
+    file: Option<SocketFile>,
+}
+
+impl UnixDatagram {
+    pub fn bind<P>(path: P) -> io::Result<UnixDatagram>
+    where
+        P: AsRef<Path>,
+    {
+        let socket = Socket::bind_unix(&SockAddr::unix(path)?, libc::SOCK_DGRAM)?;
+        Ok(UnixDatagram::from(socket))
+    }
+
+    /// Binds like `bind`, removing a stale socket file left at `path` by a
+    /// socket that is gone, and removes the socket file when dropped.
+    pub fn bind_unlink<P>(path: P) -> io::Result<UnixDatagram>
+    where
+        P: AsRef<Path>,
+    {
+        let (socket, file) = SocketFile::bind(path.as_ref(), libc::SOCK_DGRAM)?;
+        Ok(UnixDatagram {
+            inner: Packet::new(socket),
+            file: Some(file),
+        })
+    }
+
+    /// Binds to `addr`, which may be in the abstract namespace.
+    pub fn bind_addr(addr: &SocketAddr) -> io::Result<UnixDatagram> {
+        let socket = Socket::bind_unix(&addr.to_sockaddr(), libc::SOCK_DGRAM)?;
+        Ok(UnixDatagram::from(socket))
+    }
+
+    /// Creates a socket not bound to any address, peers see it as unnamed.
+    pub fn unbound() -> io::Result<UnixDatagram> {
+        let socket = Socket::new_unix(libc::SOCK_DGRAM)?;
+        Ok(UnixDatagram::from(socket))
+    }
+
+    /// Creates a pair of unnamed sockets connected to each other.
+    pub fn pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
+        let (a, b) = Socket::pair(libc::SOCK_DGRAM)?;
+        Ok((UnixDatagram::from(a), UnixDatagram::from(b)))
+    }
+
+    pub fn from_std(datagram: net::UnixDatagram) -> io::Result<UnixDatagram> {
+        let socket = unsafe { Socket::from_raw_fd(datagram.into_raw_fd()) };
+        Ok(UnixDatagram::from(socket))
+    }
+
+    /// Sets the default destination of `send` and the only source `recv`
+    /// accepts datagrams from.
+    pub async fn connect<P>(&self, path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let addr = SockAddr::unix(path)?;
+        poll_fn(|cx| self.inner.poll_connect(cx, &addr)).await
+    }
+
+    /// Connects to `addr`, which may be in the abstract namespace.
+    pub async fn connect_addr(&self, addr: &SocketAddr) -> io::Result<()> {
+        let addr = addr.to_sockaddr();
+        poll_fn(|cx| self.inner.poll_connect(cx, &addr)).await
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        let fd = self.inner.get_ref().as_raw_fd();
+        SocketAddr::new(|sockaddr, socklen| syscall!(getsockname(fd, sockaddr, socklen)))
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        let fd = self.inner.get_ref().as_raw_fd();
+        SocketAddr::new(|sockaddr, socklen| syscall!(getpeername(fd, sockaddr, socklen)))
+    }
+
+    /// The credentials of the peer process, as they were when the connection was made.
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        self.inner.get_ref().peer_cred().map(UCred::from_raw)
+    }
+
+    /// A pidfd of the peer process, it stays valid even if the pid is reused.
+    ///
+    /// Fails with `ErrorKind::Unsupported` on kernels before 6.5.
+    pub fn peer_pidfd(&self) -> io::Result<OwnedFd> {
+        self.inner.get_ref().peer_pidfd()
+    }
+
+    /// Whether received datagrams carry the credentials of their sender, see
+    /// `recv_with_cred`.
+    pub fn set_passcred(&self, passcred: bool) -> io::Result<()> {
+        self.inner.get_ref().set_passcred(passcred)
+    }
+
+    pub fn passcred(&self) -> io::Result<bool> {
+        self.inner.get_ref().passcred()
+    }
+
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.inner.poll_send(cx, buf)).await
+    }
+
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.inner.poll_recv(cx, buf)).await
+    }
+
+    /// Sends `buf` to the connected peer along with `fds`, it receives copies
+    /// of them with `recv_with_fds`. They stay open on this side.
+    pub async fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        poll_fn(|cx| self.inner.poll_send_with_fds(cx, buf, fds)).await
+    }
+
+    /// Receives a datagram into `buf`, appending the fds passed along to
+    /// `fds`. They are opened with `O_CLOEXEC`.
+    ///
+    /// Fails if the control message was truncated.
+    pub async fn recv_with_fds(&self, buf: &mut [u8], fds: &mut Vec<OwnedFd>) -> io::Result<usize> {
+        let (n, _, ancillary) = poll_fn(|cx| self.inner.poll_recv_msg(cx, buf, true)).await?;
+        fds.extend(ancillary.fds);
+        Ok(n)
+    }
+
+    /// Receives a datagram into `buf` along with the credentials of its
+    /// sender, which are only sent once `set_passcred` is enabled.
+    pub async fn recv_with_cred(&self, buf: &mut [u8]) -> io::Result<(usize, Option<UCred>)> {
+        let (n, _, ancillary) = poll_fn(|cx| self.inner.poll_recv_msg(cx, buf, true)).await?;
+        Ok((n, ancillary.cred.map(UCred::from_raw)))
+    }
+
+    pub async fn send_to<P>(&self, buf: &[u8], target: P) -> io::Result<usize>
+    where
+        P: AsRef<Path>,
+    {
+        let addr = SockAddr::unix(target)?;
+        poll_fn(|cx| self.inner.poll_send_to(cx, buf, &addr)).await
+    }
+
+    /// Sends `buf` to `target`, which may be in the abstract namespace.
+    pub async fn send_to_addr(&self, buf: &[u8], target: &SocketAddr) -> io::Result<usize> {
+        let addr = target.to_sockaddr();
+        poll_fn(|cx| self.inner.poll_send_to(cx, buf, &addr)).await
+    }
+
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        poll_fn(|cx| self.poll_recv_from(cx, buf)).await
+    }
+
+    pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.inner.poll_send(cx, buf)
+    }
+
+    pub fn poll_recv(&self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        self.inner.poll_recv(cx, buf)
+    }
+
+    pub fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        let (n, addr) = ready!(self.inner.poll_recv_from(cx, buf))?;
+        Poll::Ready(Ok((n, SocketAddr::from_sockaddr(&addr))))
+    }
+}
+
+impl From<Socket> for UnixDatagram {
+    fn from(socket: Socket) -> Self {
+        UnixDatagram {
+            inner: Packet::new(socket),
+            file: None,
+        }
+    }
+}
+
+impl AsRawFd for UnixDatagram {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.get_ref().as_raw_fd()
+    }
+}
+
+impl FromRawFd for UnixDatagram {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        UnixDatagram::from(Socket::from_raw_fd(fd))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::task::Waker;
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn peer_cred_is_this_process() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, _b) = UnixDatagram::pair().unwrap();
+            let cred = a.peer_cred().unwrap();
+            assert_eq!(cred.pid(), Some(std::process::id() as libc::pid_t));
+            assert_eq!(cred.uid(), unsafe { libc::getuid() });
+            assert_eq!(cred.gid(), unsafe { libc::getgid() });
+        });
+    }
+
+    #[test]
+    fn recv_with_cred_needs_passcred() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, b) = UnixDatagram::pair().unwrap();
+            let mut buf = [0; 16];
+            assert!(!a.passcred().unwrap());
+            b.send(b"first").await.unwrap();
+            assert_eq!(a.recv_with_cred(&mut buf).await.unwrap(), (5, None));
+
+            a.set_passcred(true).unwrap();
+            assert!(a.passcred().unwrap());
+            b.send(b"second").await.unwrap();
+            let (n, cred) = a.recv_with_cred(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"second");
+            let cred = cred.unwrap();
+            assert_eq!(cred.pid(), Some(std::process::id() as libc::pid_t));
+            assert_eq!(cred.uid(), unsafe { libc::getuid() });
+        });
+    }
+
+    #[test]
+    fn peer_pidfd_refers_to_this_process() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, _b) = UnixDatagram::pair().unwrap();
+            let pidfd = match a.peer_pidfd() {
+                Ok(pidfd) => pidfd,
+                Err(err) if err.kind() == io::ErrorKind::Unsupported => return,
+                Err(err) => panic!("peer_pidfd failed: {}", err),
+            };
+            let path = format!("/proc/self/fdinfo/{}", pidfd.as_raw_fd());
+            let fdinfo = std::fs::read_to_string(path).unwrap();
+            let pid = format!("Pid:\t{}\n", std::process::id());
+            assert!(fdinfo.contains(&pid), "{}", fdinfo);
+        });
+    }
+
+    #[test]
+    fn recv_with_fds_after_dropped_recv_from() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, b) = UnixDatagram::pair().unwrap();
+            let mut buf = [0; 16];
+            // leaves an op without room for control messages in flight.
+            assert!(poll(pin!(a.recv_from(&mut buf))).is_pending());
+
+            let (r, _w) = std::io::pipe().unwrap();
+            b.send_with_fds(b"first", &[r.as_raw_fd()]).await.unwrap();
+            let mut fds = Vec::new();
+            let err = a.recv_with_fds(&mut buf, &mut fds).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            b.send_with_fds(b"second", &[r.as_raw_fd()]).await.unwrap();
+            assert_eq!(a.recv_with_fds(&mut buf, &mut fds).await.unwrap(), 6);
+            assert_eq!(&buf[..6], b"second");
+            assert_eq!(fds.len(), 1);
+        });
+    }
+
+    #[test]
+    fn recv_from_with_other_buffer_than_in_flight_op() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, b) = UnixDatagram::pair().unwrap();
+            let mut buf = [0; 16];
+            assert!(poll(pin!(a.recv_from(&mut buf))).is_pending());
+            b.send(b"truncated").await.unwrap();
+            let mut small = [0; 4];
+            let (n, _) = a.recv_from(&mut small).await.unwrap();
+            assert_eq!(&small[..n], b"trun");
+
+            let mut small = [0; 2];
+            assert!(poll(pin!(a.recv_from(&mut small))).is_pending());
+            b.send(b"lost").await.unwrap();
+            let err = a.recv_from(&mut buf).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+    }
+}
@@ -5,11 +5,17 @@ use std::os::unix::net;
 use std::path::Path;
 use std::task::{ready, Context, Poll};
 
+use socket2::SockAddr;
+
+use super::socket_file::SocketFile;
 use super::UnixStream;
 use crate::socket::{self, socketaddr::SocketAddr, Socket};
 
 pub struct UnixListener {
     inner: socket::Listener,
+    // dropped after the socket is closed.
+    #[allow(dead_code)]
+    file: Option<SocketFile>,
 }
 
 impl UnixListener {
@@ -17,8 +23,32 @@ impl UnixListener {
     where
         P: AsRef<Path>,
     {
+        let addr = SockAddr::unix(path)?;
+        Ok(UnixListener {
+            inner: socket::Listener::bind_unix(&addr, libc::SOCK_STREAM)?,
+            file: None,
+        })
+    }
+
+    /// Binds like `bind`, removing a stale socket file left at `path` by a
+    /// socket that is gone, and removes the socket file when dropped.
+    pub fn bind_unlink<P>(path: P) -> io::Result<UnixListener>
+    where
+        P: AsRef<Path>,
+    {
+        let (socket, file) = SocketFile::bind(path.as_ref(), libc::SOCK_STREAM)?;
+        socket.listen(1024)?;
+        Ok(UnixListener {
+            inner: socket::Listener::new(socket),
+            file: Some(file),
+        })
+    }
+
+    /// Binds to `addr`, which may be in the abstract namespace.
+    pub fn bind_addr(addr: &SocketAddr) -> io::Result<UnixListener> {
         Ok(UnixListener {
-            inner: socket::Listener::bind_unix(path)?,
+            inner: socket::Listener::bind_unix(&addr.to_sockaddr(), libc::SOCK_STREAM)?,
+            file: None,
         })
     }
 
@@ -46,6 +76,7 @@ impl UnixListener {
     pub fn from_std(listener: net::UnixListener) -> io::Result<UnixListener> {
         Ok(UnixListener {
             inner: unsafe { socket::Listener::from_raw_fd(listener.into_raw_fd()) },
+            file: None,
         })
     }
 
@@ -66,6 +97,7 @@ impl FromRawFd for UnixListener {
         let socket = Socket::from_raw_fd(fd);
         UnixListener {
             inner: socket::Listener::new(socket),
+            file: None,
         }
     }
 }
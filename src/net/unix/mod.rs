@@ -1,5 +1,16 @@
+mod datagram;
 mod listener;
+mod seqpacket;
+mod socket_file;
+mod split;
 mod stream;
+mod ucred;
 
+pub use datagram::UnixDatagram;
 pub use listener::UnixListener;
+pub use seqpacket::{UnixSeqpacket, UnixSeqpacketListener};
+pub use split::{OwnedReadHalf, OwnedWriteHalf, ReadHalf, ReuniteError, WriteHalf};
 pub use stream::UnixStream;
+pub use ucred::UCred;
+
+pub use crate::socket::socketaddr::SocketAddr;
@@ -0,0 +1,232 @@
+use std::future::poll_fn;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::task::{ready, Context, Poll};
+
+use socket2::SockAddr;
+
+use super::UCred;
+use crate::socket::{self, socketaddr::SocketAddr, Packet, Socket};
+
+/// Listens for `SOCK_SEQPACKET` connections.
+pub struct UnixSeqpacketListener {
+    inner: socket::Listener,
+}
+
+/// A `SOCK_SEQPACKET` connection, which keeps the boundaries of the messages
+/// sent over it.
+pub struct UnixSeqpacket {
+    inner: Packet,
+}
+
+impl UnixSeqpacketListener {
+    pub fn bind<P>(path: P) -> io::Result<UnixSeqpacketListener>
+    where
+        P: AsRef<Path>,
+    {
+        let addr = SockAddr::unix(path)?;
+        Ok(UnixSeqpacketListener {
+            inner: socket::Listener::bind_unix(&addr, libc::SOCK_SEQPACKET)?,
+        })
+    }
+
+    /// Binds to `addr`, which may be in the abstract namespace.
+    pub fn bind_addr(addr: &SocketAddr) -> io::Result<UnixSeqpacketListener> {
+        Ok(UnixSeqpacketListener {
+            inner: socket::Listener::bind_unix(&addr.to_sockaddr(), libc::SOCK_SEQPACKET)?,
+        })
+    }
+
+    pub async fn accept(&self) -> io::Result<(UnixSeqpacket, SocketAddr)> {
+        poll_fn(|cx| self.poll_accept(cx)).await
+    }
+
+    pub fn poll_accept(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(UnixSeqpacket, SocketAddr)>> {
+        let (socket, socketstorage) = ready!(self.inner.poll_accept(cx))?;
+        Poll::Ready(Ok((UnixSeqpacket::from(socket), socketstorage.into())))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        let fd = self.inner.as_raw_fd();
+        SocketAddr::new(|sockaddr, socklen| syscall!(getsockname(fd, sockaddr, socklen)))
+    }
+}
+
+impl AsRawFd for UnixSeqpacketListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.get_ref().as_raw_fd()
+    }
+}
+
+impl FromRawFd for UnixSeqpacketListener {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        UnixSeqpacketListener {
+            inner: socket::Listener::new(Socket::from_raw_fd(fd)),
+        }
+    }
+}
+
+impl UnixSeqpacket {
+    pub async fn connect<P>(path: P) -> io::Result<UnixSeqpacket>
+    where
+        P: AsRef<Path>,
+    {
+        UnixSeqpacket::connect_sockaddr(SockAddr::unix(path)?).await
+    }
+
+    /// Connects to `addr`, which may be in the abstract namespace.
+    pub async fn connect_addr(addr: &SocketAddr) -> io::Result<UnixSeqpacket> {
+        UnixSeqpacket::connect_sockaddr(addr.to_sockaddr()).await
+    }
+
+    async fn connect_sockaddr(addr: SockAddr) -> io::Result<UnixSeqpacket> {
+        let socket = UnixSeqpacket::from(Socket::new_unix(libc::SOCK_SEQPACKET)?);
+        poll_fn(|cx| socket.inner.poll_connect(cx, &addr)).await?;
+        Ok(socket)
+    }
+
+    /// Creates a pair of sockets connected to each other.
+    pub fn pair() -> io::Result<(UnixSeqpacket, UnixSeqpacket)> {
+        let (a, b) = Socket::pair(libc::SOCK_SEQPACKET)?;
+        Ok((UnixSeqpacket::from(a), UnixSeqpacket::from(b)))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        let fd = self.inner.get_ref().as_raw_fd();
+        SocketAddr::new(|sockaddr, socklen| syscall!(getsockname(fd, sockaddr, socklen)))
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        let fd = self.inner.get_ref().as_raw_fd();
+        SocketAddr::new(|sockaddr, socklen| syscall!(getpeername(fd, sockaddr, socklen)))
+    }
+
+    /// The credentials of the peer process, as they were when the connection
+    /// was made.
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        self.inner.get_ref().peer_cred().map(UCred::from_raw)
+    }
+
+    /// Sends `buf` as one message.
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.inner.poll_send(cx, buf)).await
+    }
+
+    /// Receives one message into `buf`, returning the length written to it.
+    ///
+    /// The rest of a message larger than `buf` is lost, `recv_msg` tells if
+    /// that happened. Returns 0 once the peer closed the connection.
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_recv(cx, buf)).await
+    }
+
+    /// Receives one message like `recv`, also returning whether it was
+    /// truncated to fit `buf`.
+    pub async fn recv_msg(&self, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        poll_fn(|cx| self.poll_recv_msg(cx, buf)).await
+    }
+
+    /// Sends `buf` as one message along with `fds`, the peer receives copies of
+    /// them with `recv_with_fds`. They stay open on this side.
+    pub async fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        poll_fn(|cx| self.inner.poll_send_with_fds(cx, buf, fds)).await
+    }
+
+    /// Receives one message like `recv_msg`, appending the fds passed along
+    /// to `fds`. They are opened with `O_CLOEXEC`.
+    ///
+    /// Fails if the control message was truncated.
+    pub async fn recv_with_fds(
+        &self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> io::Result<(usize, bool)> {
+        let (n, truncated, ancillary) =
+            poll_fn(|cx| self.inner.poll_recv_msg(cx, buf, true)).await?;
+        fds.extend(ancillary.fds);
+        Ok((n, truncated))
+    }
+
+    pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.inner.poll_send(cx, buf)
+    }
+
+    pub fn poll_recv(&self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let (n, _) = ready!(self.poll_recv_msg(cx, buf))?;
+        Poll::Ready(Ok(n))
+    }
+
+    pub fn poll_recv_msg(
+        &self,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, bool)>> {
+        let (n, truncated, _) = ready!(self.inner.poll_recv_msg(cx, buf, false))?;
+        Poll::Ready(Ok((n, truncated)))
+    }
+}
+
+impl From<Socket> for UnixSeqpacket {
+    fn from(socket: Socket) -> Self {
+        UnixSeqpacket {
+            inner: Packet::new(socket),
+        }
+    }
+}
+
+impl AsRawFd for UnixSeqpacket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.get_ref().as_raw_fd()
+    }
+}
+
+impl FromRawFd for UnixSeqpacket {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        UnixSeqpacket::from(Socket::from_raw_fd(fd))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn reports_truncated_messages() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, b) = UnixSeqpacket::pair().unwrap();
+            b.send(b"hello").await.unwrap();
+            b.send(b"hello world").await.unwrap();
+            b.send(b"hello world").await.unwrap();
+            let mut buf = [0; 8];
+            assert_eq!(a.recv_msg(&mut buf).await.unwrap(), (5, false));
+            assert_eq!(a.recv_msg(&mut buf).await.unwrap(), (8, true));
+            assert_eq!(&buf, b"hello wo");
+            assert_eq!(a.recv(&mut buf).await.unwrap(), 8);
+            drop(b);
+            assert_eq!(a.recv(&mut buf).await.unwrap(), 0);
+        });
+    }
+
+    #[test]
+    fn recv_with_fds_reports_truncation() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, b) = UnixSeqpacket::pair().unwrap();
+            let (r, _w) = std::io::pipe().unwrap();
+            b.send_with_fds(b"hello world", &[r.as_raw_fd()])
+                .await
+                .unwrap();
+            let mut buf = [0; 5];
+            let mut fds = Vec::new();
+            let res = a.recv_with_fds(&mut buf, &mut fds).await.unwrap();
+            assert_eq!(res, (5, true));
+            assert_eq!(fds.len(), 1);
+        });
+    }
+}
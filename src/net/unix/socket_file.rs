@@ -0,0 +1,126 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use socket2::SockAddr;
+
+use crate::socket::Socket;
+
+/// A socket file removed when the socket bound to it is dropped, unless it
+/// was replaced in the meantime.
+pub(super) struct SocketFile {
+    path: PathBuf,
+    dev: u64,
+    ino: u64,
+}
+
+impl SocketFile {
+    /// Binds a socket to `path`, first removing the socket file there if no
+    /// socket is bound to it anymore.
+    pub(super) fn bind(path: &Path, socket_type: libc::c_int) -> io::Result<(Socket, SocketFile)> {
+        let addr = SockAddr::unix(path)?;
+        unlink_stale(path, &addr, socket_type)?;
+        let socket = Socket::bind_unix(&addr, socket_type)?;
+        let meta = fs::symlink_metadata(path)?;
+        let file = SocketFile {
+            path: path.to_path_buf(),
+            dev: meta.dev(),
+            ino: meta.ino(),
+        };
+        Ok((socket, file))
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Ok(meta) = fs::symlink_metadata(&self.path) {
+            if meta.dev() == self.dev && meta.ino() == self.ino {
+                let _ = fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+// A socket file is stale once connecting to it is refused, anything else at
+// `path` is left for bind to fail on.
+fn unlink_stale(path: &Path, addr: &SockAddr, socket_type: libc::c_int) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => {}
+        _ => return Ok(()),
+    }
+    // non-blocking so that a listener with a full backlog does not block.
+    let probe = Socket::new_unix(socket_type | libc::SOCK_NONBLOCK)?;
+    match syscall!(connect(probe.as_raw_fd(), addr.as_ptr(), addr.len())) {
+        Err(err) if err.raw_os_error() == Some(libc::ECONNREFUSED) => match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net;
+
+    use super::*;
+    use crate::net::unix::UnixListener;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("slings-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn replaces_stale_socket_file() {
+        let path = socket_path("stale-socket");
+        drop(net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let listener = UnixListener::bind_unlink(&path).unwrap();
+        net::UnixStream::connect(&path).unwrap();
+        drop(listener);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn leaves_live_socket_alone() {
+        let path = socket_path("live-socket");
+        let live = net::UnixListener::bind(&path).unwrap();
+
+        let err = UnixListener::bind_unlink(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        net::UnixStream::connect(&path).unwrap();
+        live.accept().unwrap();
+        drop(live);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn leaves_other_files_alone() {
+        let path = socket_path("regular-file");
+        fs::write(&path, b"data").unwrap();
+
+        let err = UnixListener::bind_unlink(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(fs::read(&path).unwrap(), b"data");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keeps_file_replaced_since_bind() {
+        let path = socket_path("replaced-socket");
+        let listener = UnixListener::bind_unlink(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let other = net::UnixListener::bind(&path).unwrap();
+
+        drop(listener);
+        assert!(path.exists());
+        net::UnixStream::connect(&path).unwrap();
+        drop(other);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use super::UnixStream;
+use crate::socket::{socketaddr::SocketAddr, Socket};
+
+fn local_addr(socket: &Socket) -> io::Result<SocketAddr> {
+    let fd = socket.as_raw_fd();
+    SocketAddr::new(|sockaddr, socklen| syscall!(getsockname(fd, sockaddr, socklen)))
+}
+
+fn peer_addr(socket: &Socket) -> io::Result<SocketAddr> {
+    let fd = socket.as_raw_fd();
+    SocketAddr::new(|sockaddr, socklen| syscall!(getpeername(fd, sockaddr, socklen)))
+}
+
+stream_halves!(UnixStream, SocketAddr, local_addr, peer_addr);
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    use std::os::unix::net;
+
+    use futures_util::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn pair() -> (UnixStream, net::UnixStream) {
+        let (a, b) = net::UnixStream::pair().unwrap();
+        (unsafe { UnixStream::from_raw_fd(a.into_raw_fd()) }, b)
+    }
+
+    #[test]
+    fn reunite_halves_of_the_same_stream() {
+        let (stream, mut peer) = pair();
+        let (read, write) = stream.into_split();
+        let stream = read.reunite(write).unwrap();
+        let (read, write) = stream.into_split();
+        let mut stream = write.reunite(read).unwrap();
+        // the write half went away without shutting the stream down.
+        Runtime::new()
+            .unwrap()
+            .block_on(stream.write_all(b"hello"))
+            .unwrap();
+        let mut buf = [0; 5];
+        peer.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn reunite_halves_of_different_streams() {
+        let (first, _first_peer) = pair();
+        let (second, mut second_peer) = pair();
+        let (first_read, first_write) = first.into_split();
+        let (second_read, second_write) = second.into_split();
+
+        let Err(ReuniteError(first_read, second_write)) = first_read.reunite(second_write) else {
+            panic!("reunited halves of different streams");
+        };
+        let Err(ReuniteError(second_read, first_write)) = first_write.reunite(second_read) else {
+            panic!("reunited halves of different streams");
+        };
+        // the halves given back still fit their own stream.
+        first_read.reunite(first_write).unwrap();
+        let mut second = second_write.reunite(second_read).unwrap();
+        Runtime::new()
+            .unwrap()
+            .block_on(second.write_all(b"hello"))
+            .unwrap();
+        let mut buf = [0; 5];
+        second_peer.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn read_and_write_in_separate_tasks() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, b) = pair();
+            let b = unsafe { UnixStream::from_raw_fd(b.into_raw_fd()) };
+            // more than the socket buffers hold, so that both ends block on
+            // writing until the other end reads.
+            let data: Vec<u8> = (0..4 << 20).map(|i| i as u8).collect();
+            let mut tasks = Vec::new();
+            for stream in [a, b] {
+                let (mut read, mut write) = stream.into_split();
+                let expected = data.clone();
+                tasks.push(crate::spawn_local(async move {
+                    let mut received = vec![0; expected.len()];
+                    read.read_exact(&mut received).await.unwrap();
+                    assert!(received == expected);
+                }));
+                let data = data.clone();
+                tasks.push(crate::spawn_local(async move {
+                    write.write_all(&data).await.unwrap();
+                }));
+            }
+            for task in tasks {
+                task.await;
+            }
+        });
+    }
+}
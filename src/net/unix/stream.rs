@@ -1,6 +1,6 @@
 use std::future::poll_fn;
-use std::io;
-use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::io::{self, IoSlice, IoSliceMut};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
 use std::os::unix::net;
 use std::path::Path;
 use std::pin::Pin;
@@ -9,6 +9,8 @@ use std::task::{Context, Poll};
 use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
 use socket2::SockAddr;
 
+use super::split::{self, OwnedReadHalf, OwnedWriteHalf, ReadHalf, WriteHalf};
+use super::UCred;
 use crate::socket::{self, socketaddr::SocketAddr, Socket};
 
 pub struct UnixStream {
@@ -27,6 +29,19 @@ impl UnixStream {
         Ok(UnixStream { inner: stream })
     }
 
+    /// Connects to `addr`, which may be in the abstract namespace.
+    pub async fn connect_addr(addr: &SocketAddr) -> io::Result<UnixStream> {
+        let socket = Socket::new_unix(libc::SOCK_STREAM)?;
+        let mut stream = socket::Stream::new(socket);
+        let addr = addr.to_sockaddr();
+        poll_fn(|cx| stream.poll_connect(cx, &addr)).await?;
+        Ok(UnixStream { inner: stream })
+    }
+
+    pub(crate) fn from_stream(inner: socket::Stream) -> UnixStream {
+        UnixStream { inner }
+    }
+
     pub fn from_std(stream: net::UnixStream) -> io::Result<UnixStream> {
         let socket = unsafe { Socket::from_raw_fd(stream.as_raw_fd()) };
         Ok(UnixStream {
@@ -43,6 +58,55 @@ impl UnixStream {
         let fd = self.inner.get_ref().as_raw_fd();
         SocketAddr::new(|sockaddr, socklen| syscall!(getpeername(fd, sockaddr, socklen)))
     }
+
+    /// The credentials of the peer process, as they were when the connection was made.
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        self.inner.get_ref().peer_cred().map(UCred::from_raw)
+    }
+
+    /// A pidfd of the peer process, it stays valid even if the pid is reused.
+    ///
+    /// Fails with `ErrorKind::Unsupported` on kernels before 6.5.
+    pub fn peer_pidfd(&self) -> io::Result<OwnedFd> {
+        self.inner.get_ref().peer_pidfd()
+    }
+
+    /// Sends `buf` along with `fds`, the peer receives copies of them with
+    /// `recv_with_fds`. They stay open on this side.
+    pub async fn send_with_fds(&mut self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        poll_fn(|cx| self.inner.poll_send_with_fds(cx, buf, fds)).await
+    }
+
+    /// Receives into `buf`, appending the fds passed along to `fds`. They are
+    /// opened with `O_CLOEXEC`.
+    ///
+    /// Bytes already buffered by `AsyncRead` are returned first, the fds sent
+    /// with them are lost. Fails if the control message was truncated, the
+    /// bytes received are then left for the next read.
+    pub async fn recv_with_fds(
+        &mut self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> io::Result<usize> {
+        let (n, ancillary) = poll_fn(|cx| self.inner.poll_recv_ancillary(cx, buf)).await?;
+        fds.extend(ancillary.fds);
+        Ok(n)
+    }
+
+    /// Splits into halves that borrow the stream, so reads and writes can be
+    /// polled at the same time, e.g. in `join`.
+    ///
+    /// With `AsyncReadExt` in scope, `stream.split()` resolves to its `split`,
+    /// call `UnixStream::split(&mut stream)` instead.
+    pub fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        split::split(&mut self.inner)
+    }
+
+    /// Splits into halves that can be moved into different tasks, and put
+    /// back together with `reunite`.
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        split::into_split(self.inner)
+    }
 }
 
 impl AsyncRead for UnixStream {
@@ -53,6 +117,14 @@ impl AsyncRead for UnixStream {
     ) -> Poll<io::Result<usize>> {
         self.get_mut().inner.poll_read(cx, buf)
     }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_read_vectored(cx, bufs)
+    }
 }
 
 impl AsyncBufRead for UnixStream {
@@ -70,6 +142,14 @@ impl AsyncWrite for UnixStream {
         self.get_mut().inner.poll_write(cx, buf)
     }
 
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_write_vectored(cx, bufs)
+    }
+
     fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
         Poll::Ready(Ok(()))
     }
@@ -103,3 +183,184 @@ impl FromRawFd for UnixStream {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::io::{Read, Write};
+    use std::os::unix::io::IntoRawFd;
+    use std::pin::pin;
+    use std::task::Waker;
+
+    use futures_util::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn pair() -> (UnixStream, net::UnixStream) {
+        let (a, b) = net::UnixStream::pair().unwrap();
+        (unsafe { UnixStream::from_raw_fd(a.into_raw_fd()) }, b)
+    }
+
+    #[test]
+    fn peer_cred_is_this_process() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (a, _b) = pair();
+            let cred = a.peer_cred().unwrap();
+            assert_eq!(cred.pid(), Some(std::process::id() as libc::pid_t));
+            assert_eq!(cred.uid(), unsafe { libc::getuid() });
+            assert_eq!(cred.gid(), unsafe { libc::getgid() });
+        });
+    }
+
+    #[test]
+    fn recv_with_fds_keeps_bytes_beyond_buffer() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (mut a, mut b) = pair();
+            let mut fds = Vec::new();
+            let mut buf = [0; 16];
+            assert!(poll(pin!(a.recv_with_fds(&mut buf, &mut fds))).is_pending());
+
+            b.write_all(b"hello world").unwrap();
+            let mut small = [0; 4];
+            assert_eq!(a.recv_with_fds(&mut small, &mut fds).await.unwrap(), 4);
+            assert_eq!(&small, b"hell");
+            drop(b);
+            let mut rest = Vec::new();
+            a.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(rest, b"o world");
+        });
+    }
+
+    #[test]
+    fn truncated_control_keeps_the_bytes() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (mut a, b) = pair();
+            let mut b = unsafe { UnixStream::from_raw_fd(b.into_raw_fd()) };
+            // the credentials and the pidfd leave no room for the 253 fds the
+            // control buffer is sized for.
+            for opt in [libc::SO_PASSCRED, libc::SO_PASSPIDFD] {
+                let on: libc::c_int = 1;
+                let res = syscall!(setsockopt(
+                    a.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    opt,
+                    &on as *const _ as *const libc::c_void,
+                    std::mem::size_of_val(&on) as libc::socklen_t,
+                ));
+                if res.is_err() {
+                    // SO_PASSPIDFD needs Linux 6.5.
+                    return;
+                }
+            }
+            let fds = [b.as_raw_fd(); 253];
+            b.send_with_fds(b"hello", &fds).await.unwrap();
+
+            let mut buf = [0; 2];
+            let mut received = Vec::new();
+            let err = a.recv_with_fds(&mut buf, &mut received).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(received.is_empty());
+            drop(b);
+            let mut rest = Vec::new();
+            a.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(rest, b"hello");
+        });
+    }
+
+    #[test]
+    fn read_vectored_keeps_bytes_beyond_slices() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (mut a, mut b) = pair();
+            let mut buf = [0; 16];
+            let mut bufs = [IoSliceMut::new(&mut buf)];
+            assert!(poll(pin!(a.read_vectored(&mut bufs))).is_pending());
+
+            b.write_all(b"hello world").unwrap();
+            let (mut x, mut y) = ([0; 2], [0; 2]);
+            let mut bufs = [IoSliceMut::new(&mut x), IoSliceMut::new(&mut y)];
+            assert_eq!(a.read_vectored(&mut bufs).await.unwrap(), 4);
+            assert_eq!((&x, &y), (b"he", b"ll"));
+            drop(b);
+            let mut rest = Vec::new();
+            a.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(rest, b"o world");
+        });
+    }
+
+    #[test]
+    fn read_waits_for_readv_in_flight() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (mut a, mut b) = pair();
+            let mut buf = [0; 16];
+            let mut bufs = [IoSliceMut::new(&mut buf)];
+            assert!(poll(pin!(a.read_vectored(&mut bufs))).is_pending());
+
+            b.write_all(b"first").unwrap();
+            let mut buf = [0; 16];
+            let n = a.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"first");
+            b.write_all(b"second").unwrap();
+            let n = a.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"second");
+        });
+    }
+
+    #[test]
+    fn write_waits_for_writev_in_flight() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (mut a, mut b) = pair();
+            // fill the socket buffer, so that the next write is left in flight.
+            let filler = [0; 4096];
+            let mut filled = 0;
+            while let Ok(n) = syscall!(send(
+                a.as_raw_fd(),
+                filler.as_ptr().cast(),
+                filler.len(),
+                libc::MSG_DONTWAIT,
+            )) {
+                filled += n as usize;
+            }
+            let bufs = [IoSlice::new(b"first")];
+            assert!(poll(pin!(a.write_vectored(&bufs))).is_pending());
+
+            let reader = std::thread::spawn(move || {
+                let mut received = Vec::new();
+                b.read_to_end(&mut received).unwrap();
+                received
+            });
+            a.write_all(b"second").await.unwrap();
+            a.close().await.unwrap();
+            let received = reader.join().unwrap();
+            assert_eq!(&received[filled..], b"firstsecond");
+        });
+    }
+
+    #[test]
+    fn write_vectored_writes_every_slice() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (mut a, mut b) = pair();
+            let bufs = [
+                IoSlice::new(b"one "),
+                IoSlice::new(b""),
+                IoSlice::new(b"two"),
+            ];
+            assert_eq!(a.write_vectored(&bufs).await.unwrap(), 7);
+            a.close().await.unwrap();
+            let mut received = String::new();
+            b.read_to_string(&mut received).unwrap();
+            assert_eq!(received, "one two");
+        });
+    }
+}
@@ -0,0 +1,35 @@
+/// Credentials of a process on the other end of a unix socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UCred {
+    pid: libc::pid_t,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+}
+
+impl UCred {
+    pub(crate) fn from_raw(cred: libc::ucred) -> UCred {
+        UCred {
+            pid: cred.pid,
+            uid: cred.uid,
+            gid: cred.gid,
+        }
+    }
+
+    /// The pid of the process, `None` if it is not known, as for a peer in
+    /// another pid namespace.
+    pub fn pid(&self) -> Option<libc::pid_t> {
+        if self.pid == 0 {
+            None
+        } else {
+            Some(self.pid)
+        }
+    }
+
+    pub fn uid(&self) -> libc::uid_t {
+        self.uid
+    }
+
+    pub fn gid(&self) -> libc::gid_t {
+        self.gid
+    }
+}
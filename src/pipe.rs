@@ -0,0 +1,213 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+use crate::io::FdIo;
+
+/// Creates an anonymous pipe, what is written to the `Sender` is read from
+/// the `Receiver`.
+pub fn pipe() -> io::Result<(Sender, Receiver)> {
+    let mut fds = [0; 2];
+    syscall!(pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC))?;
+    let (rx, tx) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    Ok((Sender::from_owned_fd(tx), Receiver::from_owned_fd(rx)))
+}
+
+/// The writing end of a pipe, closed by `close` or when dropped.
+pub struct Sender {
+    inner: Option<FdIo<OwnedFd>>,
+}
+
+/// The reading end of a pipe.
+pub struct Receiver {
+    inner: FdIo<OwnedFd>,
+}
+
+impl Sender {
+    /// Opens the FIFO at `path` for writing.
+    ///
+    /// Fails with `ENXIO` if no reader has it open.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Sender> {
+        open_fifo(path.as_ref(), OpenOptions::new().write(true)).map(Sender::from_owned_fd)
+    }
+
+    /// Wraps the writing end of a pipe, which may be in non-blocking mode.
+    pub fn from_owned_fd(fd: OwnedFd) -> Sender {
+        Sender {
+            inner: Some(FdIo::new(fd)),
+        }
+    }
+}
+
+impl Receiver {
+    /// Opens the FIFO at `path` for reading, without waiting for a writer.
+    ///
+    /// Reads return end of file while no writer has it open.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Receiver> {
+        open_fifo(path.as_ref(), OpenOptions::new().read(true)).map(Receiver::from_owned_fd)
+    }
+
+    /// Wraps the reading end of a pipe, which may be in non-blocking mode.
+    pub fn from_owned_fd(fd: OwnedFd) -> Receiver {
+        Receiver {
+            inner: FdIo::new(fd),
+        }
+    }
+}
+
+impl AsyncWrite for Sender {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut().inner.as_mut() {
+            Some(inner) => inner.poll_write(cx, buf),
+            None => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsRawFd for Sender {
+    /// Returns -1 once closed.
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_ref().map_or(-1, AsRawFd::as_raw_fd)
+    }
+}
+
+impl AsyncRead for Receiver {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_read(cx, buf)
+    }
+}
+
+impl AsyncBufRead for Receiver {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.get_mut().inner.poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().inner.consume(amt);
+    }
+}
+
+impl AsRawFd for Receiver {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+// Opening a FIFO in non-blocking mode does not wait for the other end.
+fn open_fifo(path: &Path, options: &mut OpenOptions) -> io::Result<OwnedFd> {
+    let file = options
+        .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+        .open(path)?;
+    if !file.metadata()?.file_type().is_fifo() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a FIFO"));
+    }
+    Ok(file.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+
+    use futures_util::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn fifo(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("slings-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        syscall!(mkfifo(c_path.as_ptr(), 0o600)).unwrap();
+        path
+    }
+
+    fn set_nonblocking(fd: RawFd) {
+        let flags = syscall!(fcntl(fd, libc::F_GETFL)).unwrap();
+        syscall!(fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK)).unwrap();
+    }
+
+    #[test]
+    fn pipe_round_trip() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let (mut tx, mut rx) = pipe().unwrap();
+            tx.write_all(b"hello").await.unwrap();
+            tx.close().await.unwrap();
+            assert_eq!(tx.as_raw_fd(), -1);
+            assert!(tx.write(b"x").await.is_err());
+
+            let mut data = Vec::new();
+            rx.read_to_end(&mut data).await.unwrap();
+            assert_eq!(data, b"hello");
+            assert_eq!(rx.read(&mut [0; 8]).await.unwrap(), 0);
+        });
+    }
+
+    #[test]
+    fn opens_fifo() {
+        let rt = Runtime::new().unwrap();
+        let path = fifo("opens-fifo");
+        rt.block_on(async {
+            let err = Sender::open(&path).err().unwrap();
+            assert_eq!(err.raw_os_error(), Some(libc::ENXIO));
+
+            let mut rx = Receiver::open(&path).unwrap();
+            let mut tx = Sender::open(&path).unwrap();
+            tx.write_all(b"fifo").await.unwrap();
+            drop(tx);
+            let mut data = Vec::new();
+            rx.read_to_end(&mut data).await.unwrap();
+            assert_eq!(data, b"fifo");
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        let err = Receiver::open(std::env::temp_dir()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = Receiver::open("/dev/null").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn retries_nonblocking_pipe() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let (mut tx, mut rx) = pipe().unwrap();
+            set_nonblocking(tx.as_raw_fd());
+            set_nonblocking(rx.as_raw_fd());
+
+            // more than the capacity of the pipe, the writer blocks once it is
+            // full and the reader whenever it is empty: depending on the
+            // kernel, io_uring fails them with EAGAIN or polls them itself.
+            let data: Vec<u8> = (0..256 * 1024).map(|i| i as u8).collect();
+            let reader = crate::spawn_local(async move {
+                let mut received = Vec::new();
+                rx.read_to_end(&mut received).await.unwrap();
+                received
+            });
+            tx.write_all(&data).await.unwrap();
+            drop(tx);
+            assert_eq!(reader.await, data);
+        });
+    }
+}
@@ -0,0 +1,478 @@
+use std::ffi::OsStr;
+use std::future::{poll_fn, Future};
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::pin::{pin, Pin};
+use std::process::{self, ExitStatus, Output, Stdio};
+use std::task::{Context, Poll};
+
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+use crate::driver::{self, Op, PollAdd};
+use crate::io::FdIo;
+use crate::runtime::Handle;
+
+/// Builds and spawns child processes, mirroring `std::process::Command`.
+pub struct Command {
+    std: process::Command,
+    kill_on_drop: bool,
+    // whether stdin, stdout and stderr were set, `output` leaves them alone.
+    stdio_set: [bool; 3],
+}
+
+impl Command {
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Command {
+        Command::from(process::Command::new(program))
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Command {
+        self.std.arg(arg);
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.std.args(args);
+        self
+    }
+
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Command
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.std.env(key, val);
+        self
+    }
+
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Command
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.std.envs(vars);
+        self
+    }
+
+    pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Command {
+        self.std.env_remove(key);
+        self
+    }
+
+    pub fn env_clear(&mut self) -> &mut Command {
+        self.std.env_clear();
+        self
+    }
+
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Command {
+        self.std.current_dir(dir);
+        self
+    }
+
+    pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.std.stdin(cfg);
+        self.stdio_set[0] = true;
+        self
+    }
+
+    pub fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.std.stdout(cfg);
+        self.stdio_set[1] = true;
+        self
+    }
+
+    pub fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.std.stderr(cfg);
+        self.stdio_set[2] = true;
+        self
+    }
+
+    /// Whether the child is killed when its `Child` is dropped before it
+    /// exited, off by default.
+    pub fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Command {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+
+    pub fn as_std(&self) -> &process::Command {
+        &self.std
+    }
+
+    /// The stdio set through it are not seen by `output`, which replaces
+    /// them with its defaults.
+    pub fn as_std_mut(&mut self) -> &mut process::Command {
+        &mut self.std
+    }
+
+    /// Spawns the child, its piped stdio are read and written through the
+    /// ring of the runtime they are first used on.
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        let mut child = self.std.spawn()?;
+        let pidfd = match pidfd_open(child.id()) {
+            Ok(pidfd) => pidfd,
+            Err(err) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+        };
+        Ok(Child {
+            stdin: child.stdin.take().map(|io| ChildStdin {
+                inner: Some(pipe(io)),
+            }),
+            stdout: child
+                .stdout
+                .take()
+                .map(|io| ChildStdout { inner: pipe(io) }),
+            stderr: child
+                .stderr
+                .take()
+                .map(|io| ChildStderr { inner: pipe(io) }),
+            child,
+            pidfd,
+            status: None,
+            kill_on_drop: self.kill_on_drop,
+        })
+    }
+
+    /// Spawns the child and waits for it to exit, its stdio are inherited
+    /// unless set otherwise.
+    pub async fn status(&mut self) -> io::Result<ExitStatus> {
+        let mut child = self.spawn()?;
+        drop(child.stdout.take());
+        drop(child.stderr.take());
+        child.wait().await
+    }
+
+    /// Spawns the child and collects its output.
+    ///
+    /// Unless set on the command, stdin is null while stdout and stderr are
+    /// piped, for this call only.
+    pub async fn output(&mut self) -> io::Result<Output> {
+        let [stdin, stdout, stderr] = self.stdio_set;
+        if !stdin {
+            self.std.stdin(Stdio::null());
+        }
+        if !stdout {
+            self.std.stdout(Stdio::piped());
+        }
+        if !stderr {
+            self.std.stderr(Stdio::piped());
+        }
+        let child = self.spawn();
+        // back to the default of `spawn` and `status`.
+        if !stdin {
+            self.std.stdin(Stdio::inherit());
+        }
+        if !stdout {
+            self.std.stdout(Stdio::inherit());
+        }
+        if !stderr {
+            self.std.stderr(Stdio::inherit());
+        }
+        child?.wait_with_output().await
+    }
+}
+
+impl From<process::Command> for Command {
+    fn from(std: process::Command) -> Command {
+        Command {
+            std,
+            kill_on_drop: false,
+            stdio_set: [false; 3],
+        }
+    }
+}
+
+/// A spawned child process.
+///
+/// A child dropped before it exited is reaped in the background by the
+/// current runtime, so it does not linger as a zombie.
+pub struct Child {
+    pub stdin: Option<ChildStdin>,
+    pub stdout: Option<ChildStdout>,
+    pub stderr: Option<ChildStderr>,
+    child: process::Child,
+    // readable once the child exited.
+    pidfd: OwnedFd,
+    status: Option<ExitStatus>,
+    kill_on_drop: bool,
+}
+
+impl Child {
+    /// The pid of the child, `None` once it has been reaped.
+    pub fn id(&self) -> Option<u32> {
+        match self.status {
+            Some(_) => None,
+            None => Some(self.child.id()),
+        }
+    }
+
+    /// Waits for the child to exit, closing its stdin first so that it does
+    /// not wait for input.
+    ///
+    /// The pidfd of the child is polled through the ring, then the child is
+    /// reaped with `waitid`. `IORING_OP_WAITID` is not used.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            Op::<PollAdd>::poll_add(self.pidfd.as_raw_fd(), libc::POLLIN as u32)?.await?;
+        }
+    }
+
+    /// The exit status of the child if it exited, without blocking.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = self.child.try_wait()?;
+        }
+        Ok(self.status)
+    }
+
+    /// Sends `SIGKILL` to the child, without waiting for it to exit.
+    pub fn start_kill(&mut self) -> io::Result<()> {
+        if self.try_wait()?.is_some() {
+            return Ok(());
+        }
+        self.child.kill()
+    }
+
+    /// Kills the child and waits for it to exit.
+    pub async fn kill(&mut self) -> io::Result<()> {
+        self.start_kill()?;
+        self.wait().await?;
+        Ok(())
+    }
+
+    /// Waits for the child to exit, collecting its stdout and stderr.
+    pub async fn wait_with_output(mut self) -> io::Result<Output> {
+        drop(self.stdin.take());
+        let mut stdout = pin!(read_to_end(self.stdout.take()));
+        let mut stderr = pin!(read_to_end(self.stderr.take()));
+        let mut stdout_res = None;
+        let mut stderr_res = None;
+        poll_fn(|cx| {
+            if stdout_res.is_none() {
+                if let Poll::Ready(res) = stdout.as_mut().poll(cx) {
+                    stdout_res = Some(res);
+                }
+            }
+            if stderr_res.is_none() {
+                if let Poll::Ready(res) = stderr.as_mut().poll(cx) {
+                    stderr_res = Some(res);
+                }
+            }
+            if stdout_res.is_some() && stderr_res.is_some() {
+                return Poll::Ready(());
+            }
+            Poll::Pending
+        })
+        .await;
+        let status = self.wait().await?;
+        Ok(Output {
+            status,
+            stdout: stdout_res.unwrap()?,
+            stderr: stderr_res.unwrap()?,
+        })
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        if !matches!(self.try_wait(), Ok(None)) {
+            return;
+        }
+        if self.kill_on_drop {
+            let _ = self.child.kill();
+        }
+        if driver::with_current(|_| ()).is_none() {
+            return;
+        }
+        let pidfd = match self.pidfd.try_clone() {
+            Ok(pidfd) => pidfd,
+            Err(_) => return,
+        };
+        Handle::current().spawn_with(move || async move {
+            if let Ok(op) = Op::<PollAdd>::poll_add(pidfd.as_raw_fd(), libc::POLLIN as u32) {
+                let _ = op.await;
+            }
+            let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+            let _ = syscall!(waitid(
+                libc::P_PIDFD,
+                pidfd.as_raw_fd() as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOHANG,
+            ));
+        });
+    }
+}
+
+/// The stdin of a child, closed by `close` or when dropped.
+pub struct ChildStdin {
+    inner: Option<FdIo<OwnedFd>>,
+}
+
+/// The stdout of a child.
+pub struct ChildStdout {
+    inner: FdIo<OwnedFd>,
+}
+
+/// The stderr of a child.
+pub struct ChildStderr {
+    inner: FdIo<OwnedFd>,
+}
+
+impl AsyncWrite for ChildStdin {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut().inner.as_mut() {
+            Some(inner) => inner.poll_write(cx, buf),
+            None => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsRawFd for ChildStdin {
+    /// Returns -1 once closed.
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_ref().map_or(-1, AsRawFd::as_raw_fd)
+    }
+}
+
+macro_rules! impl_read_pipe {
+    ($ty: ident) => {
+        impl AsyncRead for $ty {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                self.get_mut().inner.poll_read(cx, buf)
+            }
+        }
+
+        impl AsyncBufRead for $ty {
+            fn poll_fill_buf(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+            ) -> Poll<io::Result<&[u8]>> {
+                self.get_mut().inner.poll_fill_buf(cx)
+            }
+
+            fn consume(self: Pin<&mut Self>, amt: usize) {
+                self.get_mut().inner.consume(amt);
+            }
+        }
+
+        impl AsRawFd for $ty {
+            fn as_raw_fd(&self) -> RawFd {
+                self.inner.as_raw_fd()
+            }
+        }
+    };
+}
+
+impl_read_pipe!(ChildStdout);
+impl_read_pipe!(ChildStderr);
+
+fn pipe(io: impl Into<OwnedFd>) -> FdIo<OwnedFd> {
+    FdIo::new(io.into())
+}
+
+fn pidfd_open(pid: u32) -> io::Result<OwnedFd> {
+    // the pidfd is opened with `O_CLOEXEC`.
+    let fd = syscall!(syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0))?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+async fn read_to_end<R: AsyncRead + Unpin>(io: Option<R>) -> io::Result<Vec<u8>> {
+    let mut io = match io {
+        Some(io) => io,
+        None => return Ok(Vec::new()),
+    };
+    let mut out = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        let n = poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut buf)).await?;
+        if n == 0 {
+            return Ok(out);
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn output_collects_stdout_and_stderr() {
+        let runtime = Runtime::new().unwrap();
+        let output = runtime
+            .block_on(
+                Command::new("sh")
+                    .args(["-c", "echo out; echo err >&2; exit 3"])
+                    .output(),
+            )
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn output_defaults_stdin_to_null() {
+        let runtime = Runtime::new().unwrap();
+        let output = runtime.block_on(Command::new("cat").output()).unwrap();
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+
+    #[test]
+    fn output_keeps_stdio_of_command() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut command = Command::new("echo");
+            command.arg("out").stdout(Stdio::null());
+            let output = command.output().await.unwrap();
+            assert!(output.stdout.is_empty());
+
+            let mut command = Command::new("true");
+            command.output().await.unwrap();
+            // the defaults of `output` do not stick to the command.
+            let mut child = command.spawn().unwrap();
+            assert!(child.stdout.is_none() && child.stderr.is_none());
+            assert!(child.wait().await.unwrap().success());
+        });
+    }
+
+    #[test]
+    fn kill_and_wait() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+            assert!(child.try_wait().unwrap().is_none());
+            child.kill().await.unwrap();
+            assert!(child.id().is_none());
+            let status = child.wait().await.unwrap();
+            assert!(!status.success());
+        });
+    }
+}
@@ -1,46 +1,504 @@
-use std::cell::Cell;
+use std::fmt;
 use std::future::Future;
 use std::io;
-use std::pin::pin;
-use std::task::{Context, Poll};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
 
-use crate::driver::Driver;
-use crate::local_executor;
+use pin_project_lite::pin_project;
+use scoped_tls::scoped_thread_local;
+
+use crate::blocking::{self, BlockingPool, JoinError, JoinHandle};
+use crate::coop;
+use crate::driver::{Driver, Unpark};
+use crate::local_executor::LocalSet;
+use crate::msg_ring::RuntimeHandle;
+use crate::time::driver::TimeDriver;
 use crate::waker_fn::waker_fn;
 
-pub(crate) struct Runtime {
+const DEFAULT_EVENT_INTERVAL: usize = 61;
+// How long I/O in flight may hold back a paused clock before it is advanced.
+const PAUSED_IO_GRACE: Duration = Duration::from_millis(10);
+
+scoped_thread_local!(static CURRENT: Arc<Remote>);
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+pub struct Builder {
+    event_interval: usize,
+    max_blocking_threads: usize,
+    thread_keep_alive: Duration,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder {
+            event_interval: DEFAULT_EVENT_INTERVAL,
+            max_blocking_threads: blocking::DEFAULT_MAX_THREADS,
+            thread_keep_alive: blocking::DEFAULT_KEEP_ALIVE,
+        }
+    }
+
+    /// Number of task polls after which the driver reaps completions without
+    /// blocking, so busy tasks cannot starve I/O and timers.
+    pub fn event_interval(&mut self, val: usize) -> &mut Builder {
+        assert!(val > 0, "`event_interval` must be non-zero.");
+        self.event_interval = val;
+        self
+    }
+
+    /// Upper limit of threads spawned for `spawn_blocking`, closures beyond
+    /// it are queued until a thread is free.
+    pub fn max_blocking_threads(&mut self, val: usize) -> &mut Builder {
+        assert!(val > 0, "`max_blocking_threads` must be non-zero.");
+        self.max_blocking_threads = val;
+        self
+    }
+
+    /// How long a blocking thread stays idle before it exits.
+    pub fn thread_keep_alive(&mut self, val: Duration) -> &mut Builder {
+        self.thread_keep_alive = val;
+        self
+    }
+
+    pub fn build(&self) -> io::Result<Runtime> {
+        let driver = Driver::new()?;
+        let local = LocalSet::new();
+        // tasks are run by the `block_on` loop, the set only needs waking for
+        // tasks scheduled from other threads.
+        local.set_waker(&unpark_waker(driver.unpark()));
+        let remote = Arc::new(Remote {
+            owner: thread::current().id(),
+            jobs: Mutex::new(Some(Vec::new())),
+            unpark: driver.unpark(),
+        });
+        Ok(Runtime {
+            local,
+            remote,
+            driver,
+            time: TimeDriver::new(),
+            blocking: BlockingPool::new(self.max_blocking_threads, self.thread_keep_alive),
+            event_interval: self.event_interval,
+        })
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// A single thread runtime.
+///
+/// Tasks spawned with `spawn_local` from within `block_on` belong to the
+/// runtime, they are kept across `block_on` calls and dropped with it.
+pub struct Runtime {
+    local: LocalSet,
+    remote: Arc<Remote>,
     driver: Driver,
+    time: TimeDriver,
+    blocking: BlockingPool,
+    event_interval: usize,
 }
 
 impl Runtime {
     pub fn new() -> io::Result<Runtime> {
-        Ok(Runtime {
-            driver: Driver::new()?,
-        })
+        Builder::new().build()
     }
 
     pub fn block_on<F>(&self, future: F) -> F::Output
     where
         F: Future,
     {
-        thread_local! {
-            static NOTIFIED: Cell<bool> = Cell::new(false);
-        }
         let mut future = pin!(future);
-        let waker = waker_fn(|| NOTIFIED.with(|notified| notified.set(true)));
+        let notified = Arc::new(AtomicBool::new(true));
+        let waker = {
+            let notified = notified.clone();
+            let unpark = unpark_waker(self.driver.unpark());
+            waker_fn(move || {
+                notified.store(true, Ordering::Release);
+                unpark.wake_by_ref();
+            })
+        };
         let cx = &mut Context::from_waker(&waker);
 
-        self.driver.with(|| loop {
-            if let Poll::Ready(output) = future.as_mut().poll(cx) {
-                return output;
+        self.enter(|| loop {
+            // cleared before polling, so that a wakeup consumed by the poll does
+            // not cause another one.
+            if notified.swap(false, Ordering::AcqRel) {
+                if let Poll::Ready(output) = coop::budget(|| future.as_mut().poll(cx)) {
+                    return output;
+                }
             }
-            if local_executor::tick() {
+            self.remote.run_jobs();
+            if self.local.tick(self.event_interval) {
+                self.park(false);
                 continue;
             }
-            if !NOTIFIED.with(Cell::get) {
-                self.driver.wait().expect("driver wait error");
+            self.park(!notified.load(Ordering::Acquire));
+        })
+    }
+
+    /// A handle to message this runtime from other runtimes.
+    pub fn runtime_handle(&self) -> RuntimeHandle {
+        self.driver.runtime_handle()
+    }
+
+    /// A handle to spawn tasks onto this runtime from any thread.
+    pub fn handle(&self) -> Handle {
+        Handle {
+            remote: self.remote.clone(),
+        }
+    }
+
+    fn enter<T>(&self, f: impl FnOnce() -> T) -> T {
+        CURRENT.set(&self.remote, || {
+            self.driver.with(|| {
+                self.time
+                    .with(|| self.blocking.enter(|| self.local.enter(f)))
+            })
+        })
+    }
+
+    // Reap completions and fire expired timers, blocking until one of them
+    // happens if `block` is set.
+    fn park(&self, block: bool) {
+        // with a paused clock, jump to the next timer rather than waiting for
+        // it, unless some I/O completed or a blocking closure is in flight. I/O
+        // in flight is given a grace period to complete first, timers and
+        // multishot ops are not waited on.
+        if block && self.time.is_paused() && self.blocking.is_idle() {
+            let reaped = if self.driver.has_tracked_ops() {
+                self.driver.wait_timeout(PAUSED_IO_GRACE)
+            } else {
+                self.driver.poll()
+            };
+            if reaped.expect("driver poll error") > 0 {
+                self.time.process();
+                return;
             }
-            NOTIFIED.with(|notified| notified.set(false));
+            if self.time.auto_advance() {
+                return;
+            }
+        }
+        if block {
+            self.time.arm().expect("time driver arm error");
+            self.driver.wait().expect("driver wait error");
+        } else {
+            self.driver.poll().expect("driver poll error");
+        }
+        self.time.process();
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // handles spawning from now on drop their futures right away.
+        let jobs = self.remote.jobs.lock().unwrap().take();
+        drop(jobs);
+    }
+}
+
+/// A handle to a runtime, it is `Send` and `Sync`.
+///
+/// The tasks spawned through it are run by the runtime while it is driven by
+/// `block_on`, and belong to it like the ones spawned with `spawn_local`.
+#[derive(Clone)]
+pub struct Handle {
+    remote: Arc<Remote>,
+}
+
+// The tasks spawned through handles, run by the runtime thread.
+struct Remote {
+    owner: ThreadId,
+    // `None` once the runtime is dropped.
+    jobs: Mutex<Option<Vec<Job>>>,
+    unpark: Arc<Unpark>,
+}
+
+impl Handle {
+    /// The handle of the runtime of the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a runtime context.
+    pub fn current() -> Handle {
+        if !CURRENT.is_set() {
+            panic!("`Handle::current` called from outside of a runtime context");
+        }
+        CURRENT.with(|remote| Handle {
+            remote: remote.clone(),
         })
     }
+
+    /// Spawns `future` onto the runtime.
+    ///
+    /// The returned `JoinHandle` resolves to its output and resumes its panic
+    /// if it panicked, it does not cancel the task when dropped. It fails if
+    /// the runtime is dropped before the task completes.
+    ///
+    /// Futures using the I/O or the timers of a runtime are `!Send`, they are
+    /// spawned with `spawn_with` instead.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.spawn_with(|| future)
+    }
+
+    /// Spawns the `!Send` future returned by `f`, which is called on the
+    /// runtime thread.
+    pub fn spawn_with<F, Fut>(&self, f: F) -> JoinHandle<Fut::Output>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let (tx, handle) = JoinHandle::new();
+        self.remote.push(Box::new(move || {
+            crate::spawn_local(async move {
+                let output = match panic::catch_unwind(AssertUnwindSafe(f)) {
+                    Ok(future) => CatchUnwind { future }.await,
+                    Err(err) => Err(err),
+                };
+                tx.send(output);
+            })
+            .detach();
+        }));
+        handle
+    }
+
+    /// Runs `future` on the runtime, blocking the current thread until it
+    /// completes.
+    ///
+    /// Fails if the runtime is dropped before the future completes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from the runtime thread, which could never run it.
+    pub fn block_on<F>(&self, future: F) -> Result<F::Output, JoinError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        if thread::current().id() == self.remote.owner {
+            panic!("`Handle::block_on` called from the thread of its runtime");
+        }
+        let mut handle = pin!(self.spawn(future));
+        let thread = thread::current();
+        let waker = waker_fn(move || thread.unpark());
+        let cx = &mut Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = handle.as_mut().poll(cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    /// Whether both handles refer to the same runtime.
+    pub fn same_runtime(&self, other: &Handle) -> bool {
+        Arc::ptr_eq(&self.remote, &other.remote)
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("owner", &self.remote.owner)
+            .finish()
+    }
+}
+
+impl Remote {
+    fn push(&self, job: Job) {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.as_mut() {
+            Some(jobs) => jobs.push(job),
+            None => return,
+        }
+        drop(jobs);
+        // also from the runtime thread, the job may be pushed by a task after
+        // the jobs were run and before the runtime parks.
+        self.unpark.unpark();
+    }
+
+    fn run_jobs(&self) {
+        let jobs = match self.jobs.lock().unwrap().as_mut() {
+            Some(jobs) if !jobs.is_empty() => mem::take(jobs),
+            _ => return,
+        };
+        for job in jobs {
+            job();
+        }
+    }
+}
+
+pin_project! {
+    // Catches the panics of a spawned future, to resume them in its handle.
+    struct CatchUnwind<F> {
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.project().future;
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+// Wakes the driver up when woken from another thread, the owner thread is
+// never blocked in the driver while it is running the waker.
+fn unpark_waker(unpark: Arc<Unpark>) -> Waker {
+    let owner = thread::current().id();
+    waker_fn(move || {
+        if thread::current().id() != owner {
+            unpark.unpark();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::future::poll_fn;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::task::yield_now;
+    use crate::time::delay_for;
+
+    // a future that keeps waking itself must not keep the driver from reaping
+    // completions, here the timer of a spawned task.
+    #[test]
+    fn self_waking_future_does_not_starve_driver() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let fired = Rc::new(Cell::new(false));
+            let task = crate::spawn_local({
+                let fired = fired.clone();
+                async move {
+                    delay_for(Duration::from_millis(5)).await.unwrap();
+                    fired.set(true);
+                }
+            });
+            let start = std::time::Instant::now();
+            while !fired.get() {
+                assert!(
+                    start.elapsed() < Duration::from_secs(5),
+                    "driver starved by a self-waking future"
+                );
+                yield_now().await;
+            }
+            task.await;
+        });
+    }
+
+    // same with the task waking itself, without ever returning `Pending` for
+    // long enough to block in the driver.
+    #[test]
+    fn self_waking_task_does_not_starve_driver() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut delay = pin!(delay_for(Duration::from_millis(5)));
+            let spinner = crate::spawn_local(async {
+                loop {
+                    yield_now().await;
+                }
+            });
+            let start = std::time::Instant::now();
+            while !poll_fn(|cx| Poll::Ready(delay.as_mut().poll(cx).is_ready())).await {
+                assert!(
+                    start.elapsed() < Duration::from_secs(5),
+                    "driver starved by a self-waking task"
+                );
+                yield_now().await;
+            }
+            drop(spinner);
+        });
+    }
+
+    // each `slings::block_on` call drops the tasks it left pending, a detached
+    // task of the first call does not run in the second one.
+    #[test]
+    fn block_on_drops_leftover_tasks() {
+        let (tx, rx) = crate::sync::oneshot::channel::<()>();
+        crate::block_on(async {
+            crate::spawn_local(async move {
+                delay_for(Duration::from_millis(1)).await.unwrap();
+                let _ = tx.send(());
+            })
+            .detach();
+        });
+        // the sender was dropped with its task.
+        assert!(crate::block_on(rx).is_err());
+    }
+
+    #[cfg(feature = "test-util")]
+    fn pipe() -> (crate::pipe::Receiver, std::fs::File) {
+        use std::os::unix::io::{FromRawFd, OwnedFd};
+
+        let mut fds = [0; 2];
+        syscall!(pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC)).unwrap();
+        unsafe {
+            (
+                crate::pipe::Receiver::from_owned_fd(OwnedFd::from_raw_fd(fds[0])),
+                std::fs::File::from_raw_fd(fds[1]),
+            )
+        }
+    }
+
+    // a paused clock is not moved while I/O about to complete is in flight.
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn auto_advance_waits_for_io() {
+        use std::io::Write;
+
+        use futures_util::AsyncReadExt;
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            crate::time::pause();
+            let (mut rx, mut tx) = pipe();
+            let writer = thread::spawn(move || {
+                thread::sleep(Duration::from_millis(2));
+                tx.write_all(b"x").unwrap();
+            });
+            let mut buf = [0; 1];
+            let res = crate::time::timeout(Duration::from_secs(1), rx.read(&mut buf)).await;
+            assert_eq!(res.unwrap().unwrap(), 1);
+            writer.join().unwrap();
+        });
+    }
+
+    // but I/O that does not complete does not hold it back forever.
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn auto_advance_with_io_in_flight() {
+        use futures_util::AsyncReadExt;
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            crate::time::pause();
+            let (mut rx, _tx) = pipe();
+            let mut buf = [0; 1];
+            let res = crate::time::timeout(Duration::from_secs(3600), rx.read(&mut buf)).await;
+            assert!(res.is_err());
+        });
+    }
 }
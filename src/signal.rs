@@ -0,0 +1,492 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::poll_fn;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::pin::Pin;
+use std::ptr;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+
+use futures_core::stream::Stream;
+
+use crate::coop;
+use crate::driver::{self, Op, Read};
+use crate::runtime::Handle;
+
+const SIGINFO_SIZE: usize = mem::size_of::<libc::signalfd_siginfo>();
+// siginfos read at once.
+const READ_BATCH: usize = 8;
+
+thread_local! {
+    static REGISTRY: RefCell<Option<Rc<Registry>>> = const { RefCell::new(None) };
+}
+
+/// A kind of signal, wrapping its number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignalKind(libc::c_int);
+
+impl SignalKind {
+    pub const fn from_raw(signum: libc::c_int) -> SignalKind {
+        SignalKind(signum)
+    }
+
+    pub const fn as_raw_value(&self) -> libc::c_int {
+        self.0
+    }
+
+    /// `SIGALRM`
+    pub const fn alarm() -> SignalKind {
+        SignalKind(libc::SIGALRM)
+    }
+
+    /// `SIGCHLD`
+    pub const fn child() -> SignalKind {
+        SignalKind(libc::SIGCHLD)
+    }
+
+    /// `SIGHUP`
+    pub const fn hangup() -> SignalKind {
+        SignalKind(libc::SIGHUP)
+    }
+
+    /// `SIGINT`
+    pub const fn interrupt() -> SignalKind {
+        SignalKind(libc::SIGINT)
+    }
+
+    /// `SIGIO`
+    pub const fn io() -> SignalKind {
+        SignalKind(libc::SIGIO)
+    }
+
+    /// `SIGPIPE`
+    pub const fn pipe() -> SignalKind {
+        SignalKind(libc::SIGPIPE)
+    }
+
+    /// `SIGQUIT`
+    pub const fn quit() -> SignalKind {
+        SignalKind(libc::SIGQUIT)
+    }
+
+    /// `SIGTERM`
+    pub const fn terminate() -> SignalKind {
+        SignalKind(libc::SIGTERM)
+    }
+
+    /// `SIGUSR1`
+    pub const fn user_defined1() -> SignalKind {
+        SignalKind(libc::SIGUSR1)
+    }
+
+    /// `SIGUSR2`
+    pub const fn user_defined2() -> SignalKind {
+        SignalKind(libc::SIGUSR2)
+    }
+
+    /// `SIGWINCH`
+    pub const fn window_change() -> SignalKind {
+        SignalKind(libc::SIGWINCH)
+    }
+}
+
+/// Listens for a kind of signal, created by `signal`.
+///
+/// Deliveries of the signal happening before the listener is polled again are
+/// coalesced into one.
+pub struct Signal {
+    registry: Rc<Registry>,
+    slot: Rc<RefCell<Slot>>,
+    // version of the slot last seen.
+    version: u64,
+    // key of the waker registered in `waiters`.
+    waiting: Option<u64>,
+}
+
+// The signalfd of a thread and the listeners of the signals it reads.
+struct Registry {
+    fd: OwnedFd,
+    state: RefCell<State>,
+}
+
+struct State {
+    mask: libc::sigset_t,
+    slots: BTreeMap<libc::c_int, Rc<RefCell<Slot>>>,
+    // whether a task is reading the signalfd.
+    reading: bool,
+    // the signalfd failed, listeners are closed.
+    failed: bool,
+}
+
+struct Slot {
+    // listeners of the signal, the slot is removed once they are all dropped.
+    listeners: usize,
+    // whether the signal was blocked by the registry rather than before it.
+    blocked: bool,
+    // bumped on each delivery.
+    version: u64,
+    // listeners waiting for a delivery, keyed by arrival order.
+    waiters: BTreeMap<u64, Waker>,
+    next_id: u64,
+}
+
+/// Listens for the signal `kind` on the current thread.
+///
+/// The signal is blocked on the calling thread, so that it is read from a
+/// signalfd rather than running its default action. A signal sent to the
+/// process goes to any thread not blocking it: listen before spawning other
+/// threads, they inherit the signal mask. The listeners of a thread share its
+/// signalfd, read by the runtime of the thread, and are all notified of a
+/// delivery. When several threads listen for the same signal, a delivery is
+/// only seen by one of them.
+///
+/// Once the last listener of the thread is dropped, the signal is removed
+/// from the signalfd and unblocked again, unless it was blocked before. A
+/// delivery pending then is discarded.
+///
+/// Fails for `SIGKILL`, `SIGSTOP` and invalid numbers, or if called outside
+/// of a runtime context.
+pub fn signal(kind: SignalKind) -> io::Result<Signal> {
+    let signum = kind.0;
+    if signum <= 0
+        || signum > libc::SIGRTMAX()
+        || signum == libc::SIGKILL
+        || signum == libc::SIGSTOP
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported signal {}", signum),
+        ));
+    }
+    if driver::with_current(|_| ()).is_none() {
+        return Err(io::Error::other(
+            "signal listened outside of a runtime context",
+        ));
+    }
+    let registry = Registry::current()?;
+    let slot = registry.add(signum)?;
+    registry.start_reading();
+    let version = slot.borrow().version;
+    Ok(Signal {
+        registry,
+        slot,
+        version,
+        waiting: None,
+    })
+}
+
+/// Completes on the next `SIGINT`, see `signal`.
+pub async fn ctrl_c() -> io::Result<()> {
+    let mut signal = signal(SignalKind::interrupt())?;
+    match signal.recv().await {
+        Some(()) => Ok(()),
+        None => Err(io::Error::other("signalfd read failed")),
+    }
+}
+
+impl Signal {
+    /// Waits for the next delivery of the signal.
+    ///
+    /// Returns `None` if reading the signalfd failed, no delivery is received
+    /// from then on.
+    pub async fn recv(&mut self) -> Option<()> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<()>> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let mut slot = self.slot.borrow_mut();
+        if slot.version != self.version {
+            self.version = slot.version;
+            coop.made_progress();
+            return Poll::Ready(Some(()));
+        }
+        if self.registry.state.borrow().failed {
+            coop.made_progress();
+            return Poll::Ready(None);
+        }
+        let id = match self.waiting {
+            Some(id) if slot.waiters.contains_key(&id) => id,
+            _ => {
+                let id = slot.next_id;
+                slot.next_id += 1;
+                self.waiting = Some(id);
+                id
+            }
+        };
+        slot.waiters.insert(id, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        let mut slot = self.slot.borrow_mut();
+        if let Some(id) = self.waiting {
+            slot.waiters.remove(&id);
+        }
+        slot.listeners -= 1;
+        if slot.listeners == 0 {
+            drop(slot);
+            self.registry.remove(&self.slot);
+        }
+    }
+}
+
+impl Stream for Signal {
+    type Item = ();
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<()>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl Registry {
+    // The registry of the current thread, created on first use.
+    fn current() -> io::Result<Rc<Registry>> {
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            if let Some(registry) = registry.as_ref() {
+                return Ok(registry.clone());
+            }
+            let mask = unsafe {
+                let mut mask = mem::zeroed();
+                libc::sigemptyset(&mut mask);
+                mask
+            };
+            // the fd must stay blocking, io_uring fails reads on a O_NONBLOCK
+            // fd with EAGAIN instead of waiting for them.
+            let fd = syscall!(signalfd(-1, &mask, libc::SFD_CLOEXEC))?;
+            let new = Rc::new(Registry {
+                fd: unsafe { OwnedFd::from_raw_fd(fd) },
+                state: RefCell::new(State {
+                    mask,
+                    slots: BTreeMap::new(),
+                    reading: false,
+                    failed: false,
+                }),
+            });
+            *registry = Some(new.clone());
+            Ok(new)
+        })
+    }
+
+    // Block `signum` and add it to the signalfd, returns its slot with a new
+    // listener counted.
+    fn add(&self, signum: libc::c_int) -> io::Result<Rc<RefCell<Slot>>> {
+        let mut state = self.state.borrow_mut();
+        if let Some(slot) = state.slots.get(&signum) {
+            slot.borrow_mut().listeners += 1;
+            return Ok(slot.clone());
+        }
+        let mut mask = state.mask;
+        let blocked = unsafe {
+            let block = sigset(signum);
+            let mut old = mem::zeroed();
+            let res = libc::pthread_sigmask(libc::SIG_BLOCK, &block, &mut old);
+            if res != 0 {
+                return Err(io::Error::from_raw_os_error(res));
+            }
+            libc::sigaddset(&mut mask, signum);
+            libc::sigismember(&old, signum) == 0
+        };
+        if let Err(err) = syscall!(signalfd(self.fd.as_raw_fd(), &mask, 0)) {
+            if blocked {
+                unblock(signum);
+            }
+            return Err(err);
+        }
+        state.mask = mask;
+        let slot = Rc::new(RefCell::new(Slot {
+            listeners: 1,
+            blocked,
+            version: 0,
+            waiters: BTreeMap::new(),
+            next_id: 0,
+        }));
+        state.slots.insert(signum, slot.clone());
+        Ok(slot)
+    }
+
+    // Remove the slot of the last listener dropped, restoring the mask.
+    fn remove(&self, slot: &Rc<RefCell<Slot>>) {
+        let mut state = self.state.borrow_mut();
+        let signum = match state.slots.iter().find(|(_, s)| Rc::ptr_eq(s, slot)) {
+            Some((&signum, _)) => signum,
+            None => return,
+        };
+        state.slots.remove(&signum);
+        unsafe { libc::sigdelset(&mut state.mask, signum) };
+        let _ = syscall!(signalfd(self.fd.as_raw_fd(), &state.mask, 0));
+        if slot.borrow().blocked {
+            unblock(signum);
+        }
+    }
+
+    // Spawn the task reading the signalfd onto the current runtime, unless it
+    // is running already.
+    fn start_reading(&self) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.reading || state.failed {
+                return;
+            }
+            state.reading = true;
+        }
+        // spawned through the handle so that it belongs to the runtime rather
+        // than to the `LocalSet` of the caller.
+        Handle::current().spawn_with(read_signals);
+    }
+
+    fn deliver(&self, signum: libc::c_int) {
+        let slot = match self.state.borrow().slots.get(&signum) {
+            Some(slot) => slot.clone(),
+            None => return,
+        };
+        let waiters = {
+            let mut slot = slot.borrow_mut();
+            slot.version += 1;
+            mem::take(&mut slot.waiters)
+        };
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+    }
+
+    fn fail(&self) {
+        self.state.borrow_mut().failed = true;
+        let slots: Vec<_> = self.state.borrow().slots.values().cloned().collect();
+        for slot in slots {
+            let waiters = mem::take(&mut slot.borrow_mut().waiters);
+            for waker in waiters.into_values() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+fn sigset(signum: libc::c_int) -> libc::sigset_t {
+    unsafe {
+        let mut set = mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, signum);
+        set
+    }
+}
+
+// Unblock `signum` on the current thread, discarding a pending delivery which
+// would otherwise run the default action of the signal.
+fn unblock(signum: libc::c_int) {
+    let set = sigset(signum);
+    let timeout = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        while libc::sigtimedwait(&set, ptr::null_mut(), &timeout) == signum {}
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &set, ptr::null_mut());
+    }
+}
+
+// Clears `reading` when the reading task ends or is dropped with its runtime.
+struct Reading(Rc<Registry>);
+
+impl Drop for Reading {
+    fn drop(&mut self) {
+        self.0.state.borrow_mut().reading = false;
+    }
+}
+
+async fn read_signals() {
+    let registry = match REGISTRY.with(|registry| registry.borrow().clone()) {
+        Some(registry) => registry,
+        None => return,
+    };
+    let reading = Reading(registry);
+    let registry = &reading.0;
+    loop {
+        let res =
+            match Op::<Read>::read(registry.fd.as_raw_fd(), (SIGINFO_SIZE * READ_BATCH) as u32) {
+                Ok(op) => op.await,
+                Err(err) => Err(err),
+            };
+        match res {
+            Ok(buf) => {
+                for info in buf.chunks_exact(SIGINFO_SIZE) {
+                    // `ssi_signo` is the first field.
+                    let signum = u32::from_ne_bytes(info[..4].try_into().unwrap());
+                    registry.deliver(signum as libc::c_int);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(_) => {
+                registry.fail();
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn is_blocked(signum: libc::c_int) -> bool {
+        unsafe {
+            let mut mask = mem::zeroed();
+            libc::pthread_sigmask(libc::SIG_BLOCK, ptr::null(), &mut mask);
+            libc::sigismember(&mask, signum) == 1
+        }
+    }
+
+    fn raise(signum: libc::c_int) {
+        unsafe { libc::pthread_kill(libc::pthread_self(), signum) };
+    }
+
+    #[test]
+    fn delivers_to_every_listener() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut a = signal(SignalKind::user_defined1()).unwrap();
+            let mut b = signal(SignalKind::user_defined1()).unwrap();
+            raise(libc::SIGUSR1);
+            assert_eq!(a.recv().await, Some(()));
+            assert_eq!(b.recv().await, Some(()));
+        });
+    }
+
+    #[test]
+    fn last_listener_restores_mask() {
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            assert!(!is_blocked(libc::SIGUSR2));
+            let a = signal(SignalKind::user_defined2()).unwrap();
+            let b = signal(SignalKind::user_defined2()).unwrap();
+            assert!(is_blocked(libc::SIGUSR2));
+            drop(a);
+            assert!(is_blocked(libc::SIGUSR2));
+            // a pending delivery is discarded rather than terminating the
+            // process once unblocked.
+            raise(libc::SIGUSR2);
+            drop(b);
+            assert!(!is_blocked(libc::SIGUSR2));
+            let registry = Registry::current().unwrap();
+            assert!(registry.state.borrow().slots.is_empty());
+        });
+    }
+
+    #[test]
+    fn keeps_signals_blocked_before() {
+        let runtime = Runtime::new().unwrap();
+        let set = sigset(libc::SIGWINCH);
+        unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) };
+        runtime.block_on(async {
+            drop(signal(SignalKind::window_change()).unwrap());
+        });
+        assert!(is_blocked(libc::SIGWINCH));
+        unsafe { libc::pthread_sigmask(libc::SIG_UNBLOCK, &set, ptr::null_mut()) };
+    }
+}
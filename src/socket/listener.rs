@@ -3,10 +3,11 @@ use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
 use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
-use std::path::Path;
 use std::pin::Pin;
 use std::task::{ready, Context, Poll};
 
+use socket2::SockAddr;
+
 use super::{Socket, SocketStorage};
 use crate::driver::{self, Op};
 
@@ -36,11 +37,8 @@ impl Listener {
         Ok(Listener::new(socket))
     }
 
-    pub(crate) fn bind_unix<P>(path: P) -> io::Result<Listener>
-    where
-        P: AsRef<Path>,
-    {
-        let socket = Socket::bind_unix(path, libc::SOCK_STREAM)?;
+    pub(crate) fn bind_unix(addr: &SockAddr, socket_type: libc::c_int) -> io::Result<Listener> {
+        let socket = Socket::bind_unix(addr, socket_type)?;
         socket.listen(1024)?;
         Ok(Listener::new(socket))
     }
@@ -125,17 +123,15 @@ impl Inner {
                 }
                 AcceptMultiState::Accepting(op) => {
                     if let Some(res) = op.get_mut().next() {
-                        let fd = res.result.map(|fd| fd as i32).map_err(|err| {
+                        let fd = res.result.map(|fd| fd as i32).inspect_err(|_| {
                             self.accept_multi = AcceptMultiState::Done;
-                            err
                         })?;
                         let socket = unsafe { Socket::from_raw_fd(fd) };
                         return Poll::Ready(Ok(socket));
                     }
                     let res = ready!(Pin::new(op).poll(cx));
-                    let fd = res.result.map(|fd| fd as i32).map_err(|err| {
+                    let fd = res.result.map(|fd| fd as i32).inspect_err(|_| {
                         self.accept_multi = AcceptMultiState::Done;
-                        err
                     })?;
                     let socket = unsafe { Socket::from_raw_fd(fd) };
                     self.accept_multi = AcceptMultiState::Idle;
@@ -10,8 +10,7 @@ pub(crate) use stream::Stream;
 use std::io;
 use std::mem;
 use std::net::SocketAddr;
-use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
-use std::path::Path;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
 
 use socket2::SockAddr;
 
@@ -33,46 +32,59 @@ fn get_domain(socket_addr: SocketAddr) -> libc::c_int {
 
 impl Socket {
     pub(crate) fn new(socket_addr: SocketAddr, socket_type: libc::c_int) -> io::Result<Socket> {
-        let socket_type = socket_type | libc::SOCK_CLOEXEC;
-        let domain = get_domain(socket_addr);
-        let fd = socket2::Socket::new(domain.into(), socket_type.into(), None)?.into_raw_fd();
-        Ok(Socket { fd })
+        Socket::new_domain(get_domain(socket_addr), socket_type)
     }
 
     pub(crate) fn new_unix(socket_type: libc::c_int) -> io::Result<Socket> {
+        Socket::new_domain(libc::AF_UNIX, socket_type)
+    }
+
+    pub(crate) fn new_domain(domain: libc::c_int, socket_type: libc::c_int) -> io::Result<Socket> {
         let socket_type = socket_type | libc::SOCK_CLOEXEC;
-        let domain = libc::AF_UNIX;
         let fd = socket2::Socket::new(domain.into(), socket_type.into(), None)?.into_raw_fd();
         Ok(Socket { fd })
     }
 
+    pub(crate) fn pair(socket_type: libc::c_int) -> io::Result<(Socket, Socket)> {
+        let socket_type = socket_type | libc::SOCK_CLOEXEC;
+        let (a, b) = socket2::Socket::pair(libc::AF_UNIX.into(), socket_type.into(), None)?;
+        Ok((
+            Socket {
+                fd: a.into_raw_fd(),
+            },
+            Socket {
+                fd: b.into_raw_fd(),
+            },
+        ))
+    }
+
+    /// Binds a new socket to `socket_addr`. `SO_REUSEADDR` is set for stream
+    /// sockets, so that a restarted listener does not trip over connections
+    /// of the previous one left in `TIME_WAIT`. Datagram sockets go without,
+    /// which would let another socket bind the same port.
     pub(crate) fn bind(socket_addr: SocketAddr, socket_type: libc::c_int) -> io::Result<Socket> {
-        Self::bind_internal(
-            socket_addr.into(),
-            get_domain(socket_addr).into(),
-            socket_type.into(),
-        )
+        let socket = Socket::new(socket_addr, socket_type)?;
+        if socket_type == libc::SOCK_STREAM {
+            socket.sock_ref().set_reuse_address(true)?;
+        }
+        socket.bind_addr(&SockAddr::from(socket_addr))?;
+        Ok(socket)
+    }
+
+    pub(crate) fn bind_unix(addr: &SockAddr, socket_type: libc::c_int) -> io::Result<Socket> {
+        let socket = Socket::new_unix(socket_type)?;
+        socket.bind_addr(addr)?;
+        Ok(socket)
     }
 
-    pub(crate) fn bind_unix<P: AsRef<Path>>(
-        path: P,
-        socket_type: libc::c_int,
-    ) -> io::Result<Socket> {
-        let addr = socket2::SockAddr::unix(path.as_ref())?;
-        Socket::bind_internal(addr, libc::AF_UNIX.into(), socket_type.into())
+    pub(crate) fn bind_addr(&self, addr: &SockAddr) -> io::Result<()> {
+        syscall!(bind(self.as_raw_fd(), addr.as_ptr(), addr.len()))?;
+        Ok(())
     }
 
-    fn bind_internal(
-        socket_addr: socket2::SockAddr,
-        domain: socket2::Domain,
-        socket_type: socket2::Type,
-    ) -> io::Result<Socket> {
-        let sys_listener = socket2::Socket::new(domain, socket_type, None)?;
-        sys_listener.set_reuse_port(true)?;
-        sys_listener.set_reuse_address(true)?;
-        sys_listener.bind(&socket_addr)?;
-        let fd = sys_listener.into_raw_fd();
-        Ok(Self { fd })
+    /// Gives access to the socket options socket2 knows about.
+    pub(crate) fn sock_ref(&self) -> socket2::SockRef<'_> {
+        socket2::SockRef::from(self)
     }
 
     pub(crate) fn listen(&self, backlog: libc::c_int) -> io::Result<()> {
@@ -96,6 +108,95 @@ impl Socket {
             nodelay as libc::c_int,
         )
     }
+
+    pub(crate) fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        if self.is_ipv6()? {
+            self.sock_ref().set_unicast_hops_v6(ttl)
+        } else {
+            self.sock_ref().set_ttl(ttl)
+        }
+    }
+
+    pub(crate) fn ttl(&self) -> io::Result<u32> {
+        if self.is_ipv6()? {
+            self.sock_ref().unicast_hops_v6()
+        } else {
+            self.sock_ref().ttl()
+        }
+    }
+
+    pub(crate) fn set_tos(&self, tos: u32) -> io::Result<()> {
+        if self.is_ipv6()? {
+            self.sock_ref().set_tclass_v6(tos)
+        } else {
+            self.sock_ref().set_tos(tos)
+        }
+    }
+
+    pub(crate) fn tos(&self) -> io::Result<u32> {
+        if self.is_ipv6()? {
+            self.sock_ref().tclass_v6()
+        } else {
+            self.sock_ref().tos()
+        }
+    }
+
+    fn is_ipv6(&self) -> io::Result<bool> {
+        Ok(self.sock_ref().domain()? == socket2::Domain::IPV6)
+    }
+
+    pub(crate) fn set_notsent_lowat(&self, lowat: u32) -> io::Result<()> {
+        setsockopt(
+            self.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_NOTSENT_LOWAT,
+            lowat as libc::c_int,
+        )
+    }
+
+    pub(crate) fn notsent_lowat(&self) -> io::Result<u32> {
+        let lowat: libc::c_int =
+            getsockopt(self.as_raw_fd(), libc::IPPROTO_TCP, libc::TCP_NOTSENT_LOWAT)?;
+        Ok(lowat as u32)
+    }
+
+    pub(crate) fn tcp_info(&self) -> io::Result<libc::tcp_info> {
+        getsockopt(self.as_raw_fd(), libc::IPPROTO_TCP, libc::TCP_INFO)
+    }
+
+    pub(crate) fn peer_cred(&self) -> io::Result<libc::ucred> {
+        getsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PEERCRED)
+    }
+
+    pub(crate) fn peer_pidfd(&self) -> io::Result<OwnedFd> {
+        let fd: libc::c_int = getsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PEERPIDFD)
+            .map_err(|err| {
+                match err.raw_os_error() {
+                    // kernels before 6.5 do not know the option.
+                    Some(libc::ENOPROTOOPT) => io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "SO_PEERPIDFD is not supported by the kernel",
+                    ),
+                    _ => err,
+                }
+            })?;
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    pub(crate) fn set_passcred(&self, passcred: bool) -> io::Result<()> {
+        setsockopt(
+            self.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PASSCRED,
+            passcred as libc::c_int,
+        )
+    }
+
+    pub(crate) fn passcred(&self) -> io::Result<bool> {
+        let passcred: libc::c_int =
+            getsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PASSCRED)?;
+        Ok(passcred != 0)
+    }
 }
 
 fn setsockopt<T>(
@@ -115,6 +216,19 @@ fn setsockopt<T>(
     Ok(())
 }
 
+fn getsockopt<T>(sock: libc::c_int, opt: libc::c_int, val: libc::c_int) -> io::Result<T> {
+    let mut payload: T = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<T>() as libc::socklen_t;
+    syscall!(getsockopt(
+        sock,
+        opt,
+        val,
+        &mut payload as *mut T as *mut libc::c_void,
+        &mut len,
+    ))?;
+    Ok(payload)
+}
+
 pub(crate) fn sockname<F>(f: F) -> io::Result<SocketAddr>
 where
     F: FnOnce(*mut libc::sockaddr, *mut libc::socklen_t) -> io::Result<libc::c_int>,
@@ -139,6 +253,12 @@ impl Drop for Socket {
     }
 }
 
+impl AsFd for Socket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.fd) }
+    }
+}
+
 impl AsRawFd for Socket {
     fn as_raw_fd(&self) -> RawFd {
         self.fd
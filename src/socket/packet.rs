@@ -1,7 +1,6 @@
 use std::cell::RefCell;
 use std::future::Future;
-use std::io;
-use std::net::SocketAddr;
+use std::io::{self, IoSlice};
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::pin::Pin;
 use std::task::{ready, Context, Poll};
@@ -9,7 +8,7 @@ use std::task::{ready, Context, Poll};
 use socket2::SockAddr;
 
 use super::Socket;
-use crate::driver::{self, Op};
+use crate::driver::{self, check_ctrunc, Ancillary, Op};
 
 pub(crate) struct Packet {
     inner: RefCell<Inner>,
@@ -51,11 +50,42 @@ impl Packet {
         &self,
         cx: &mut Context,
         buf: &[u8],
-        addr: SocketAddr,
+        addr: &SockAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.inner.borrow_mut().poll_sendmsg(
+            cx,
+            &[IoSlice::new(buf)],
+            Some(addr),
+            &[],
+            self.io.as_raw_fd(),
+        )
+    }
+
+    /// Sends the slices as one datagram.
+    pub(crate) fn poll_send_vectored_to(
+        &self,
+        cx: &mut Context,
+        bufs: &[IoSlice<'_>],
+        addr: &SockAddr,
     ) -> Poll<io::Result<usize>> {
         self.inner
             .borrow_mut()
-            .poll_send_to(cx, buf, addr, self.io.as_raw_fd())
+            .poll_sendmsg(cx, bufs, Some(addr), &[], self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_send_with_fds(
+        &self,
+        cx: &mut Context,
+        buf: &[u8],
+        fds: &[RawFd],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.borrow_mut().poll_sendmsg(
+            cx,
+            &[IoSlice::new(buf)],
+            None,
+            fds,
+            self.io.as_raw_fd(),
+        )
     }
 
     pub(crate) fn poll_recv(&self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
@@ -74,10 +104,33 @@ impl Packet {
         &self,
         cx: &mut Context,
         buf: &mut [u8],
-    ) -> Poll<io::Result<(usize, SocketAddr)>> {
-        self.inner
-            .borrow_mut()
-            .poll_recv_from(cx, buf, self.io.as_raw_fd())
+    ) -> Poll<io::Result<(usize, SockAddr)>> {
+        let (n, _, addr, _) =
+            ready!(self
+                .inner
+                .borrow_mut()
+                .poll_recvmsg(cx, buf, false, self.io.as_raw_fd()))?;
+        Poll::Ready(Ok((n, addr)))
+    }
+
+    /// Returns the length written to `buf`, whether the message was truncated
+    /// to fit it, and the control messages if `ancillary`.
+    ///
+    /// With `ancillary`, fails if the fds passed along were lost, because the
+    /// control messages were truncated or a dropped receive left an op without
+    /// room for them in flight.
+    pub(crate) fn poll_recv_msg(
+        &self,
+        cx: &mut Context,
+        buf: &mut [u8],
+        ancillary: bool,
+    ) -> Poll<io::Result<(usize, bool, Ancillary)>> {
+        let (n, truncated, _, ancillary) =
+            ready!(self
+                .inner
+                .borrow_mut()
+                .poll_recvmsg(cx, buf, ancillary, self.io.as_raw_fd()))?;
+        Poll::Ready(Ok((n, truncated, ancillary)))
     }
 }
 
@@ -128,17 +181,19 @@ impl Inner {
         }
     }
 
-    fn poll_send_to(
+    fn poll_sendmsg(
         &mut self,
         cx: &mut Context,
-        buf: &[u8],
-        addr: SocketAddr,
+        bufs: &[IoSlice<'_>],
+        addr: Option<&SockAddr>,
+        fds: &[RawFd],
         fd: RawFd,
     ) -> Poll<io::Result<usize>> {
         loop {
             match &mut self.send_to {
                 SendMsgState::Idle => {
-                    self.send_to = SendMsgState::Sending(Op::sendmsg(fd, buf, addr)?);
+                    self.send_to =
+                        SendMsgState::Sending(Op::sendmsg(fd, bufs, addr.cloned(), fds)?);
                 }
                 SendMsgState::Sending(op) => {
                     let n = ready!(Pin::new(op).poll(cx))?;
@@ -162,7 +217,7 @@ impl Inner {
                 }
                 RecvState::Recving(op) => {
                     let buf1 = ready!(Pin::new(op).poll(cx))?;
-                    let n = buf1.len();
+                    let n = buf1.len().min(buf.len());
                     buf[..n].copy_from_slice(&buf1[..n]);
                     self.recv = RecvState::Idle;
                     return Poll::Ready(Ok(n));
@@ -171,23 +226,42 @@ impl Inner {
         }
     }
 
-    fn poll_recv_from(
+    fn poll_recvmsg(
         &mut self,
         cx: &mut Context,
         buf: &mut [u8],
+        ancillary: bool,
         fd: RawFd,
-    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+    ) -> Poll<io::Result<(usize, bool, SockAddr, Ancillary)>> {
         loop {
             match &mut self.recv_from {
                 RecvMsgState::Idle => {
-                    self.recv_from = RecvMsgState::Recving(Op::recvmsg(fd, buf.len())?);
+                    self.recv_from = RecvMsgState::Recving {
+                        op: Op::recvmsg(fd, buf.len(), ancillary)?,
+                        len: buf.len(),
+                    };
                 }
-                RecvMsgState::Recving(op) => {
-                    let (buf1, addr) = ready!(Pin::new(op).poll(cx))?;
-                    let n = buf1.len();
-                    buf[..n].copy_from_slice(&buf1[..n]);
+                RecvMsgState::Recving { op, len } => {
+                    let res = ready!(Pin::new(op).poll(cx));
+                    let len = *len;
                     self.recv_from = RecvMsgState::Idle;
-                    return Poll::Ready(Ok((n, addr)));
+                    let (buf1, addr, ancillary1, msg_flags) = res?;
+                    // the op may have been left in flight by a dropped receive
+                    // of another kind, without room for the control messages
+                    // or with a smaller buffer.
+                    if ancillary {
+                        check_ctrunc(msg_flags)?;
+                    }
+                    let truncated = msg_flags & libc::MSG_TRUNC != 0;
+                    if truncated && len < buf.len() {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "message truncated by an earlier receive",
+                        )));
+                    }
+                    let n = buf1.len().min(buf.len());
+                    buf[..n].copy_from_slice(&buf1[..n]);
+                    return Poll::Ready(Ok((n, truncated || n < buf1.len(), addr, ancillary1)));
                 }
             }
         }
@@ -206,19 +280,17 @@ impl Inner {
                 }
                 RecvMultiState::Recving(op) => {
                     if let Some(buf1) = op.get_mut().next() {
-                        let buf1 = buf1.map_err(|err| {
+                        let buf1 = buf1.inspect_err(|_| {
                             self.recv_multi = RecvMultiState::Done;
-                            err
                         })?;
-                        let n = buf1.len();
+                        let n = buf1.len().min(buf.len());
                         buf[..n].copy_from_slice(&buf1[..n]);
                         return Poll::Ready(Ok(n));
                     }
-                    let buf1 = ready!(Pin::new(&mut *op).poll(cx)).map_err(|err| {
+                    let buf1 = ready!(Pin::new(&mut *op).poll(cx)).inspect_err(|_| {
                         self.recv_multi = RecvMultiState::Done;
-                        err
                     })?;
-                    let n = buf1.len();
+                    let n = buf1.len().min(buf.len());
                     buf[..n].copy_from_slice(&buf1[..n]);
                     self.recv_multi = RecvMultiState::Idle;
                     return Poll::Ready(Ok(n));
@@ -254,7 +326,8 @@ enum RecvState {
 
 enum RecvMsgState {
     Idle,
-    Recving(Op<driver::RecvMsg>),
+    // the length of the buffer the op was submitted with.
+    Recving { op: Op<driver::RecvMsg>, len: usize },
 }
 
 enum RecvMultiState {
@@ -5,9 +5,13 @@ use std::io;
 use std::mem;
 use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
+use std::ptr;
+
+use socket2::SockAddr;
 
 use super::SocketStorage;
 
+#[derive(Clone)]
 pub struct SocketAddr {
     sockaddr: libc::sockaddr_un,
     socklen: libc::socklen_t,
@@ -70,6 +74,71 @@ impl SocketAddr {
         SocketAddr { sockaddr, socklen }
     }
 
+    /// The address of a socket file at `path`.
+    pub fn from_pathname<P: AsRef<Path>>(path: P) -> io::Result<SocketAddr> {
+        let name = path.as_ref().as_os_str().as_bytes();
+        if name.is_empty() || name.contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "paths must be non-empty and must not contain null bytes",
+            ));
+        }
+        // the path is null terminated.
+        SocketAddr::from_name(name, 0, 1)
+    }
+
+    /// The address `name` in the abstract namespace of Linux, which is not
+    /// backed by a file and goes away with the last socket bound to it.
+    pub fn from_abstract_name<N: AsRef<[u8]>>(name: N) -> io::Result<SocketAddr> {
+        // the name follows a null byte.
+        SocketAddr::from_name(name.as_ref(), 1, 0)
+    }
+
+    fn from_name(name: &[u8], start: usize, end: usize) -> io::Result<SocketAddr> {
+        let mut sockaddr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        if start + name.len() + end > sockaddr.sun_path.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "address is too long for a unix socket",
+            ));
+        }
+        sockaddr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        for (dst, src) in sockaddr.sun_path[start..].iter_mut().zip(name) {
+            *dst = *src as libc::c_char;
+        }
+        let socklen = path_offset(&sockaddr) + start + name.len() + end;
+        Ok(SocketAddr::from_parts(sockaddr, socklen as libc::socklen_t))
+    }
+
+    pub(crate) fn to_sockaddr(&self) -> SockAddr {
+        let (_, addr) = unsafe {
+            SockAddr::try_init(|storage, len| {
+                ptr::copy_nonoverlapping(
+                    &self.sockaddr as *const libc::sockaddr_un as *const u8,
+                    storage as *mut u8,
+                    self.socklen as usize,
+                );
+                *len = self.socklen;
+                Ok(())
+            })
+        }
+        .unwrap();
+        addr
+    }
+
+    pub(crate) fn from_sockaddr(addr: &SockAddr) -> SocketAddr {
+        let mut sockaddr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        let len = (addr.len() as usize).min(mem::size_of_val(&sockaddr));
+        unsafe {
+            ptr::copy_nonoverlapping(
+                addr.as_ptr() as *const u8,
+                &mut sockaddr as *mut libc::sockaddr_un as *mut u8,
+                len,
+            )
+        };
+        SocketAddr::from_parts(sockaddr, len as libc::socklen_t)
+    }
+
     pub fn is_unnamed(&self) -> bool {
         matches!(self.address(), AddressKind::Unnamed)
     }
@@ -120,3 +189,49 @@ impl From<SocketStorage> for SocketAddr {
         SocketAddr::from_parts(sockaddr, socklen)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::unix::{UnixListener, UnixStream};
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn abstract_name_round_trip() {
+        let name = format!("slings-abstract-{}", std::process::id());
+        let addr = SocketAddr::from_abstract_name(&name).unwrap();
+        assert_eq!(addr.as_abstract_namespace(), Some(name.as_bytes()));
+        assert!(addr.as_pathname().is_none());
+
+        let runtime = Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = UnixListener::bind_addr(&addr).unwrap();
+            let local = listener.local_addr().unwrap();
+            assert_eq!(local.as_abstract_namespace(), Some(name.as_bytes()));
+
+            let _stream = UnixStream::connect_addr(&addr).await.unwrap();
+            let (_, peer) = listener.accept().await.unwrap();
+            assert!(peer.is_unnamed());
+        });
+    }
+
+    #[test]
+    fn rejects_overlong_names() {
+        let len = mem::size_of::<libc::sockaddr_un>() - path_offset(&unsafe { mem::zeroed() });
+        // the null byte before an abstract name or after a path takes a byte.
+        assert!(SocketAddr::from_abstract_name(vec![b'a'; len - 1]).is_ok());
+        let err = SocketAddr::from_abstract_name(vec![b'a'; len])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let path = "a".repeat(len - 1);
+        let addr = SocketAddr::from_pathname(&path).unwrap();
+        assert_eq!(addr.as_pathname(), Some(Path::new(&path)));
+        let err = SocketAddr::from_pathname("a".repeat(len)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        assert!(SocketAddr::from_pathname("").is_err());
+        assert!(SocketAddr::from_pathname("a\0b").is_err());
+    }
+}
@@ -1,37 +1,35 @@
 use std::future::Future;
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::mem;
 use std::net;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::{ready, Context, Poll};
 
 use socket2::SockAddr;
 
 use super::Socket;
 use crate::buffer::Buf;
-use crate::driver::{self, Op};
+use crate::coop;
+use crate::driver::{self, check_ctrunc, Ancillary, Op};
 
 const DEFAULT_BUFFER_SIZE: u32 = 4096;
 
 pub(crate) struct Stream {
-    inner: Inner,
     io: Socket,
+    connect: ConnectState,
+    reader: Reader,
+    writer: Writer,
 }
 
 impl Stream {
     pub(crate) fn new(io: Socket) -> Stream {
         Stream {
             io,
-            inner: Inner {
-                read: Read {
-                    pos: 0,
-                    buf: None,
-                    state: ReadState::Idle,
-                },
-                write: WriteState::Idle,
-                shutdown: ShutdownState::Idle,
-                connect: ConnectState::Idle,
-            },
+            connect: ConnectState::Idle,
+            reader: Reader::new(),
+            writer: Writer::new(),
         }
     }
 
@@ -44,7 +42,21 @@ impl Stream {
         cx: &mut Context,
         addr: &SockAddr,
     ) -> Poll<io::Result<()>> {
-        self.inner.poll_connect(cx, self.io.as_raw_fd(), addr)
+        let fd = self.io.as_raw_fd();
+        loop {
+            match &mut self.connect {
+                ConnectState::Idle => {
+                    self.connect = ConnectState::Connecting(Op::connect(fd, addr.clone())?);
+                }
+                ConnectState::Connecting(op) => {
+                    ready!(Pin::new(op).poll(cx))?;
+                    self.connect = ConnectState::Done;
+                }
+                ConnectState::Done => {
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
     }
 
     pub(crate) fn poll_read(
@@ -52,23 +64,58 @@ impl Stream {
         cx: &mut Context,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        let src = ready!(self.inner.poll_fill_buf(cx, self.io.as_raw_fd()))?;
-        let n = buf.len().min(src.len());
-        buf[..n].copy_from_slice(&src[..n]);
-        self.inner.consume(n);
-        Poll::Ready(Ok(n))
+        self.reader.poll_read(cx, buf, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_read_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.reader
+            .poll_read_vectored(cx, bufs, self.io.as_raw_fd())
     }
 
     pub(crate) fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
-        self.inner.poll_fill_buf(cx, self.io.as_raw_fd())
+        self.reader.poll_fill_buf(cx, self.io.as_raw_fd())
     }
 
     pub(crate) fn consume(&mut self, amt: usize) {
-        self.inner.consume(amt)
+        self.reader.read.consume(amt)
     }
 
     pub(crate) fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
-        self.inner.poll_write(cx, buf, self.io.as_raw_fd())
+        self.writer.poll_write(cx, buf, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_write_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.writer
+            .poll_write_vectored(cx, bufs, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_send_with_fds(
+        &mut self,
+        cx: &mut Context,
+        buf: &[u8],
+        fds: &[RawFd],
+    ) -> Poll<io::Result<usize>> {
+        self.writer
+            .poll_send_with_fds(cx, buf, fds, self.io.as_raw_fd())
+    }
+
+    /// Returns the bytes buffered by earlier reads first, without control
+    /// messages.
+    pub(crate) fn poll_recv_ancillary(
+        &mut self,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, Ancillary)>> {
+        self.reader
+            .poll_recv_ancillary(cx, buf, self.io.as_raw_fd())
     }
 
     pub(crate) fn poll_shutdown(
@@ -76,20 +123,221 @@ impl Stream {
         cx: &mut Context,
         how: net::Shutdown,
     ) -> Poll<io::Result<()>> {
-        let how = match how {
-            net::Shutdown::Write => libc::SHUT_WR,
-            net::Shutdown::Read => libc::SHUT_RD,
-            net::Shutdown::Both => libc::SHUT_RDWR,
+        self.writer.poll_shutdown(cx, self.io.as_raw_fd(), how)
+    }
+
+    /// Borrows the read and write sides separately, so that both can be
+    /// polled at the same time.
+    pub(crate) fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        (
+            ReadHalf {
+                io: &self.io,
+                reader: &mut self.reader,
+            },
+            WriteHalf {
+                io: &self.io,
+                writer: &mut self.writer,
+            },
+        )
+    }
+
+    pub(crate) fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        let io = Rc::new(self.io);
+        (
+            OwnedReadHalf {
+                io: io.clone(),
+                reader: self.reader,
+            },
+            OwnedWriteHalf {
+                io,
+                writer: self.writer,
+                shutdown_on_drop: true,
+            },
+        )
+    }
+
+    /// Puts the halves of `into_split` back together, or returns them if
+    /// they come from different streams.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn reunite(
+        read: OwnedReadHalf,
+        mut write: OwnedWriteHalf,
+    ) -> Result<Stream, (OwnedReadHalf, OwnedWriteHalf)> {
+        if !Rc::ptr_eq(&read.io, &write.io) {
+            return Err((read, write));
+        }
+        write.shutdown_on_drop = false;
+        let writer = mem::replace(&mut write.writer, Writer::new());
+        drop(write);
+        let io = match Rc::try_unwrap(read.io) {
+            Ok(io) => io,
+            Err(_) => unreachable!("the halves hold the only references"),
         };
-        self.inner.poll_shutdown(cx, self.io.as_raw_fd(), how)
+        Ok(Stream {
+            io,
+            connect: ConnectState::Done,
+            reader: read.reader,
+            writer,
+        })
     }
 }
 
-struct Inner {
+pub(crate) struct ReadHalf<'a> {
+    io: &'a Socket,
+    reader: &'a mut Reader,
+}
+
+impl ReadHalf<'_> {
+    pub(crate) fn get_ref(&self) -> &Socket {
+        self.io
+    }
+
+    pub(crate) fn poll_read(
+        &mut self,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.reader.poll_read(cx, buf, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_read_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.reader
+            .poll_read_vectored(cx, bufs, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.reader.poll_fill_buf(cx, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn consume(&mut self, amt: usize) {
+        self.reader.read.consume(amt)
+    }
+}
+
+pub(crate) struct WriteHalf<'a> {
+    io: &'a Socket,
+    writer: &'a mut Writer,
+}
+
+impl WriteHalf<'_> {
+    pub(crate) fn get_ref(&self) -> &Socket {
+        self.io
+    }
+
+    pub(crate) fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.writer.poll_write(cx, buf, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_write_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.writer
+            .poll_write_vectored(cx, bufs, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_shutdown(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.writer
+            .poll_shutdown(cx, self.io.as_raw_fd(), net::Shutdown::Write)
+    }
+}
+
+pub(crate) struct OwnedReadHalf {
+    io: Rc<Socket>,
+    reader: Reader,
+}
+
+impl OwnedReadHalf {
+    pub(crate) fn get_ref(&self) -> &Socket {
+        &self.io
+    }
+
+    pub(crate) fn poll_read(
+        &mut self,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.reader.poll_read(cx, buf, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_read_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.reader
+            .poll_read_vectored(cx, bufs, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        self.reader.poll_fill_buf(cx, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn consume(&mut self, amt: usize) {
+        self.reader.read.consume(amt)
+    }
+}
+
+/// Shuts down the write direction when dropped, unless `shutdown_on_drop` was
+/// unset, so that the peer sees EOF while the read half is still in use.
+pub(crate) struct OwnedWriteHalf {
+    io: Rc<Socket>,
+    writer: Writer,
+    shutdown_on_drop: bool,
+}
+
+impl OwnedWriteHalf {
+    pub(crate) fn get_ref(&self) -> &Socket {
+        &self.io
+    }
+
+    pub(crate) fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.writer.poll_write(cx, buf, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_write_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.writer
+            .poll_write_vectored(cx, bufs, self.io.as_raw_fd())
+    }
+
+    pub(crate) fn poll_shutdown(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.writer
+            .poll_shutdown(cx, self.io.as_raw_fd(), net::Shutdown::Write)
+    }
+
+    pub(crate) fn set_shutdown_on_drop(&mut self, shutdown_on_drop: bool) {
+        self.shutdown_on_drop = shutdown_on_drop;
+    }
+}
+
+impl Drop for OwnedWriteHalf {
+    fn drop(&mut self) {
+        if self.shutdown_on_drop && !matches!(self.writer.shutdown, ShutdownState::Done) {
+            let _ = unsafe { libc::shutdown(self.io.as_raw_fd(), libc::SHUT_WR) };
+        }
+    }
+}
+
+struct Reader {
     read: Read,
+    readv: ReadvState,
+    recv_msg: RecvMsgState,
+}
+
+struct Writer {
     write: WriteState,
+    writev: WritevState,
+    send_msg: SendMsgState,
     shutdown: ShutdownState,
-    connect: ConnectState,
 }
 
 enum ConnectState {
@@ -103,6 +351,26 @@ enum WriteState {
     Writing(Op<driver::Write>),
 }
 
+enum WritevState {
+    Idle,
+    Writing(Op<driver::Writev>),
+}
+
+enum SendMsgState {
+    Idle,
+    Sending(Op<driver::SendMsg>),
+}
+
+enum RecvMsgState {
+    Idle,
+    Recving(Op<driver::RecvMsg>),
+}
+
+enum ReadvState {
+    Idle,
+    Reading(Op<driver::Readv>),
+}
+
 enum ReadState {
     Idle,
     Reading(Op<driver::Read>),
@@ -111,15 +379,51 @@ enum ReadState {
 struct Read {
     buf: Option<Buf>,
     pos: usize,
+    // bytes received by an op beyond the buffer of the call which polled it
+    // last, returned before `buf`.
+    rest: Vec<u8>,
+    rest_pos: usize,
     state: ReadState,
 }
 
 impl Read {
+    /// The bytes received but not consumed yet.
+    fn buffered(&self) -> &[u8] {
+        if self.rest_pos < self.rest.len() {
+            return &self.rest[self.rest_pos..];
+        }
+        match &self.buf {
+            Some(buf) => &buf[self.pos..],
+            None => &[],
+        }
+    }
+
+    /// Keeps `src[pos..]` to be returned by the next reads, after the bytes
+    /// kept already.
+    fn append_rest(&mut self, src: Vec<u8>, pos: usize) {
+        if self.rest_pos == self.rest.len() {
+            self.rest = src;
+            self.rest_pos = pos;
+        } else {
+            self.rest.extend_from_slice(&src[pos..]);
+        }
+        if self.rest_pos == self.rest.len() {
+            self.rest = Vec::new();
+            self.rest_pos = 0;
+        }
+    }
+
     fn poll_fill_buf(&mut self, cx: &mut Context, fd: RawFd) -> Poll<io::Result<&[u8]>> {
+        if self.rest_pos < self.rest.len() {
+            ready!(coop::poll_proceed(cx)).made_progress();
+            return Poll::Ready(Ok(&self.rest[self.rest_pos..]));
+        }
         loop {
             match &mut self.state {
                 ReadState::Idle => {
-                    if self.buf.is_some() && !self.buf.as_ref().unwrap()[self.pos..].is_empty() {
+                    if self.buf.as_ref().is_some_and(|buf| self.pos < buf.len()) {
+                        // buffered data does not hit the driver, charge the budget here.
+                        ready!(coop::poll_proceed(cx)).made_progress();
                         return Poll::Ready(Ok(&self.buf.as_ref().unwrap()[self.pos..]));
                     }
                     self.pos = 0;
@@ -141,7 +445,15 @@ impl Read {
     }
 
     fn consume(&mut self, amt: usize) {
-        self.pos += amt;
+        if self.rest_pos < self.rest.len() {
+            self.rest_pos += amt;
+            if self.rest_pos == self.rest.len() {
+                self.rest = Vec::new();
+                self.rest_pos = 0;
+            }
+        } else {
+            self.pos += amt;
+        }
     }
 }
 
@@ -151,55 +463,168 @@ enum ShutdownState {
     Done,
 }
 
-impl Inner {
-    fn poll_connect(
+impl Reader {
+    fn new() -> Reader {
+        Reader {
+            read: Read {
+                pos: 0,
+                buf: None,
+                rest: Vec::new(),
+                rest_pos: 0,
+                state: ReadState::Idle,
+            },
+            readv: ReadvState::Idle,
+            recv_msg: RecvMsgState::Idle,
+        }
+    }
+
+    /// Awaits the `readv` or `recvmsg` op a dropped call left in flight, and
+    /// keeps the bytes it read for the next reads. The fds passed along with
+    /// them are closed.
+    fn poll_in_flight(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        if let ReadvState::Reading(op) = &mut self.readv {
+            let res = ready!(Pin::new(&mut *op).poll(cx));
+            self.readv = ReadvState::Idle;
+            self.read.append_rest(res?, 0);
+        }
+        if let RecvMsgState::Recving(op) = &mut self.recv_msg {
+            let res = ready!(Pin::new(&mut *op).poll(cx));
+            self.recv_msg = RecvMsgState::Idle;
+            let (buf, _, _, _) = res?;
+            self.read.append_rest(buf, 0);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_fill_buf(&mut self, cx: &mut Context, fd: RawFd) -> Poll<io::Result<&[u8]>> {
+        ready!(self.poll_in_flight(cx))?;
+        self.read.poll_fill_buf(cx, fd)
+    }
+
+    fn poll_read(
         &mut self,
         cx: &mut Context,
+        buf: &mut [u8],
         fd: RawFd,
-        addr: &SockAddr,
-    ) -> Poll<io::Result<()>> {
+    ) -> Poll<io::Result<usize>> {
+        let src = ready!(self.poll_fill_buf(cx, fd))?;
+        let n = buf.len().min(src.len());
+        buf[..n].copy_from_slice(&src[..n]);
+        self.read.consume(n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_read_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+        fd: RawFd,
+    ) -> Poll<io::Result<usize>> {
+        // data already buffered, or another read in flight, comes first.
+        if !self.read.buffered().is_empty()
+            || matches!(self.read.state, ReadState::Reading(_))
+            || matches!(self.recv_msg, RecvMsgState::Recving(_))
+        {
+            let src = ready!(self.poll_fill_buf(cx, fd))?;
+            let n = copy_to_slices(src, bufs);
+            self.read.consume(n);
+            return Poll::Ready(Ok(n));
+        }
         loop {
-            match &mut self.connect {
-                ConnectState::Idle => {
-                    self.connect = ConnectState::Connecting(Op::connect(fd, addr.clone())?);
+            match &mut self.readv {
+                ReadvState::Idle => {
+                    self.readv = ReadvState::Reading(Op::readv(fd, bufs)?);
                 }
-                ConnectState::Connecting(op) => {
-                    ready!(Pin::new(op).poll(cx))?;
-                    self.connect = ConnectState::Done;
-                }
-                ConnectState::Done => {
-                    return Poll::Ready(Ok(()));
+                ReadvState::Reading(op) => {
+                    let src = ready!(Pin::new(&mut *op).poll(cx))?;
+                    self.readv = ReadvState::Idle;
+                    // the op may have been sized by an earlier call with larger
+                    // slices, the rest is returned by the next read.
+                    let n = copy_to_slices(&src, bufs);
+                    self.read.append_rest(src, n);
+                    return Poll::Ready(Ok(n));
                 }
             }
         }
     }
 
-    fn poll_shutdown(
+    fn poll_recv_ancillary(
         &mut self,
         cx: &mut Context,
+        buf: &mut [u8],
         fd: RawFd,
-        how: libc::c_int,
-    ) -> Poll<io::Result<()>> {
+    ) -> Poll<io::Result<(usize, Ancillary)>> {
+        // data already buffered, or another read in flight, comes first.
+        if !self.read.buffered().is_empty()
+            || matches!(self.read.state, ReadState::Reading(_))
+            || matches!(self.readv, ReadvState::Reading(_))
+        {
+            let src = ready!(self.poll_fill_buf(cx, fd))?;
+            let n = buf.len().min(src.len());
+            buf[..n].copy_from_slice(&src[..n]);
+            self.read.consume(n);
+            return Poll::Ready(Ok((n, Ancillary::default())));
+        }
         loop {
-            match &mut self.shutdown {
-                ShutdownState::Idle => {
-                    self.shutdown = ShutdownState::Shutdowning(Op::shutdown(fd, how)?);
-                }
-                ShutdownState::Shutdowning(op) => {
-                    ready!(Pin::new(op).poll(cx))?;
-                    self.shutdown = ShutdownState::Done;
+            match &mut self.recv_msg {
+                RecvMsgState::Idle => {
+                    self.recv_msg = RecvMsgState::Recving(Op::recvmsg(fd, buf.len(), true)?);
                 }
-                ShutdownState::Done => {
-                    return Poll::Ready(Ok(()));
+                RecvMsgState::Recving(op) => {
+                    let res = ready!(Pin::new(&mut *op).poll(cx));
+                    self.recv_msg = RecvMsgState::Idle;
+                    let (buf1, _, ancillary, msg_flags) = res?;
+                    // the bytes are kept for the next read, only the fds are lost.
+                    if let Err(err) = check_ctrunc(msg_flags) {
+                        self.read.append_rest(buf1, 0);
+                        return Poll::Ready(Err(err));
+                    }
+                    // the op may have been sized by an earlier call with a
+                    // larger buffer, the rest is returned by the next read.
+                    let n = buf1.len().min(buf.len());
+                    buf[..n].copy_from_slice(&buf1[..n]);
+                    self.read.append_rest(buf1, n);
+                    return Poll::Ready(Ok((n, ancillary)));
                 }
             }
         }
     }
+}
+
+impl Writer {
+    fn new() -> Writer {
+        Writer {
+            write: WriteState::Idle,
+            writev: WritevState::Idle,
+            send_msg: SendMsgState::Idle,
+            shutdown: ShutdownState::Idle,
+        }
+    }
+
+    // Waits for the writes left in flight by dropped futures, so that the data
+    // goes out in the order of the calls. Their results are lost with the
+    // futures.
+    fn poll_in_flight(&mut self, cx: &mut Context) -> Poll<()> {
+        if let WriteState::Writing(op) = &mut self.write {
+            let _ = ready!(Pin::new(&mut *op).poll(cx));
+            self.write = WriteState::Idle;
+        }
+        if let WritevState::Writing(op) = &mut self.writev {
+            let _ = ready!(Pin::new(&mut *op).poll(cx));
+            self.writev = WritevState::Idle;
+        }
+        if let SendMsgState::Sending(op) = &mut self.send_msg {
+            let _ = ready!(Pin::new(&mut *op).poll(cx));
+            self.send_msg = SendMsgState::Idle;
+        }
+        Poll::Ready(())
+    }
 
     fn poll_write(&mut self, cx: &mut Context, buf: &[u8], fd: RawFd) -> Poll<io::Result<usize>> {
         loop {
             match &mut self.write {
                 WriteState::Idle => {
+                    ready!(self.poll_in_flight(cx));
                     self.write = WriteState::Writing(Op::write(fd, buf)?);
                 }
                 WriteState::Writing(op) => {
@@ -211,11 +636,85 @@ impl Inner {
         }
     }
 
-    fn poll_fill_buf(&mut self, cx: &mut Context, fd: RawFd) -> Poll<io::Result<&[u8]>> {
-        self.read.poll_fill_buf(cx, fd)
+    fn poll_write_vectored(
+        &mut self,
+        cx: &mut Context,
+        bufs: &[IoSlice<'_>],
+        fd: RawFd,
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match &mut self.writev {
+                WritevState::Idle => {
+                    ready!(self.poll_in_flight(cx));
+                    self.writev = WritevState::Writing(Op::writev(fd, bufs)?);
+                }
+                WritevState::Writing(op) => {
+                    let n = ready!(Pin::new(&mut *op).poll(cx))?;
+                    self.writev = WritevState::Idle;
+                    return Poll::Ready(Ok(n));
+                }
+            }
+        }
     }
 
-    fn consume(&mut self, amt: usize) {
-        self.read.consume(amt);
+    fn poll_send_with_fds(
+        &mut self,
+        cx: &mut Context,
+        buf: &[u8],
+        fds: &[RawFd],
+        fd: RawFd,
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match &mut self.send_msg {
+                SendMsgState::Idle => {
+                    ready!(self.poll_in_flight(cx));
+                    self.send_msg =
+                        SendMsgState::Sending(Op::sendmsg(fd, &[IoSlice::new(buf)], None, fds)?);
+                }
+                SendMsgState::Sending(op) => {
+                    let res = ready!(Pin::new(&mut *op).poll(cx));
+                    self.send_msg = SendMsgState::Idle;
+                    return Poll::Ready(res);
+                }
+            }
+        }
+    }
+
+    fn poll_shutdown(
+        &mut self,
+        cx: &mut Context,
+        fd: RawFd,
+        how: net::Shutdown,
+    ) -> Poll<io::Result<()>> {
+        let how = match how {
+            net::Shutdown::Write => libc::SHUT_WR,
+            net::Shutdown::Read => libc::SHUT_RD,
+            net::Shutdown::Both => libc::SHUT_RDWR,
+        };
+        loop {
+            match &mut self.shutdown {
+                ShutdownState::Idle => {
+                    ready!(self.poll_in_flight(cx));
+                    self.shutdown = ShutdownState::Shutdowning(Op::shutdown(fd, how)?);
+                }
+                ShutdownState::Shutdowning(op) => {
+                    ready!(Pin::new(op).poll(cx))?;
+                    self.shutdown = ShutdownState::Done;
+                }
+                ShutdownState::Done => {
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
+    }
+}
+
+fn copy_to_slices(src: &[u8], bufs: &mut [IoSliceMut<'_>]) -> usize {
+    let mut n = 0;
+    for buf in bufs {
+        let len = buf.len().min(src.len() - n);
+        buf[..len].copy_from_slice(&src[n..n + len]);
+        n += len;
     }
+    n
 }
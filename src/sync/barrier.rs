@@ -0,0 +1,204 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+
+use crate::coop;
+
+/// A barrier letting tasks of the same thread wait for each other.
+///
+/// Once `n` tasks are waiting, they are all released in FIFO order and the
+/// barrier can be reused.
+pub struct Barrier {
+    n: usize,
+    state: RefCell<State>,
+    // local to the thread it was created on.
+    _local: PhantomData<Rc<()>>,
+}
+
+struct State {
+    arrived: usize,
+    // bumped every time the barrier releases its waiters.
+    generation: u64,
+    // waiters keyed by arrival order.
+    waiters: BTreeMap<u64, Waker>,
+    next_id: u64,
+}
+
+/// Returned by `Barrier::wait`, exactly one task per release is the leader.
+#[derive(Debug)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Creates a barrier releasing groups of `n` tasks, `0` is treated as `1`.
+    pub fn new(n: usize) -> Barrier {
+        Barrier {
+            n: n.max(1),
+            state: RefCell::new(State {
+                arrived: 0,
+                generation: 0,
+                waiters: BTreeMap::new(),
+                next_id: 0,
+            }),
+            _local: PhantomData,
+        }
+    }
+
+    /// Waits until `n` tasks are waiting on the barrier.
+    ///
+    /// A task stops counting towards `n` if its `wait` future is dropped
+    /// before the barrier releases it.
+    pub async fn wait(&self) -> BarrierWaitResult {
+        Wait {
+            barrier: self,
+            state: WaitState::Idle,
+        }
+        .await
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Barrier").field("n", &self.n).finish()
+    }
+}
+
+struct Wait<'a> {
+    barrier: &'a Barrier,
+    state: WaitState,
+}
+
+enum WaitState {
+    Idle,
+    Waiting { id: u64, generation: u64 },
+    Done,
+}
+
+impl Future for Wait<'_> {
+    type Output = BarrierWaitResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<BarrierWaitResult> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let this = &mut *self;
+        let waiters = {
+            let mut state = this.barrier.state.borrow_mut();
+            match this.state {
+                WaitState::Idle => {
+                    state.arrived += 1;
+                    if state.arrived < this.barrier.n {
+                        let id = state.next_id;
+                        state.next_id += 1;
+                        state.waiters.insert(id, cx.waker().clone());
+                        this.state = WaitState::Waiting {
+                            id,
+                            generation: state.generation,
+                        };
+                        return Poll::Pending;
+                    }
+                    state.arrived = 0;
+                    state.generation += 1;
+                    mem::take(&mut state.waiters)
+                }
+                WaitState::Waiting { id, generation } => {
+                    if state.generation == generation {
+                        let waker = state.waiters.get_mut(&id).expect("invalid waiter");
+                        if !waker.will_wake(cx.waker()) {
+                            *waker = cx.waker().clone();
+                        }
+                        return Poll::Pending;
+                    }
+                    this.state = WaitState::Done;
+                    coop.made_progress();
+                    return Poll::Ready(BarrierWaitResult(false));
+                }
+                WaitState::Done => panic!("`Barrier::wait` polled after completion"),
+            }
+        };
+        this.state = WaitState::Done;
+        coop.made_progress();
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+        Poll::Ready(BarrierWaitResult(true))
+    }
+}
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        if let WaitState::Waiting { id, generation } = self.state {
+            let mut state = self.barrier.state.borrow_mut();
+            if state.generation == generation {
+                state.arrived -= 1;
+                state.waiters.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn releases_once_n_tasks_wait() {
+        let barrier = Barrier::new(3);
+        for _ in 0..2 {
+            let mut a = pin!(barrier.wait());
+            let mut b = pin!(barrier.wait());
+            assert!(poll(a.as_mut()).is_pending());
+            assert!(poll(b.as_mut()).is_pending());
+            let leader = match poll(pin!(barrier.wait())) {
+                Poll::Ready(res) => res,
+                Poll::Pending => panic!("barrier not released"),
+            };
+            assert!(leader.is_leader());
+            for wait in [a, b] {
+                match poll(wait) {
+                    Poll::Ready(res) => assert!(!res.is_leader()),
+                    Poll::Pending => panic!("waiter not released"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dropped_waiter_does_not_count() {
+        let barrier = Barrier::new(2);
+        let mut dropped = Box::pin(barrier.wait());
+        assert!(poll(dropped.as_mut()).is_pending());
+        drop(dropped);
+        let mut wait = pin!(barrier.wait());
+        assert!(poll(wait.as_mut()).is_pending());
+        assert!(poll(pin!(barrier.wait())).is_ready());
+        assert!(poll(wait.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn wait_charges_budget() {
+        let barrier = Barrier::new(1);
+        coop::budget(|| {
+            let mut released = 0;
+            while poll(pin!(barrier.wait())).is_ready() {
+                released += 1;
+            }
+            assert_eq!(released, 128);
+        });
+    }
+}
@@ -0,0 +1,388 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::poll_fn;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+
+use futures_core::stream::Stream;
+
+use crate::coop;
+
+use self::error::{RecvError, SendError, TryRecvError};
+
+pub mod error {
+    use std::error::Error;
+    use std::fmt;
+
+    /// Returned by `send` when there is no receiver, with the value not sent.
+    #[derive(PartialEq, Eq, Clone, Copy)]
+    pub struct SendError<T>(pub T);
+
+    impl<T> fmt::Debug for SendError<T> {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt.debug_struct("SendError").finish_non_exhaustive()
+        }
+    }
+
+    impl<T> fmt::Display for SendError<T> {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(fmt, "channel closed")
+        }
+    }
+
+    impl<T> Error for SendError<T> {}
+
+    /// Returned by `recv`.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum RecvError {
+        /// All the senders are gone and every value was received.
+        Closed,
+        /// The receiver fell behind, the given number of the oldest values
+        /// were overwritten before it received them.
+        Lagged(u64),
+    }
+
+    impl fmt::Display for RecvError {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                RecvError::Closed => write!(fmt, "channel closed"),
+                RecvError::Lagged(n) => write!(fmt, "channel lagged by {}", n),
+            }
+        }
+    }
+
+    impl Error for RecvError {}
+
+    /// Returned by `try_recv`.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum TryRecvError {
+        /// No new value, but senders are still alive.
+        Empty,
+        /// All the senders are gone and every value was received.
+        Closed,
+        /// The receiver fell behind by the given number of values.
+        Lagged(u64),
+    }
+
+    impl fmt::Display for TryRecvError {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                TryRecvError::Empty => write!(fmt, "channel empty"),
+                TryRecvError::Closed => write!(fmt, "channel closed"),
+                TryRecvError::Lagged(n) => write!(fmt, "channel lagged by {}", n),
+            }
+        }
+    }
+
+    impl Error for TryRecvError {}
+}
+
+/// Creates a channel where every value sent is received by all the receivers.
+///
+/// Up to `capacity` values are kept for receivers lagging behind, past that
+/// the oldest value is overwritten and the lagging receivers are notified by a
+/// `Lagged` error.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel requires capacity > 0");
+    let shared = Rc::new(Shared {
+        capacity,
+        state: RefCell::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            head: 0,
+            tx_count: 1,
+            rx_count: 1,
+            waiters: BTreeMap::new(),
+            next_id: 0,
+        }),
+    });
+    let rx = Receiver {
+        shared: shared.clone(),
+        next: 0,
+        waiting: None,
+    };
+    (Sender { shared }, rx)
+}
+
+/// The sending half of a broadcast channel.
+pub struct Sender<T> {
+    shared: Rc<Shared<T>>,
+}
+
+/// A receiving half of a broadcast channel, created by `subscribe`.
+pub struct Receiver<T> {
+    shared: Rc<Shared<T>>,
+    // position of the next value to receive.
+    next: u64,
+    // key of the waker registered in `waiters`.
+    waiting: Option<u64>,
+}
+
+struct Shared<T> {
+    capacity: usize,
+    state: RefCell<State<T>>,
+}
+
+struct State<T> {
+    buffer: VecDeque<T>,
+    // position of the oldest value of `buffer`.
+    head: u64,
+    tx_count: usize,
+    rx_count: usize,
+    // receivers waiting for a value, keyed by arrival order.
+    waiters: BTreeMap<u64, Waker>,
+    next_id: u64,
+}
+
+impl<T> State<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends `value` to all the receivers, returns how many there are.
+    ///
+    /// Fails if there is no receiver, the value is given back in the error.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let (rx_count, waiters) = {
+            let mut state = self.shared.state.borrow_mut();
+            if state.rx_count == 0 {
+                return Err(SendError(value));
+            }
+            if state.buffer.len() == self.shared.capacity {
+                state.buffer.pop_front();
+                state.head += 1;
+            }
+            state.buffer.push_back(value);
+            (state.rx_count, mem::take(&mut state.waiters))
+        };
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+        Ok(rx_count)
+    }
+
+    /// Creates a receiver of the values sent from now on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.state.borrow_mut();
+        state.rx_count += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            next: state.tail(),
+            waiting: None,
+        }
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.shared.state.borrow().rx_count
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.shared.state.borrow_mut().tx_count += 1;
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = self.shared.state.borrow_mut();
+            state.tx_count -= 1;
+            if state.tx_count > 0 {
+                return;
+            }
+            mem::take(&mut state.waiters)
+        };
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Receives the next value.
+    ///
+    /// Fails with `Lagged` if values were overwritten before this receiver
+    /// got them, the next call then returns the oldest value still kept.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self.recv_ref() {
+            Ok(value) => Ok(value),
+            Err(TryRecvError::Empty) => {
+                let state = self.shared.state.borrow();
+                if state.tx_count == 0 {
+                    return Err(TryRecvError::Closed);
+                }
+                Err(TryRecvError::Empty)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates another receiver of the values sent from now on.
+    pub fn resubscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.state.borrow_mut();
+        state.rx_count += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            next: state.tail(),
+            waiting: None,
+        }
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let res = match self.recv_ref() {
+            Ok(value) => Ok(value),
+            Err(TryRecvError::Lagged(n)) => Err(RecvError::Lagged(n)),
+            Err(_) => {
+                let mut state = self.shared.state.borrow_mut();
+                if state.tx_count == 0 {
+                    Err(RecvError::Closed)
+                } else {
+                    let id = match self.waiting {
+                        Some(id) if state.waiters.contains_key(&id) => id,
+                        _ => {
+                            let id = state.next_id;
+                            state.next_id += 1;
+                            self.waiting = Some(id);
+                            id
+                        }
+                    };
+                    state.waiters.insert(id, cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        };
+        coop.made_progress();
+        Poll::Ready(res)
+    }
+
+    // The next buffered value, `Empty` if there is none whatever the senders.
+    fn recv_ref(&mut self) -> Result<T, TryRecvError> {
+        let state = self.shared.state.borrow();
+        if self.next < state.head {
+            let lagged = state.head - self.next;
+            self.next = state.head;
+            return Err(TryRecvError::Lagged(lagged));
+        }
+        match state.buffer.get((self.next - state.head) as usize) {
+            Some(value) => {
+                self.next += 1;
+                Ok(value.clone())
+            }
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.borrow_mut();
+        state.rx_count -= 1;
+        if let Some(id) = self.waiting {
+            state.waiters.remove(&id);
+        }
+    }
+}
+
+/// Yields the values received, `Lagged` errors included, and ends once the
+/// channel is closed.
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = Result<T, RecvError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.get_mut().poll_recv(cx)) {
+            Err(RecvError::Closed) => Poll::Ready(None),
+            res => Poll::Ready(Some(res)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn every_receiver_gets_every_value() {
+        let (tx, mut rx1) = channel(4);
+        let mut rx2 = tx.subscribe();
+        assert_eq!(tx.send(1), Ok(2));
+        assert_eq!(tx.send(2), Ok(2));
+        for rx in [&mut rx1, &mut rx2] {
+            assert_eq!(rx.try_recv(), Ok(1));
+            assert_eq!(rx.try_recv(), Ok(2));
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        }
+        // subscribers only see the values sent from then on.
+        let mut rx3 = rx1.resubscribe();
+        assert_eq!(rx3.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn lagged_receiver_skips_to_oldest() {
+        let (tx, mut rx) = channel(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(
+            poll(pin!(rx.recv())),
+            Poll::Ready(Err(RecvError::Lagged(3)))
+        );
+        assert_eq!(poll(pin!(rx.recv())), Poll::Ready(Ok(3)));
+        assert_eq!(rx.try_recv(), Ok(4));
+        tx.send(5).unwrap();
+        tx.send(6).unwrap();
+        tx.send(7).unwrap();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Lagged(1)));
+        assert_eq!(rx.try_recv(), Ok(6));
+    }
+
+    #[test]
+    fn close_semantics() {
+        let (tx, mut rx) = channel(2);
+        let tx2 = tx.clone();
+        tx.send(1).unwrap();
+        drop(tx);
+        assert_eq!(poll(pin!(rx.recv())), Poll::Ready(Ok(1)));
+        assert!(poll(pin!(rx.recv())).is_pending());
+        drop(tx2);
+        assert_eq!(poll(pin!(rx.recv())), Poll::Ready(Err(RecvError::Closed)));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+
+        let (tx, rx) = channel(2);
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+
+    #[test]
+    fn dropped_waiting_receiver_unregisters() {
+        let (tx, mut rx) = channel::<i32>(2);
+        let mut rx2 = tx.subscribe();
+        assert!(poll(pin!(rx2.recv())).is_pending());
+        drop(rx2);
+        assert!(tx.shared.state.borrow().waiters.is_empty());
+        assert_eq!(tx.send(1), Ok(1));
+        assert_eq!(rx.try_recv(), Ok(1));
+    }
+}
@@ -0,0 +1,286 @@
+use std::future::poll_fn;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_core::stream::Stream;
+
+use super::chan::{Chan, Reserve};
+use super::error::{SendError, TryRecvError, TrySendError};
+
+/// Creates a bounded channel buffering up to `buffer` values.
+///
+/// Senders wait for a free slot once the buffer is full, and are served in
+/// FIFO order as the receiver makes room.
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(buffer > 0, "mpsc bounded channel requires buffer > 0");
+    let chan = Rc::new(Chan::new(Some(buffer)));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// The sending half of a bounded channel.
+pub struct Sender<T> {
+    chan: Rc<Chan<T>>,
+}
+
+/// The receiving half of a bounded channel.
+pub struct Receiver<T> {
+    chan: Rc<Chan<T>>,
+}
+
+/// A slot reserved in a bounded channel, given back if dropped without sending.
+pub struct Permit<'a, T> {
+    chan: &'a Chan<T>,
+}
+
+impl<T> Sender<T> {
+    /// Sends `value`, waiting for a free slot if the buffer is full.
+    ///
+    /// Fails if the receiver is closed, the value is given back in the error.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match Reserve::new(&self.chan).await {
+            Ok(()) => self.chan.send_reserved(value).map_err(SendError),
+            Err(_) => Err(SendError(value)),
+        }
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        match self.chan.try_reserve() {
+            Ok(()) => self.chan.send_reserved(value).map_err(TrySendError::Closed),
+            Err(TrySendError::Full(())) => Err(TrySendError::Full(value)),
+            Err(TrySendError::Closed(())) => Err(TrySendError::Closed(value)),
+        }
+    }
+
+    /// Waits for a free slot, to send a value later without waiting.
+    pub async fn reserve(&self) -> Result<Permit<'_, T>, SendError<()>> {
+        Reserve::new(&self.chan).await?;
+        Ok(Permit { chan: &self.chan })
+    }
+
+    pub fn try_reserve(&self) -> Result<Permit<'_, T>, TrySendError<()>> {
+        self.chan.try_reserve()?;
+        Ok(Permit { chan: &self.chan })
+    }
+
+    /// Whether the receiver has been closed or dropped.
+    pub fn is_closed(&self) -> bool {
+        self.chan.is_closed()
+    }
+
+    /// Waits until the receiver is closed or dropped.
+    pub async fn closed(&self) {
+        poll_fn(|cx| self.chan.poll_closed(cx)).await
+    }
+
+    /// The number of free slots.
+    pub fn capacity(&self) -> usize {
+        self.chan.capacity()
+    }
+
+    pub fn max_capacity(&self) -> usize {
+        self.chan.max_capacity()
+    }
+
+    pub fn same_channel(&self, other: &Sender<T>) -> bool {
+        Rc::ptr_eq(&self.chan, &other.chan)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.chan.add_tx();
+        Sender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.drop_tx();
+    }
+}
+
+impl<T> Permit<'_, T> {
+    /// Sends `value` in the reserved slot, it is dropped right away if the
+    /// receiver was closed meanwhile.
+    pub fn send(self, value: T) {
+        let _ = self.chan.send_reserved(value);
+        mem::forget(self);
+    }
+}
+
+impl<T> Drop for Permit<'_, T> {
+    fn drop(&mut self) {
+        self.chan.release();
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value, `None` once all the senders are gone and the
+    /// buffer is drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.chan.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.chan.poll_recv(cx)
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Closes the channel without dropping the receiver, senders fail from now
+    /// on but the buffered values can still be received.
+    pub fn close(&mut self) {
+        self.chan.close();
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.close();
+        self.chan.clear();
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Waker;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn values_are_received_in_order() {
+        let (tx, mut rx) = channel(4);
+        for i in 0..4 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(matches!(tx.try_send(4), Err(TrySendError::Full(4))));
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Ok(i));
+        }
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn senders_wait_in_fifo_order() {
+        let (tx, mut rx) = channel(1);
+        tx.try_send(0).unwrap();
+        let mut first = pin!(tx.send(1));
+        let mut second = pin!(tx.send(2));
+        assert!(poll(first.as_mut()).is_pending());
+        assert!(poll(second.as_mut()).is_pending());
+
+        assert_eq!(rx.try_recv(), Ok(0));
+        // the freed slot is granted to `first`, even when `second` is
+        // polled first.
+        assert!(poll(second.as_mut()).is_pending());
+        assert!(tx.try_send(3).is_err());
+        assert!(poll(first.as_mut()).is_ready());
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert!(poll(second.as_mut()).is_ready());
+        assert_eq!(rx.try_recv(), Ok(2));
+    }
+
+    #[test]
+    fn dropping_granted_sender_frees_its_slot() {
+        let (tx, mut rx) = channel(1);
+        tx.try_send(0).unwrap();
+        let mut reserve = Box::pin(tx.reserve());
+        let mut send = pin!(tx.send(1));
+        assert!(poll(reserve.as_mut()).is_pending());
+        assert!(poll(send.as_mut()).is_pending());
+        assert_eq!(rx.try_recv(), Ok(0));
+        // granted, but never polled again.
+        drop(reserve);
+        assert!(poll(send.as_mut()).is_ready());
+        assert_eq!(rx.try_recv(), Ok(1));
+    }
+
+    #[test]
+    fn dropping_permit_frees_its_slot() {
+        let (tx, mut rx) = channel::<i32>(1);
+        let permit = tx.try_reserve().unwrap();
+        assert_eq!(tx.capacity(), 0);
+        drop(permit);
+        assert_eq!(tx.capacity(), 1);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn close_fails_senders_and_keeps_buffer() {
+        let (tx, mut rx) = channel(1);
+        tx.try_send(0).unwrap();
+        let mut send = pin!(tx.send(1));
+        assert!(poll(send.as_mut()).is_pending());
+        rx.close();
+        assert!(tx.is_closed());
+        assert!(matches!(
+            poll(send.as_mut()),
+            Poll::Ready(Err(SendError(1)))
+        ));
+        assert!(matches!(tx.try_send(2), Err(TrySendError::Closed(2))));
+        assert!(poll(pin!(tx.closed())).is_ready());
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn reserved_slot_fails_once_closed() {
+        let (tx, mut rx) = channel(1);
+        let value = Rc::new(());
+        let permit = tx.try_reserve().unwrap();
+        rx.close();
+        permit.send(value.clone());
+        // dropped right away rather than kept in the queue.
+        assert_eq!(Rc::strong_count(&value), 1);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, mut rx) = channel(1);
+        tx.try_send(0).unwrap();
+        let mut send = pin!(tx.send(1));
+        assert!(poll(send.as_mut()).is_pending());
+        // the freed slot is granted to `send`, then the receiver closes.
+        assert_eq!(rx.try_recv(), Ok(0));
+        rx.close();
+        assert!(matches!(
+            poll(send.as_mut()),
+            Poll::Ready(Err(SendError(1)))
+        ));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn receiver_ends_once_senders_are_gone() {
+        let (tx, mut rx) = channel(2);
+        let tx2 = tx.clone();
+        tx.try_send(0).unwrap();
+        drop(tx);
+        assert!(poll(pin!(rx.recv())).is_ready());
+        assert!(poll(pin!(rx.recv())).is_pending());
+        drop(tx2);
+        assert!(matches!(poll(pin!(rx.recv())), Poll::Ready(None)));
+    }
+}
@@ -0,0 +1,406 @@
+use std::cell::{RefCell, RefMut};
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::DerefMut;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll, Waker};
+
+use super::error::{SendError, TryRecvError, TrySendError};
+use crate::coop;
+
+/// The lock guarding the state of a channel.
+///
+/// A `RefCell` for the channels of a single thread, the remote channels use a
+/// mutex and hook the notifications to wake threads and runtimes.
+pub(crate) trait Lock<S> {
+    type Guard<'a>: DerefMut<Target = S>
+    where
+        Self: 'a;
+
+    fn new(state: S) -> Self;
+
+    fn lock(&self) -> Self::Guard<'_>;
+
+    /// Called after the waiting receiver was woken by a new value or by the
+    /// last sender leaving.
+    fn notify_rx(&self) {}
+
+    /// Called when a slot is free and no async sender waits for it, or with
+    /// `all` once the receiver is closed.
+    fn notify_tx(&self, _all: bool) {}
+}
+
+/// A lock senders can block a thread on until a slot is free.
+pub(crate) trait BlockingLock<S>: Lock<S> {
+    fn wait<'a>(&'a self, guard: Self::Guard<'a>) -> Self::Guard<'a>;
+}
+
+impl<S> Lock<S> for RefCell<S> {
+    type Guard<'a>
+        = RefMut<'a, S>
+    where
+        S: 'a;
+
+    fn new(state: S) -> RefCell<S> {
+        RefCell::new(state)
+    }
+
+    fn lock(&self) -> RefMut<'_, S> {
+        self.borrow_mut()
+    }
+}
+
+/// The state shared by the senders and the receiver of a channel.
+///
+/// A bounded channel hands its slots out to senders in FIFO order: a slot is
+/// reserved first, then filled with the value.
+pub(crate) struct Chan<T, L = RefCell<State<T>>> {
+    bound: Option<usize>,
+    state: L,
+    // the state owns the values, senders and receiver are `Send` if they are.
+    _marker: PhantomData<fn() -> T>,
+}
+
+pub(crate) struct State<T> {
+    queue: VecDeque<T>,
+    // slots reserved by senders which have not sent their value yet.
+    reserved: usize,
+    tx_count: usize,
+    rx_closed: bool,
+    rx_waker: Option<Waker>,
+    // senders waiting for a slot, keyed by arrival order.
+    send_waiters: BTreeMap<u64, Waker>,
+    // tasks waiting for the receiver to close.
+    closed_waiters: Vec<Waker>,
+    next_id: u64,
+}
+
+impl<T, L: Lock<State<T>>> Chan<T, L> {
+    pub(crate) fn new(bound: Option<usize>) -> Chan<T, L> {
+        Chan {
+            bound,
+            state: L::new(State {
+                queue: VecDeque::new(),
+                reserved: 0,
+                tx_count: 1,
+                rx_closed: false,
+                rx_waker: None,
+                send_waiters: BTreeMap::new(),
+                closed_waiters: Vec::new(),
+                next_id: 0,
+            }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The lock of the state, to reach its own hooks.
+    pub(crate) fn lock_ref(&self) -> &L {
+        &self.state
+    }
+
+    pub(crate) fn add_tx(&self) {
+        self.state.lock().tx_count += 1;
+    }
+
+    pub(crate) fn drop_tx(&self) {
+        let waker = {
+            let mut state = self.state.lock();
+            state.tx_count -= 1;
+            if state.tx_count > 0 {
+                return;
+            }
+            state.rx_waker.take()
+        };
+        self.wake_rx(waker);
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.state.lock().rx_closed
+    }
+
+    pub(crate) fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock();
+        if state.rx_closed {
+            return Poll::Ready(());
+        }
+        if !state.closed_waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            state.closed_waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    /// Free slots of a bounded channel.
+    pub(crate) fn capacity(&self) -> usize {
+        let state = self.state.lock();
+        match self.bound {
+            Some(bound) => bound - state.queue.len() - state.reserved,
+            None => usize::MAX,
+        }
+    }
+
+    pub(crate) fn max_capacity(&self) -> usize {
+        self.bound.unwrap_or(usize::MAX)
+    }
+
+    pub(crate) fn try_reserve(&self) -> Result<(), TrySendError<()>> {
+        let mut state = self.state.lock();
+        if state.rx_closed {
+            return Err(TrySendError::Closed(()));
+        }
+        if !state.send_waiters.is_empty() || !self.has_slot(&state) {
+            return Err(TrySendError::Full(()));
+        }
+        state.reserved += 1;
+        Ok(())
+    }
+
+    /// Push a value in the slot reserved for it.
+    ///
+    /// The value is given back if the receiver was closed since the slot was
+    /// reserved.
+    pub(crate) fn send_reserved(&self, value: T) -> Result<(), T> {
+        let waker = {
+            let mut state = self.state.lock();
+            state.reserved -= 1;
+            if state.rx_closed {
+                return Err(value);
+            }
+            state.queue.push_back(value);
+            state.rx_waker.take()
+        };
+        self.wake_rx(waker);
+        Ok(())
+    }
+
+    /// Push a value to an unbounded channel.
+    pub(crate) fn send_unbounded(&self, value: T) -> Result<(), SendError<T>> {
+        let waker = {
+            let mut state = self.state.lock();
+            if state.rx_closed {
+                return Err(SendError(value));
+            }
+            state.queue.push_back(value);
+            state.rx_waker.take()
+        };
+        self.wake_rx(waker);
+        Ok(())
+    }
+
+    /// Give back a reserved slot that was not used.
+    pub(crate) fn release(&self) {
+        self.state.lock().reserved -= 1;
+        self.grant();
+    }
+
+    pub(crate) fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let value = {
+            let mut state = self.state.lock();
+            match state.queue.pop_front() {
+                Some(value) => value,
+                None if state.tx_count == 0 || state.rx_closed => {
+                    coop.made_progress();
+                    return Poll::Ready(None);
+                }
+                None => {
+                    match &state.rx_waker {
+                        Some(waker) if waker.will_wake(cx.waker()) => {}
+                        _ => state.rx_waker = Some(cx.waker().clone()),
+                    }
+                    return Poll::Pending;
+                }
+            }
+        };
+        coop.made_progress();
+        self.grant();
+        Poll::Ready(Some(value))
+    }
+
+    pub(crate) fn try_recv(&self) -> Result<T, TryRecvError> {
+        let value = {
+            let mut state = self.state.lock();
+            match state.queue.pop_front() {
+                Some(value) => value,
+                None if state.tx_count == 0 || state.rx_closed => {
+                    return Err(TryRecvError::Disconnected);
+                }
+                None => return Err(TryRecvError::Empty),
+            }
+        };
+        self.grant();
+        Ok(value)
+    }
+
+    /// Close the receiving half, buffered values can still be received.
+    pub(crate) fn close(&self) {
+        let wakers = {
+            let mut state = self.state.lock();
+            state.rx_closed = true;
+            // queued senders leave the queue themselves, which tells them apart
+            // from the ones granted a slot.
+            let mut wakers: Vec<Waker> = state.send_waiters.values().cloned().collect();
+            wakers.append(&mut state.closed_waiters);
+            wakers
+        };
+        self.state.notify_tx(true);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Drop the buffered values once the receiver is gone.
+    pub(crate) fn clear(&self) {
+        let queue = mem::take(&mut self.state.lock().queue);
+        drop(queue);
+    }
+
+    fn has_slot(&self, state: &State<T>) -> bool {
+        match self.bound {
+            Some(bound) => state.queue.len() + state.reserved < bound,
+            None => true,
+        }
+    }
+
+    fn wake_rx(&self, waker: Option<Waker>) {
+        if let Some(waker) = waker {
+            waker.wake();
+            self.state.notify_rx();
+        }
+    }
+
+    // Hand the free slots out to the oldest waiting senders.
+    fn grant(&self) {
+        if self.bound.is_none() {
+            return;
+        }
+        let mut wakers = Vec::new();
+        let mut free = false;
+        {
+            let mut state = self.state.lock();
+            while !state.rx_closed && self.has_slot(&state) {
+                match state.send_waiters.pop_first() {
+                    Some((_, waker)) => {
+                        state.reserved += 1;
+                        wakers.push(waker);
+                    }
+                    None => {
+                        free = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if free {
+            self.state.notify_tx(false);
+        }
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl<T, L: BlockingLock<State<T>>> Chan<T, L> {
+    /// Push a value, blocking the thread until a slot is free.
+    ///
+    /// Blocked senders are served once no async sender waits for a slot.
+    pub(crate) fn blocking_send(&self, value: T) -> Result<(), SendError<T>> {
+        let waker = {
+            let mut state = self.state.lock();
+            loop {
+                if state.rx_closed {
+                    return Err(SendError(value));
+                }
+                if state.send_waiters.is_empty() && self.has_slot(&state) {
+                    break;
+                }
+                state = self.state.wait(state);
+            }
+            state.queue.push_back(value);
+            state.rx_waker.take()
+        };
+        self.wake_rx(waker);
+        Ok(())
+    }
+}
+
+/// Reserves a slot of a bounded channel.
+///
+/// Dropping it while queued leaves the queue, and a slot granted to it but not
+/// observed yet is handed to the next sender.
+pub(crate) struct Reserve<'a, T, L: Lock<State<T>> = RefCell<State<T>>> {
+    chan: &'a Chan<T, L>,
+    state: ReserveState,
+}
+
+enum ReserveState {
+    Idle,
+    Waiting(u64),
+    Done,
+}
+
+impl<T, L: Lock<State<T>>> Reserve<'_, T, L> {
+    pub(crate) fn new(chan: &Chan<T, L>) -> Reserve<'_, T, L> {
+        Reserve {
+            chan,
+            state: ReserveState::Idle,
+        }
+    }
+}
+
+impl<T, L: Lock<State<T>>> Future for Reserve<'_, T, L> {
+    type Output = Result<(), SendError<()>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let this = &mut *self;
+        let mut state = this.chan.state.lock();
+        match this.state {
+            ReserveState::Idle => {
+                if state.rx_closed {
+                    this.state = ReserveState::Done;
+                    return Poll::Ready(Err(SendError(())));
+                }
+                if state.send_waiters.is_empty() && this.chan.has_slot(&state) {
+                    state.reserved += 1;
+                    this.state = ReserveState::Done;
+                    coop.made_progress();
+                    return Poll::Ready(Ok(()));
+                }
+                let id = state.next_id;
+                state.next_id += 1;
+                state.send_waiters.insert(id, cx.waker().clone());
+                this.state = ReserveState::Waiting(id);
+                Poll::Pending
+            }
+            ReserveState::Waiting(id) => {
+                if state.rx_closed && state.send_waiters.remove(&id).is_some() {
+                    this.state = ReserveState::Done;
+                    return Poll::Ready(Err(SendError(())));
+                }
+                if let Some(waker) = state.send_waiters.get_mut(&id) {
+                    if !waker.will_wake(cx.waker()) {
+                        *waker = cx.waker().clone();
+                    }
+                    return Poll::Pending;
+                }
+                this.state = ReserveState::Done;
+                coop.made_progress();
+                Poll::Ready(Ok(()))
+            }
+            ReserveState::Done => panic!("`Reserve` polled after completion"),
+        }
+    }
+}
+
+impl<T, L: Lock<State<T>>> Drop for Reserve<'_, T, L> {
+    fn drop(&mut self) {
+        if let ReserveState::Waiting(id) = self.state {
+            let queued = self.chan.state.lock().send_waiters.remove(&id);
+            if queued.is_none() {
+                self.chan.release();
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::error::Error;
+use std::fmt;
+
+/// Returned by `send` once the receiver is closed, with the value not sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "channel closed")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+/// Returned by `try_send`, with the value not sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrySendError<T> {
+    /// The channel has no free slot.
+    Full(T),
+    /// The receiver is closed.
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(value) => value,
+            TrySendError::Closed(value) => value,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(..) => write!(fmt, "Full(..)"),
+            TrySendError::Closed(..) => write!(fmt, "Closed(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(..) => write!(fmt, "no available capacity"),
+            TrySendError::Closed(..) => write!(fmt, "channel closed"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+/// Returned by `try_recv`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TryRecvError {
+    /// No value is buffered, but senders are still alive.
+    Empty,
+    /// No value is buffered and all the senders are gone.
+    Disconnected,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(fmt, "receiving on an empty channel"),
+            TryRecvError::Disconnected => write!(fmt, "receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
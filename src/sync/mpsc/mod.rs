@@ -0,0 +1,7 @@
+mod bounded;
+pub(crate) mod chan;
+pub mod error;
+mod unbounded;
+
+pub use bounded::{channel, Permit, Receiver, Sender};
+pub use unbounded::{unbounded_channel, UnboundedReceiver, UnboundedSender};
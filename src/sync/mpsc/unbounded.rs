@@ -0,0 +1,141 @@
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_core::stream::Stream;
+
+use super::chan::Chan;
+use super::error::{SendError, TryRecvError};
+
+/// Creates an unbounded channel, sending never waits.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+    let chan = Rc::new(Chan::new(None));
+    (
+        UnboundedSender { chan: chan.clone() },
+        UnboundedReceiver { chan },
+    )
+}
+
+/// The sending half of an unbounded channel.
+pub struct UnboundedSender<T> {
+    chan: Rc<Chan<T>>,
+}
+
+/// The receiving half of an unbounded channel.
+pub struct UnboundedReceiver<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> UnboundedSender<T> {
+    /// Sends `value`, fails if the receiver is closed.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.chan.send_unbounded(value)
+    }
+
+    /// Whether the receiver has been closed or dropped.
+    pub fn is_closed(&self) -> bool {
+        self.chan.is_closed()
+    }
+
+    /// Waits until the receiver is closed or dropped.
+    pub async fn closed(&self) {
+        poll_fn(|cx| self.chan.poll_closed(cx)).await
+    }
+
+    pub fn same_channel(&self, other: &UnboundedSender<T>) -> bool {
+        Rc::ptr_eq(&self.chan, &other.chan)
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> UnboundedSender<T> {
+        self.chan.add_tx();
+        UnboundedSender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        self.chan.drop_tx();
+    }
+}
+
+impl<T> UnboundedReceiver<T> {
+    /// Receives the next value, `None` once all the senders are gone and the
+    /// buffer is drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.chan.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.chan.poll_recv(cx)
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Closes the channel without dropping the receiver, senders fail from now
+    /// on but the buffered values can still be received.
+    pub fn close(&mut self) {
+        self.chan.close();
+    }
+}
+
+impl<T> Drop for UnboundedReceiver<T> {
+    fn drop(&mut self) {
+        self.chan.close();
+        self.chan.clear();
+    }
+}
+
+impl<T> Stream for UnboundedReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Waker;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn values_are_received_in_order() {
+        let (tx, mut rx) = unbounded_channel();
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+        for i in 0..100 {
+            assert!(matches!(poll(pin!(rx.recv())), Poll::Ready(Some(n)) if n == i));
+        }
+        assert!(poll(pin!(rx.recv())).is_pending());
+    }
+
+    #[test]
+    fn close_semantics() {
+        let (tx, mut rx) = unbounded_channel();
+        tx.send(0).unwrap();
+        rx.close();
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1).unwrap_err().0, 1);
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, mut rx) = unbounded_channel::<i32>();
+        drop(tx);
+        assert!(matches!(poll(pin!(rx.recv())), Poll::Ready(None)));
+    }
+}
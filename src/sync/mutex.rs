@@ -0,0 +1,154 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use super::semaphore::{Acquire, Semaphore};
+
+/// An asynchronous mutex for tasks of the same thread.
+///
+/// Unlike `RefCell`, the guard may be held across `.await` points, other tasks
+/// locking it wait in FIFO order until it is released.
+pub struct Mutex<T> {
+    semaphore: Semaphore,
+    value: UnsafeCell<T>,
+}
+
+/// A guard giving access to the value of a `Mutex`, unlocks it when dropped.
+#[must_use = "if unused the Mutex will immediately unlock"]
+pub struct MutexGuard<'a, T> {
+    lock: &'a Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Mutex<T> {
+        Mutex {
+            semaphore: Semaphore::new(1),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub async fn lock(&self) -> MutexGuard<'_, T> {
+        Acquire::new(&self.semaphore, 1).await;
+        MutexGuard { lock: self }
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if !self.semaphore.try_acquire_inner(1) {
+            return None;
+        }
+        Some(MutexGuard { lock: self })
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Mutex<T> {
+        Mutex::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = fmt.debug_struct("Mutex");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: the guard holds the only permit of the semaphore.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: the guard holds the only permit of the semaphore.
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.release(1);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let mutex = Mutex::new(0);
+        let mut guard = mutex.try_lock().unwrap();
+        *guard += 1;
+        assert!(mutex.try_lock().is_none());
+        let mut lock = pin!(mutex.lock());
+        assert!(poll(lock.as_mut()).is_pending());
+        drop(guard);
+        match poll(lock.as_mut()) {
+            Poll::Ready(guard) => assert_eq!(*guard, 1),
+            Poll::Pending => panic!("lock not granted"),
+        }
+        assert!(mutex.try_lock().is_some());
+    }
+
+    #[test]
+    fn waiters_are_served_in_fifo_order() {
+        let mutex = Mutex::new(());
+        let guard = mutex.try_lock().unwrap();
+        let mut first = pin!(mutex.lock());
+        let mut second = pin!(mutex.lock());
+        assert!(poll(first.as_mut()).is_pending());
+        assert!(poll(second.as_mut()).is_pending());
+        drop(guard);
+        // `second` cannot jump ahead, even when polled first.
+        assert!(poll(second.as_mut()).is_pending());
+        let guard = match poll(first.as_mut()) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("lock not granted"),
+        };
+        assert!(poll(second.as_mut()).is_pending());
+        drop(guard);
+        assert!(poll(second.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn dropping_granted_lock_unlocks() {
+        let mutex = Mutex::new(());
+        let guard = mutex.try_lock().unwrap();
+        let mut lock = Box::pin(mutex.lock());
+        assert!(poll(lock.as_mut()).is_pending());
+        drop(guard);
+        drop(lock);
+        assert!(mutex.try_lock().is_some());
+    }
+}
@@ -0,0 +1,246 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+
+use crate::coop;
+
+/// Notifies a task of the same thread of an event.
+///
+/// `notify_one` wakes the oldest waiting task, or stores a single permit
+/// consumed by the next `notified().await` if none is waiting.
+pub struct Notify {
+    state: RefCell<State>,
+    // local to the thread it was created on.
+    _local: PhantomData<Rc<()>>,
+}
+
+struct State {
+    permit: bool,
+    // bumped by `notify_waiters`.
+    generation: u64,
+    // waiters keyed by arrival order.
+    waiters: BTreeMap<u64, Waker>,
+    // waiters dequeued by `notify_one` which have not observed it yet.
+    notified: HashSet<u64>,
+    next_id: u64,
+}
+
+/// Future returned by `Notify::notified`.
+///
+/// It is registered for `notify_waiters` from its creation, but only queued
+/// for `notify_one` once polled. Dropping it after it was picked by
+/// `notify_one` passes the notification on to the next waiter.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    generation: u64,
+    state: NotifiedState,
+}
+
+enum NotifiedState {
+    Idle,
+    Waiting(u64),
+    Done,
+}
+
+impl Notify {
+    pub fn new() -> Notify {
+        Notify {
+            state: RefCell::new(State {
+                permit: false,
+                generation: 0,
+                waiters: BTreeMap::new(),
+                notified: HashSet::new(),
+                next_id: 0,
+            }),
+            _local: PhantomData,
+        }
+    }
+
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            generation: self.state.borrow().generation,
+            state: NotifiedState::Idle,
+        }
+    }
+
+    /// Wakes the oldest waiting task, or stores a permit if there is none.
+    pub fn notify_one(&self) {
+        let waker = {
+            let mut state = self.state.borrow_mut();
+            match state.waiters.pop_first() {
+                Some((id, waker)) => {
+                    state.notified.insert(id);
+                    waker
+                }
+                None => {
+                    state.permit = true;
+                    return;
+                }
+            }
+        };
+        waker.wake();
+    }
+
+    /// Wakes all the `Notified` futures created so far, no permit is stored.
+    pub fn notify_waiters(&self) {
+        let waiters = {
+            let mut state = self.state.borrow_mut();
+            state.generation += 1;
+            mem::take(&mut state.waiters)
+        };
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for Notify {
+    fn default() -> Notify {
+        Notify::new()
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Notify").finish()
+    }
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let this = &mut *self;
+        let mut state = this.notify.state.borrow_mut();
+        match this.state {
+            NotifiedState::Idle => {
+                if state.generation != this.generation || mem::take(&mut state.permit) {
+                    this.state = NotifiedState::Done;
+                    coop.made_progress();
+                    return Poll::Ready(());
+                }
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.insert(id, cx.waker().clone());
+                this.state = NotifiedState::Waiting(id);
+                Poll::Pending
+            }
+            NotifiedState::Waiting(id) => match state.waiters.get_mut(&id) {
+                Some(waker) => {
+                    if !waker.will_wake(cx.waker()) {
+                        *waker = cx.waker().clone();
+                    }
+                    Poll::Pending
+                }
+                None => {
+                    state.notified.remove(&id);
+                    this.state = NotifiedState::Done;
+                    coop.made_progress();
+                    Poll::Ready(())
+                }
+            },
+            NotifiedState::Done => panic!("`Notified` polled after completion"),
+        }
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        if let NotifiedState::Waiting(id) = self.state {
+            let forward = {
+                let mut state = self.notify.state.borrow_mut();
+                state.waiters.remove(&id);
+                state.notified.remove(&id)
+            };
+            if forward {
+                self.notify.notify_one();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn notify_one_stores_a_single_permit() {
+        let notify = Notify::new();
+        notify.notify_one();
+        notify.notify_one();
+        assert!(poll(pin!(notify.notified())).is_ready());
+        assert!(poll(pin!(notify.notified())).is_pending());
+    }
+
+    #[test]
+    fn notify_one_wakes_in_fifo_order() {
+        let notify = Notify::new();
+        let mut first = pin!(notify.notified());
+        let mut second = pin!(notify.notified());
+        assert!(poll(first.as_mut()).is_pending());
+        assert!(poll(second.as_mut()).is_pending());
+        notify.notify_one();
+        assert!(poll(second.as_mut()).is_pending());
+        assert!(poll(first.as_mut()).is_ready());
+        notify.notify_one();
+        assert!(poll(second.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn dropped_waiter_passes_notification_on() {
+        let notify = Notify::new();
+        let mut first = Box::pin(notify.notified());
+        let mut second = pin!(notify.notified());
+        assert!(poll(first.as_mut()).is_pending());
+        assert!(poll(second.as_mut()).is_pending());
+        notify.notify_one();
+        drop(first);
+        assert!(poll(second.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn notify_waiters_wakes_created_futures() {
+        let notify = Notify::new();
+        let mut polled = pin!(notify.notified());
+        let mut created = pin!(notify.notified());
+        assert!(poll(polled.as_mut()).is_pending());
+        notify.notify_waiters();
+        assert!(poll(polled.as_mut()).is_ready());
+        assert!(poll(created.as_mut()).is_ready());
+        // no permit is stored.
+        assert!(poll(pin!(notify.notified())).is_pending());
+    }
+
+    #[test]
+    fn notified_charges_budget() {
+        let notify = Notify::new();
+        coop::budget(|| {
+            let mut notified = 0;
+            loop {
+                notify.notify_one();
+                if poll(pin!(notify.notified())).is_pending() {
+                    break;
+                }
+                notified += 1;
+            }
+            assert_eq!(notified, 128);
+        });
+        // the permit is kept for when the task is polled again.
+        assert!(poll(pin!(notify.notified())).is_ready());
+    }
+}
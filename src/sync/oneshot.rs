@@ -0,0 +1,265 @@
+use std::cell::RefCell;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+
+use crate::coop;
+
+use self::error::{RecvError, TryRecvError};
+
+pub mod error {
+    use std::error::Error;
+    use std::fmt;
+
+    /// Returned by a `Receiver` whose sender was dropped without sending.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct RecvError(pub(super) ());
+
+    impl fmt::Display for RecvError {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(fmt, "channel closed")
+        }
+    }
+
+    impl Error for RecvError {}
+
+    /// Returned by `try_recv`.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum TryRecvError {
+        /// The value has not been sent yet.
+        Empty,
+        /// The sender was dropped without sending, or the value was already
+        /// received.
+        Closed,
+    }
+
+    impl fmt::Display for TryRecvError {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                TryRecvError::Empty => write!(fmt, "channel empty"),
+                TryRecvError::Closed => write!(fmt, "channel closed"),
+            }
+        }
+    }
+
+    impl Error for TryRecvError {}
+}
+
+/// Creates a channel sending a single value.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Rc::new(Inner {
+        state: RefCell::new(State {
+            value: None,
+            tx_dropped: false,
+            rx_closed: false,
+            rx_waker: None,
+            tx_waker: None,
+        }),
+    });
+    (
+        Sender {
+            inner: Some(inner.clone()),
+        },
+        Receiver { inner: Some(inner) },
+    )
+}
+
+/// Sends a value to the paired `Receiver`.
+pub struct Sender<T> {
+    inner: Option<Rc<Inner<T>>>,
+}
+
+/// Receives the value sent by the paired `Sender`, it is a future.
+pub struct Receiver<T> {
+    inner: Option<Rc<Inner<T>>>,
+}
+
+struct Inner<T> {
+    state: RefCell<State<T>>,
+}
+
+struct State<T> {
+    value: Option<T>,
+    tx_dropped: bool,
+    rx_closed: bool,
+    rx_waker: Option<Waker>,
+    tx_waker: Option<Waker>,
+}
+
+impl<T> Sender<T> {
+    /// Sends `value`, it is given back if the receiver is closed.
+    pub fn send(mut self, value: T) -> Result<(), T> {
+        let inner = self.inner.take().unwrap();
+        let waker = {
+            let mut state = inner.state.borrow_mut();
+            state.tx_dropped = true;
+            if state.rx_closed {
+                return Err(value);
+            }
+            state.value = Some(value);
+            state.rx_waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Whether the receiver has been closed or dropped.
+    pub fn is_closed(&self) -> bool {
+        self.inner.as_ref().unwrap().state.borrow().rx_closed
+    }
+
+    /// Waits until the receiver is closed or dropped.
+    pub async fn closed(&mut self) {
+        poll_fn(|cx| self.poll_closed(cx)).await
+    }
+
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.inner.as_ref().unwrap().state.borrow_mut();
+        if state.rx_closed {
+            return Poll::Ready(());
+        }
+        match &state.tx_waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            _ => state.tx_waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let waker = {
+                let mut state = inner.state.borrow_mut();
+                state.tx_dropped = true;
+                state.rx_waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let inner = self.inner.as_ref().ok_or(TryRecvError::Closed)?;
+        let mut state = inner.state.borrow_mut();
+        match state.value.take() {
+            Some(value) => {
+                drop(state);
+                self.inner = None;
+                Ok(value)
+            }
+            None if state.tx_dropped => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Closes the channel, the sender fails from now on but a value already
+    /// sent can still be received.
+    pub fn close(&mut self) {
+        if let Some(inner) = &self.inner {
+            let waker = {
+                let mut state = inner.state.borrow_mut();
+                state.rx_closed = true;
+                state.tx_waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let inner = self
+            .inner
+            .as_ref()
+            .expect("`Receiver` polled after completion");
+        let mut state = inner.state.borrow_mut();
+        let value = match state.value.take() {
+            Some(value) => Ok(value),
+            None if state.tx_dropped => Err(RecvError(())),
+            None => {
+                match &state.rx_waker {
+                    Some(waker) if waker.will_wake(cx.waker()) => {}
+                    _ => state.rx_waker = Some(cx.waker().clone()),
+                }
+                return Poll::Pending;
+            }
+        };
+        drop(state);
+        self.inner = None;
+        coop.made_progress();
+        Poll::Ready(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn sends_value() {
+        let (tx, mut rx) = channel();
+        assert!(poll(Pin::new(&mut rx)).is_pending());
+        tx.send(1).unwrap();
+        assert_eq!(poll(Pin::new(&mut rx)), Poll::Ready(Ok(1)));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn dropped_sender_closes() {
+        let (tx, mut rx) = channel::<i32>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(poll(Pin::new(&mut rx)), Poll::Ready(Err(RecvError(()))));
+    }
+
+    #[test]
+    fn close_keeps_sent_value() {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        rx.close();
+        assert_eq!(rx.try_recv(), Ok(1));
+
+        let (mut tx, mut rx) = channel();
+        {
+            let mut closed = pin!(tx.closed());
+            assert!(poll(closed.as_mut()).is_pending());
+            rx.close();
+            assert!(poll(closed.as_mut()).is_ready());
+        }
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1), Err(1));
+        assert_eq!(poll(Pin::new(&mut rx)), Poll::Ready(Err(RecvError(()))));
+    }
+
+    #[test]
+    fn dropped_receiver_closes() {
+        let (tx, rx) = channel();
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1), Err(1));
+    }
+}
@@ -0,0 +1,302 @@
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use futures_core::stream::Stream;
+
+use super::mpsc::chan::{self, BlockingLock, Lock, Reserve, State};
+use super::mpsc::error::{SendError, TryRecvError, TrySendError};
+use crate::driver::{self, Unpark};
+
+/// Creates a bounded channel whose senders can be used from any thread.
+///
+/// The receiver is polled by a slings runtime. A sender waking it up from
+/// another thread also writes to the eventfd watched by the driver of that
+/// runtime, so that it leaves `io_uring_enter` to run the receiver. Senders
+/// wait for a free slot once `buffer` values are queued, with `send` from an
+/// async context or `blocking_send` from a plain thread.
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(buffer > 0, "remote bounded channel requires buffer > 0");
+    let chan = Arc::new(Chan::new(Some(buffer)));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// Creates an unbounded channel whose senders can be used from any thread.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, Receiver<T>) {
+    let chan = Arc::new(Chan::new(None));
+    (UnboundedSender { chan: chan.clone() }, Receiver { chan })
+}
+
+type Chan<T> = chan::Chan<T, Shared<State<T>>>;
+
+/// The sending half of a bounded remote channel, it is `Send` and `Sync`.
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The sending half of an unbounded remote channel, it is `Send` and `Sync`.
+pub struct UnboundedSender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The receiving half of a remote channel.
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+// The lock of a remote channel.
+struct Shared<S> {
+    state: Mutex<S>,
+    // blocking senders waiting for a slot.
+    condvar: Condvar,
+    // the driver of the runtime which last polled the receiver.
+    unpark: Mutex<Option<Arc<Unpark>>>,
+}
+
+impl<S> Shared<S> {
+    // Remember the driver of the current runtime, if any, to unpark it once
+    // the receiver is woken.
+    fn register(&self) {
+        let current = match driver::with_current(|driver| driver.unpark()) {
+            Some(current) => current,
+            None => return,
+        };
+        let mut unpark = self.unpark.lock().unwrap();
+        match &*unpark {
+            Some(unpark) if Arc::ptr_eq(unpark, &current) => {}
+            _ => *unpark = Some(current),
+        }
+    }
+}
+
+impl<S> Lock<S> for Shared<S> {
+    type Guard<'a>
+        = MutexGuard<'a, S>
+    where
+        S: 'a;
+
+    fn new(state: S) -> Shared<S> {
+        Shared {
+            state: Mutex::new(state),
+            condvar: Condvar::new(),
+            unpark: Mutex::new(None),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, S> {
+        self.state.lock().unwrap()
+    }
+
+    fn notify_rx(&self) {
+        if let Some(unpark) = &*self.unpark.lock().unwrap() {
+            unpark.unpark();
+        }
+    }
+
+    fn notify_tx(&self, all: bool) {
+        if all {
+            self.condvar.notify_all();
+        } else {
+            self.condvar.notify_one();
+        }
+    }
+}
+
+impl<S> BlockingLock<S> for Shared<S> {
+    fn wait<'a>(&'a self, guard: MutexGuard<'a, S>) -> MutexGuard<'a, S> {
+        self.condvar.wait(guard).unwrap()
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends `value`, waiting for a free slot if the buffer is full.
+    ///
+    /// The waiting task may belong to another runtime than the receiver.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match Reserve::new(&self.chan).await {
+            Ok(()) => self.chan.send_reserved(value).map_err(SendError),
+            Err(_) => Err(SendError(value)),
+        }
+    }
+
+    /// Sends `value`, blocking the current thread until a slot is free.
+    ///
+    /// It must not be called from a task, which would block its runtime.
+    pub fn blocking_send(&self, value: T) -> Result<(), SendError<T>> {
+        self.chan.blocking_send(value)
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        match self.chan.try_reserve() {
+            Ok(()) => self.chan.send_reserved(value).map_err(TrySendError::Closed),
+            Err(TrySendError::Full(())) => Err(TrySendError::Full(value)),
+            Err(TrySendError::Closed(())) => Err(TrySendError::Closed(value)),
+        }
+    }
+
+    /// Whether the receiver has been closed or dropped.
+    pub fn is_closed(&self) -> bool {
+        self.chan.is_closed()
+    }
+
+    /// Waits until the receiver is closed or dropped.
+    pub async fn closed(&self) {
+        poll_fn(|cx| self.chan.poll_closed(cx)).await
+    }
+
+    /// The number of free slots.
+    pub fn capacity(&self) -> usize {
+        self.chan.capacity()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.chan.add_tx();
+        Sender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.drop_tx();
+    }
+}
+
+impl<T> UnboundedSender<T> {
+    /// Sends `value`, fails if the receiver is closed.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.chan.send_unbounded(value)
+    }
+
+    /// Whether the receiver has been closed or dropped.
+    pub fn is_closed(&self) -> bool {
+        self.chan.is_closed()
+    }
+
+    /// Waits until the receiver is closed or dropped.
+    pub async fn closed(&self) {
+        poll_fn(|cx| self.chan.poll_closed(cx)).await
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> UnboundedSender<T> {
+        self.chan.add_tx();
+        UnboundedSender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        self.chan.drop_tx();
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value, `None` once all the senders are gone and the
+    /// buffer is drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.chan.lock_ref().register();
+        self.chan.poll_recv(cx)
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Closes the channel without dropping the receiver, senders fail from now
+    /// on but the buffered values can still be received.
+    pub fn close(&mut self) {
+        self.chan.close();
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.close();
+        self.chan.clear();
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn senders_are_send_and_sync() {
+        assert_send_sync::<Sender<std::cell::Cell<u8>>>();
+        assert_send_sync::<UnboundedSender<std::cell::Cell<u8>>>();
+    }
+
+    #[test]
+    fn wakes_runtime_from_plain_thread() {
+        let runtime = Runtime::new().unwrap();
+        let (tx, mut rx) = unbounded_channel();
+        let sender = thread::spawn(move || {
+            // lets the runtime block in the driver first.
+            thread::sleep(Duration::from_millis(20));
+            tx.send(1).unwrap();
+        });
+        assert_eq!(runtime.block_on(rx.recv()), Some(1));
+        sender.join().unwrap();
+        assert_eq!(runtime.block_on(rx.recv()), None);
+    }
+
+    #[test]
+    fn blocking_senders_wait_for_slots() {
+        let runtime = Runtime::new().unwrap();
+        let (tx, mut rx) = channel(1);
+        let sender = thread::spawn(move || {
+            for i in 0..100 {
+                tx.blocking_send(i).unwrap();
+            }
+        });
+        runtime.block_on(async {
+            for i in 0..100 {
+                assert_eq!(rx.recv().await, Some(i));
+            }
+            assert_eq!(rx.recv().await, None);
+        });
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn closing_fails_blocked_sender() {
+        let (tx, mut rx) = channel(1);
+        tx.try_send(0).unwrap();
+        let sender = thread::spawn(move || tx.blocking_send(1));
+        thread::sleep(Duration::from_millis(20));
+        rx.close();
+        assert_eq!(sender.join().unwrap().unwrap_err().0, 1);
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+}
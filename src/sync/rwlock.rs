@@ -0,0 +1,196 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use super::semaphore::{Acquire, Semaphore};
+
+// A reader takes one permit, a writer takes them all.
+const MAX_READS: usize = 1 << 20;
+
+/// An asynchronous reader-writer lock for tasks of the same thread.
+///
+/// Readers and writers are served in FIFO order, so a queued writer is not
+/// starved by readers arriving after it.
+pub struct RwLock<T> {
+    semaphore: Semaphore,
+    value: UnsafeCell<T>,
+}
+
+/// A guard giving shared access to the value of a `RwLock`.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/// A guard giving exclusive access to the value of a `RwLock`.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> RwLock<T> {
+    pub fn new(value: T) -> RwLock<T> {
+        RwLock {
+            semaphore: Semaphore::new(MAX_READS),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        Acquire::new(&self.semaphore, 1).await;
+        RwLockReadGuard { lock: self }
+    }
+
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        Acquire::new(&self.semaphore, MAX_READS).await;
+        RwLockWriteGuard { lock: self }
+    }
+
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        if !self.semaphore.try_acquire_inner(1) {
+            return None;
+        }
+        Some(RwLockReadGuard { lock: self })
+    }
+
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        if !self.semaphore.try_acquire_inner(MAX_READS) {
+            return None;
+        }
+        Some(RwLockWriteGuard { lock: self })
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> RwLock<T> {
+        RwLock::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = fmt.debug_struct("RwLock");
+        match self.try_read() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: no writer holds the lock while a reader does.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.release(1);
+    }
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: the guard holds all the permits of the semaphore.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: the guard holds all the permits of the semaphore.
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.release(MAX_READS);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, fmt)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn readers_share_writers_exclude() {
+        let lock = RwLock::new(0);
+        let a = lock.try_read().unwrap();
+        let b = lock.try_read().unwrap();
+        assert_eq!(*a + *b, 0);
+        assert!(lock.try_write().is_none());
+        drop((a, b));
+        let mut guard = lock.try_write().unwrap();
+        *guard = 1;
+        assert!(lock.try_read().is_none());
+        drop(guard);
+        assert_eq!(*lock.try_read().unwrap(), 1);
+    }
+
+    #[test]
+    fn queued_writer_is_not_starved() {
+        let lock = RwLock::new(());
+        let reader = lock.try_read().unwrap();
+        let mut write = pin!(lock.write());
+        assert!(poll(write.as_mut()).is_pending());
+        // readers arriving after the writer wait behind it.
+        assert!(lock.try_read().is_none());
+        let mut read = pin!(lock.read());
+        assert!(poll(read.as_mut()).is_pending());
+
+        drop(reader);
+        let writer = match poll(write.as_mut()) {
+            Poll::Ready(writer) => writer,
+            Poll::Pending => panic!("write not granted"),
+        };
+        assert!(poll(read.as_mut()).is_pending());
+        drop(writer);
+        assert!(poll(read.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn dropping_granted_write_unlocks() {
+        let lock = RwLock::new(());
+        let reader = lock.try_read().unwrap();
+        let mut write = Box::pin(lock.write());
+        assert!(poll(write.as_mut()).is_pending());
+        drop(reader);
+        drop(write);
+        assert!(lock.try_write().is_some());
+    }
+}
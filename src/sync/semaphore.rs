@@ -0,0 +1,398 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+
+use crate::coop;
+
+/// A counting semaphore for tasks of the same thread.
+///
+/// Waiters are served in FIFO order: a waiter asking for more permits than
+/// available blocks the ones queued behind it, even if those could be served.
+pub struct Semaphore {
+    state: RefCell<State>,
+    // local to the thread it was created on.
+    _local: PhantomData<Rc<()>>,
+}
+
+struct State {
+    permits: usize,
+    // waiters keyed by arrival order.
+    waiters: BTreeMap<u64, Waiter>,
+    next_id: u64,
+}
+
+struct Waiter {
+    permits: usize,
+    waker: Waker,
+}
+
+/// A permit acquired from a `Semaphore`, released when dropped.
+#[must_use]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+/// An owned permit acquired from a `Semaphore`, released when dropped.
+#[must_use]
+pub struct OwnedSemaphorePermit {
+    semaphore: Rc<Semaphore>,
+    permits: usize,
+}
+
+impl Semaphore {
+    /// The maximum number of permits a semaphore can hold.
+    pub const MAX_PERMITS: usize = usize::MAX >> 3;
+
+    pub fn new(permits: usize) -> Semaphore {
+        assert!(
+            permits <= Semaphore::MAX_PERMITS,
+            "a semaphore may not have more than MAX_PERMITS permits ({})",
+            Semaphore::MAX_PERMITS
+        );
+        Semaphore {
+            state: RefCell::new(State {
+                permits,
+                waiters: BTreeMap::new(),
+                next_id: 0,
+            }),
+            _local: PhantomData,
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.borrow().permits
+    }
+
+    /// Adds `n` permits, waking the waiters they are enough for.
+    pub fn add_permits(&self, n: usize) {
+        self.release(n);
+    }
+
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.acquire_many(1).await
+    }
+
+    pub async fn acquire_many(&self, n: usize) -> SemaphorePermit<'_> {
+        Acquire::new(self, n).await;
+        SemaphorePermit {
+            semaphore: self,
+            permits: n,
+        }
+    }
+
+    /// Acquires a permit if one is available and nobody is waiting for one.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    pub fn try_acquire_many(&self, n: usize) -> Option<SemaphorePermit<'_>> {
+        if !self.try_acquire_inner(n) {
+            return None;
+        }
+        Some(SemaphorePermit {
+            semaphore: self,
+            permits: n,
+        })
+    }
+
+    pub async fn acquire_owned(self: Rc<Self>) -> OwnedSemaphorePermit {
+        self.acquire_many_owned(1).await
+    }
+
+    pub async fn acquire_many_owned(self: Rc<Self>, n: usize) -> OwnedSemaphorePermit {
+        Acquire::new(&self, n).await;
+        OwnedSemaphorePermit {
+            semaphore: self,
+            permits: n,
+        }
+    }
+
+    pub fn try_acquire_owned(self: Rc<Self>) -> Option<OwnedSemaphorePermit> {
+        self.try_acquire_many_owned(1)
+    }
+
+    pub fn try_acquire_many_owned(self: Rc<Self>, n: usize) -> Option<OwnedSemaphorePermit> {
+        if !self.try_acquire_inner(n) {
+            return None;
+        }
+        Some(OwnedSemaphorePermit {
+            semaphore: self,
+            permits: n,
+        })
+    }
+
+    pub(crate) fn try_acquire_inner(&self, n: usize) -> bool {
+        let mut state = self.state.borrow_mut();
+        if state.waiters.is_empty() && state.permits >= n {
+            state.permits -= n;
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn release(&self, n: usize) {
+        let mut wakers = Vec::new();
+        {
+            let mut state = self.state.borrow_mut();
+            let state = &mut *state;
+            state.permits += n;
+            assert!(
+                state.permits <= Semaphore::MAX_PERMITS,
+                "number of added permits ({}) would overflow MAX_PERMITS ({})",
+                n,
+                Semaphore::MAX_PERMITS
+            );
+            while let Some(entry) = state.waiters.first_entry() {
+                if entry.get().permits > state.permits {
+                    break;
+                }
+                let waiter = entry.remove();
+                state.permits -= waiter.permits;
+                wakers.push(waiter.waker);
+            }
+        }
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Semaphore")
+            .field("permits", &self.available_permits())
+            .finish()
+    }
+}
+
+impl SemaphorePermit<'_> {
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Forgets the permits, they are not given back to the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.release(self.permits);
+        }
+    }
+}
+
+impl OwnedSemaphorePermit {
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    pub fn semaphore(&self) -> &Rc<Semaphore> {
+        &self.semaphore
+    }
+
+    /// Forgets the permits, they are not given back to the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.release(self.permits);
+        }
+    }
+}
+
+/// Waits for `permits` permits, they are handed over to the caller on completion.
+///
+/// Dropping it while queued leaves the queue, and permits granted to it but not
+/// observed yet go back to the semaphore.
+pub(crate) struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+    state: AcquireState,
+}
+
+enum AcquireState {
+    Idle,
+    Waiting(u64),
+    Done,
+}
+
+impl Acquire<'_> {
+    pub(crate) fn new(semaphore: &Semaphore, permits: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore,
+            permits,
+            state: AcquireState::Idle,
+        }
+    }
+}
+
+impl Future for Acquire<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let this = &mut *self;
+        let mut state = this.semaphore.state.borrow_mut();
+        match this.state {
+            AcquireState::Idle => {
+                if state.waiters.is_empty() && state.permits >= this.permits {
+                    state.permits -= this.permits;
+                    this.state = AcquireState::Done;
+                    coop.made_progress();
+                    return Poll::Ready(());
+                }
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.insert(
+                    id,
+                    Waiter {
+                        permits: this.permits,
+                        waker: cx.waker().clone(),
+                    },
+                );
+                this.state = AcquireState::Waiting(id);
+                Poll::Pending
+            }
+            AcquireState::Waiting(id) => match state.waiters.get_mut(&id) {
+                Some(waiter) => {
+                    if !waiter.waker.will_wake(cx.waker()) {
+                        waiter.waker = cx.waker().clone();
+                    }
+                    Poll::Pending
+                }
+                None => {
+                    this.state = AcquireState::Done;
+                    coop.made_progress();
+                    Poll::Ready(())
+                }
+            },
+            AcquireState::Done => panic!("`Acquire` polled after completion"),
+        }
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if let AcquireState::Waiting(id) = mem::replace(&mut self.state, AcquireState::Done) {
+            let queued = self.semaphore.state.borrow_mut().waiters.remove(&id);
+            match queued {
+                // the waiters behind may be served now.
+                Some(_) => self.semaphore.release(0),
+                None => self.semaphore.release(self.permits),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn acquire_and_release() {
+        let semaphore = Semaphore::new(2);
+        let a = semaphore.try_acquire().unwrap();
+        let b = semaphore.try_acquire().unwrap();
+        assert!(semaphore.try_acquire().is_none());
+        drop(a);
+        assert_eq!(semaphore.available_permits(), 1);
+        b.forget();
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    #[test]
+    fn waiters_are_served_in_fifo_order() {
+        let semaphore = Semaphore::new(0);
+        let mut many = pin!(semaphore.acquire_many(2));
+        let mut one = pin!(semaphore.acquire());
+        assert!(poll(many.as_mut()).is_pending());
+        assert!(poll(one.as_mut()).is_pending());
+
+        // enough for `one`, but `many` is ahead of it.
+        semaphore.add_permits(1);
+        assert!(poll(one.as_mut()).is_pending());
+        assert!(semaphore.try_acquire().is_none());
+
+        semaphore.add_permits(1);
+        let permit = match poll(many.as_mut()) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => panic!("acquire_many not granted"),
+        };
+        assert_eq!(permit.num_permits(), 2);
+        assert!(poll(one.as_mut()).is_pending());
+        drop(permit);
+        let permit = match poll(one.as_mut()) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => panic!("acquire not granted"),
+        };
+        assert_eq!(semaphore.available_permits(), 1);
+        drop(permit);
+        assert_eq!(semaphore.available_permits(), 2);
+    }
+
+    #[test]
+    fn dropping_queued_waiter_serves_the_next() {
+        let semaphore = Semaphore::new(1);
+        let mut many = Box::pin(semaphore.acquire_many(2));
+        let mut one = pin!(semaphore.acquire());
+        assert!(poll(many.as_mut()).is_pending());
+        assert!(poll(one.as_mut()).is_pending());
+        drop(many);
+        assert!(poll(one.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn dropping_granted_waiter_returns_permits() {
+        let semaphore = Semaphore::new(0);
+        let mut acquire = Box::pin(semaphore.acquire_many(3));
+        assert!(poll(acquire.as_mut()).is_pending());
+        semaphore.add_permits(3);
+        assert_eq!(semaphore.available_permits(), 0);
+        // granted, but never polled again.
+        drop(acquire);
+        assert_eq!(semaphore.available_permits(), 3);
+    }
+
+    #[test]
+    fn owned_permit() {
+        let semaphore = Rc::new(Semaphore::new(1));
+        let permit = semaphore.clone().try_acquire_owned().unwrap();
+        let mut acquire = pin!(semaphore.clone().acquire_owned());
+        assert!(poll(acquire.as_mut()).is_pending());
+        drop(permit);
+        assert!(poll(acquire.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn acquire_charges_budget() {
+        let semaphore = Semaphore::new(usize::MAX >> 4);
+        coop::budget(|| {
+            let mut acquired = 0;
+            while let Poll::Ready(permit) = poll(pin!(semaphore.acquire())) {
+                permit.forget();
+                acquired += 1;
+            }
+            assert_eq!(acquired, 128);
+        });
+    }
+}
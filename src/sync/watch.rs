@@ -0,0 +1,407 @@
+use std::cell::{self, RefCell};
+use std::collections::BTreeMap;
+use std::future::poll_fn;
+use std::mem;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll, Waker};
+
+use futures_core::stream::Stream;
+
+use crate::coop;
+
+use self::error::{RecvError, SendError};
+
+pub mod error {
+    use std::error::Error;
+    use std::fmt;
+
+    /// Returned by `send` when there is no receiver, with the value not sent.
+    #[derive(PartialEq, Eq, Clone, Copy)]
+    pub struct SendError<T>(pub T);
+
+    impl<T> fmt::Debug for SendError<T> {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt.debug_struct("SendError").finish_non_exhaustive()
+        }
+    }
+
+    impl<T> fmt::Display for SendError<T> {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(fmt, "channel closed")
+        }
+    }
+
+    impl<T> Error for SendError<T> {}
+
+    /// Returned by `changed` once the sender is gone.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct RecvError(pub(super) ());
+
+    impl fmt::Display for RecvError {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(fmt, "channel closed")
+        }
+    }
+
+    impl Error for RecvError {}
+}
+
+/// Creates a channel holding a single value, receivers are notified when it
+/// changes and only ever see the latest one.
+pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Rc::new(Shared {
+        value: RefCell::new(init),
+        state: RefCell::new(State {
+            version: 0,
+            tx_dropped: false,
+            rx_count: 1,
+            waiters: BTreeMap::new(),
+            closed_waiters: Vec::new(),
+            next_id: 0,
+        }),
+    });
+    let rx = Receiver {
+        shared: shared.clone(),
+        version: 0,
+        waiting: None,
+    };
+    (Sender { shared }, rx)
+}
+
+/// The sending half of a watch channel.
+pub struct Sender<T> {
+    shared: Rc<Shared<T>>,
+}
+
+/// A receiving half of a watch channel.
+pub struct Receiver<T> {
+    shared: Rc<Shared<T>>,
+    // version of the value last seen.
+    version: u64,
+    // key of the waker registered in `waiters`.
+    waiting: Option<u64>,
+}
+
+/// A borrow of the value of a watch channel.
+///
+/// Sending a new value while it is held panics, so it should not be held
+/// across an `.await` point.
+pub struct Ref<'a, T> {
+    inner: cell::Ref<'a, T>,
+    has_changed: bool,
+}
+
+struct Shared<T> {
+    value: RefCell<T>,
+    state: RefCell<State>,
+}
+
+struct State {
+    version: u64,
+    tx_dropped: bool,
+    rx_count: usize,
+    // receivers waiting for a change, keyed by arrival order.
+    waiters: BTreeMap<u64, Waker>,
+    // tasks waiting for all the receivers to be dropped.
+    closed_waiters: Vec<Waker>,
+    next_id: u64,
+}
+
+impl<T> Sender<T> {
+    /// Replaces the value and notifies the receivers.
+    ///
+    /// Fails if there is no receiver, the value is given back in the error.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.shared.state.borrow().rx_count == 0 {
+            return Err(SendError(value));
+        }
+        self.send_replace(value);
+        Ok(())
+    }
+
+    /// Replaces the value and notifies the receivers, even if there is none.
+    /// Returns the previous value.
+    pub fn send_replace(&self, value: T) -> T {
+        let mut value = value;
+        self.send_modify(|old| mem::swap(old, &mut value));
+        value
+    }
+
+    /// Modifies the value in place and notifies the receivers.
+    pub fn send_modify(&self, modify: impl FnOnce(&mut T)) {
+        modify(&mut self.shared.value.borrow_mut());
+        let waiters = {
+            let mut state = self.shared.state.borrow_mut();
+            state.version += 1;
+            mem::take(&mut state.waiters)
+        };
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+    }
+
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            inner: self.shared.value.borrow(),
+            has_changed: false,
+        }
+    }
+
+    /// Creates a receiver which has seen the current value.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.state.borrow_mut();
+        state.rx_count += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            version: state.version,
+            waiting: None,
+        }
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.shared.state.borrow().rx_count
+    }
+
+    /// Whether all the receivers have been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.receiver_count() == 0
+    }
+
+    /// Waits until all the receivers are dropped.
+    pub async fn closed(&self) {
+        poll_fn(|cx| {
+            let mut state = self.shared.state.borrow_mut();
+            if state.rx_count == 0 {
+                return Poll::Ready(());
+            }
+            if !state.closed_waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                state.closed_waiters.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = self.shared.state.borrow_mut();
+            state.tx_dropped = true;
+            mem::take(&mut state.waiters)
+        };
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Borrows the latest value, without marking it as seen.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        let version = self.shared.state.borrow().version;
+        Ref {
+            inner: self.shared.value.borrow(),
+            has_changed: version != self.version,
+        }
+    }
+
+    /// Borrows the latest value and marks it as seen.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        let version = self.shared.state.borrow().version;
+        let has_changed = version != self.version;
+        self.version = version;
+        Ref {
+            inner: self.shared.value.borrow(),
+            has_changed,
+        }
+    }
+
+    /// Whether the value changed since it was last seen, fails once the
+    /// sender is gone.
+    pub fn has_changed(&self) -> Result<bool, RecvError> {
+        let state = self.shared.state.borrow();
+        if state.tx_dropped {
+            return Err(RecvError(()));
+        }
+        Ok(state.version != self.version)
+    }
+
+    pub fn mark_unchanged(&mut self) {
+        self.version = self.shared.state.borrow().version;
+    }
+
+    pub fn mark_changed(&mut self) {
+        self.version = self.shared.state.borrow().version.wrapping_sub(1);
+    }
+
+    /// Waits for a value not seen yet and marks it as seen.
+    ///
+    /// Fails once the sender is gone and the latest value was seen.
+    pub async fn changed(&mut self) -> Result<(), RecvError> {
+        poll_fn(|cx| self.poll_changed(cx)).await
+    }
+
+    pub fn same_channel(&self, other: &Receiver<T>) -> bool {
+        Rc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    fn poll_changed(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), RecvError>> {
+        let coop = ready!(coop::poll_proceed(cx));
+        let mut state = self.shared.state.borrow_mut();
+        if state.version != self.version {
+            self.version = state.version;
+            coop.made_progress();
+            return Poll::Ready(Ok(()));
+        }
+        if state.tx_dropped {
+            coop.made_progress();
+            return Poll::Ready(Err(RecvError(())));
+        }
+        let id = match self.waiting {
+            Some(id) if state.waiters.contains_key(&id) => id,
+            _ => {
+                let id = state.next_id;
+                state.next_id += 1;
+                self.waiting = Some(id);
+                id
+            }
+        };
+        state.waiters.insert(id, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        self.shared.state.borrow_mut().rx_count += 1;
+        Receiver {
+            shared: self.shared.clone(),
+            version: self.version,
+            waiting: None,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let closed_waiters = {
+            let mut state = self.shared.state.borrow_mut();
+            if let Some(id) = self.waiting {
+                state.waiters.remove(&id);
+            }
+            state.rx_count -= 1;
+            if state.rx_count > 0 {
+                return;
+            }
+            mem::take(&mut state.closed_waiters)
+        };
+        for waker in closed_waiters {
+            waker.wake();
+        }
+    }
+}
+
+/// Yields a clone of the value each time it changes, and ends once the sender
+/// is gone.
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        match ready!(this.poll_changed(cx)) {
+            Ok(()) => Poll::Ready(Some(this.shared.value.borrow().clone())),
+            Err(_) => Poll::Ready(None),
+        }
+    }
+}
+
+impl<T> Ref<'_, T> {
+    /// Whether the value had not been seen by the receiver when borrowed.
+    pub fn has_changed(&self) -> bool {
+        self.has_changed
+    }
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+
+    use super::*;
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn receivers_see_latest_value() {
+        let (tx, mut rx) = channel(0);
+        {
+            let mut changed = pin!(rx.changed());
+            assert!(poll(changed.as_mut()).is_pending());
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+            assert_eq!(poll(changed.as_mut()), Poll::Ready(Ok(())));
+        }
+        assert_eq!(*rx.borrow(), 2);
+        assert!(!rx.has_changed().unwrap());
+        assert!(poll(pin!(rx.changed())).is_pending());
+
+        let mut rx2 = tx.subscribe();
+        assert!(!rx2.has_changed().unwrap());
+        tx.send_modify(|value| *value += 1);
+        assert!(rx2.borrow().has_changed());
+        assert_eq!(*rx2.borrow_and_update(), 3);
+        assert!(!rx2.borrow().has_changed());
+    }
+
+    #[test]
+    fn close_semantics() {
+        let (tx, mut rx) = channel(0);
+        tx.send(1).unwrap();
+        drop(tx);
+        // the last value is still seen before the error.
+        assert_eq!(poll(pin!(rx.changed())), Poll::Ready(Ok(())));
+        assert_eq!(poll(pin!(rx.changed())), Poll::Ready(Err(RecvError(()))));
+        assert!(rx.has_changed().is_err());
+
+        let (tx, rx) = channel(0);
+        let rx2 = rx.clone();
+        let mut closed = pin!(tx.closed());
+        drop(rx);
+        assert!(poll(closed.as_mut()).is_pending());
+        drop(rx2);
+        assert!(poll(closed.as_mut()).is_ready());
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1), Err(SendError(1)));
+        assert_eq!(tx.send_replace(2), 0);
+    }
+
+    #[test]
+    fn changed_charges_budget() {
+        let (tx, mut rx) = channel(0);
+        coop::budget(|| {
+            let mut changed = 0;
+            loop {
+                tx.send_modify(|value| *value += 1);
+                if poll(pin!(rx.changed())).is_pending() {
+                    break;
+                }
+                changed += 1;
+            }
+            assert_eq!(changed, 128);
+        });
+        assert!(poll(pin!(rx.changed())).is_ready());
+    }
+}
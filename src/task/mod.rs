@@ -0,0 +1,5 @@
+mod yield_now;
+
+pub use crate::blocking::{spawn_blocking, JoinError, JoinHandle};
+pub use crate::local_executor::{spawn_local, LocalSet};
+pub use yield_now::yield_now;
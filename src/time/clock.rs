@@ -0,0 +1,144 @@
+#[cfg(feature = "test-util")]
+use std::cell::Cell;
+use std::time::Duration;
+
+use super::driver;
+use super::Instant;
+
+/// The source of time of a runtime.
+///
+/// With the `test-util` feature it can be paused, and then advanced manually
+/// or automatically when the runtime has nothing else to do.
+pub(crate) struct Clock {
+    #[cfg(feature = "test-util")]
+    inner: Cell<Inner>,
+}
+
+#[cfg(feature = "test-util")]
+#[derive(Clone, Copy)]
+struct Inner {
+    base: std::time::Instant,
+    // when the clock was last resumed, `None` while it is paused.
+    unfrozen: Option<std::time::Instant>,
+}
+
+impl Clock {
+    pub(crate) fn new() -> Clock {
+        Clock {
+            #[cfg(feature = "test-util")]
+            inner: Cell::new(Inner {
+                base: std::time::Instant::now(),
+                unfrozen: Some(std::time::Instant::now()),
+            }),
+        }
+    }
+
+    #[cfg(not(feature = "test-util"))]
+    pub(crate) fn now(&self) -> Instant {
+        Instant::from_std(std::time::Instant::now())
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn now(&self) -> Instant {
+        let inner = self.inner.get();
+        let mut now = inner.base;
+        if let Some(unfrozen) = inner.unfrozen {
+            now += unfrozen.elapsed();
+        }
+        Instant::from_std(now)
+    }
+
+    #[cfg(not(feature = "test-util"))]
+    pub(crate) fn is_paused(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn is_paused(&self) -> bool {
+        self.inner.get().unfrozen.is_none()
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn pause(&self) {
+        assert!(!self.is_paused(), "time is already frozen");
+        self.inner.set(Inner {
+            base: self.now().into_std(),
+            unfrozen: None,
+        });
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn resume(&self) {
+        assert!(self.is_paused(), "time is not frozen");
+        let mut inner = self.inner.get();
+        inner.unfrozen = Some(std::time::Instant::now());
+        self.inner.set(inner);
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn advance(&self, duration: Duration) {
+        assert!(self.is_paused(), "time is not frozen");
+        let mut inner = self.inner.get();
+        inner.base += duration;
+        self.inner.set(inner);
+    }
+}
+
+/// The current time of the kernel clock `clock`, measured from its epoch.
+pub(crate) fn clock_gettime(clock: libc::clockid_t) -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // Safety: `ts` is a valid timespec for the kernel to fill.
+    let ret = unsafe { libc::clock_gettime(clock, &mut ts) };
+    assert_eq!(ret, 0, "clock_gettime failed");
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+pub(crate) fn now() -> Instant {
+    driver::with_current(|driver| driver.now())
+        .unwrap_or_else(|| Instant::from_std(std::time::Instant::now()))
+}
+
+/// Pause the clock of the current runtime.
+///
+/// While paused, `Instant::now` stops moving forward, and whenever the runtime
+/// has no work left besides pending timers, the clock is advanced to the
+/// nearest one instead of waiting for it. I/O in flight holds the clock back
+/// for a few milliseconds of real time, in case it is about to complete.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime context, or if time is already frozen.
+#[cfg(feature = "test-util")]
+pub fn pause() {
+    driver::with_current(|driver| driver.pause())
+        .expect("time::pause() called from outside of a runtime context");
+}
+
+/// Resume the clock of the current runtime.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime context, or if time is not frozen.
+#[cfg(feature = "test-util")]
+pub fn resume() {
+    driver::with_current(|driver| driver.resume())
+        .expect("time::resume() called from outside of a runtime context");
+}
+
+/// Advance the paused clock of the current runtime by `duration`.
+///
+/// Timers elapsed by the move are fired, and the current task yields so that
+/// the woken tasks run before it continues.
+///
+/// # Panics
+///
+/// Panics if called outside of a runtime context, or if time is not frozen.
+#[cfg(feature = "test-util")]
+pub async fn advance(duration: Duration) {
+    driver::with_current(|driver| driver.advance(duration))
+        .expect("time::advance() called from outside of a runtime context");
+    crate::task::yield_now().await;
+}
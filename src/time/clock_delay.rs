@@ -0,0 +1,124 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use io_uring::types::TimeoutFlags;
+
+use super::clock::clock_gettime;
+use super::driver;
+use crate::driver::{Op, Timeout};
+
+/// A delay measured against another kernel clock than the one of `Delay`.
+///
+/// It is a kernel timeout of its own rather than an entry of the timer wheel,
+/// and does not follow the paused clock of the `test-util` feature. It fails if
+/// it is polled outside of a runtime context.
+pub struct ClockDelay {
+    deadline: Duration,
+    clock: TimeoutFlags,
+    op: Option<Op<Timeout>>,
+}
+
+/// Waits until the system time reaches `deadline`.
+///
+/// The deadline follows `CLOCK_REALTIME`, so the delay completes earlier or
+/// later if the system time is changed meanwhile. Deadlines before the unix
+/// epoch complete right away.
+pub fn sleep_until_system_time(deadline: SystemTime) -> ClockDelay {
+    ClockDelay {
+        deadline: deadline
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default(),
+        clock: TimeoutFlags::REALTIME,
+        op: None,
+    }
+}
+
+/// Waits for `duration` measured against `CLOCK_BOOTTIME`.
+///
+/// Unlike `delay_for`, the time the system spends suspended counts towards the
+/// delay, which suits jobs scheduled minutes or hours ahead.
+pub fn sleep_for_boottime(duration: Duration) -> ClockDelay {
+    sleep_until_boottime(clock_gettime(libc::CLOCK_BOOTTIME) + duration)
+}
+
+/// Waits until `CLOCK_BOOTTIME` reaches `deadline`, the time since boot
+/// including the time the system spent suspended.
+///
+/// Deadlines already passed complete right away.
+pub fn sleep_until_boottime(deadline: Duration) -> ClockDelay {
+    ClockDelay {
+        deadline,
+        clock: TimeoutFlags::BOOTTIME,
+        op: None,
+    }
+}
+
+impl Future for ClockDelay {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            match &mut this.op {
+                None => this.op = Some(submit(this.deadline, this.clock)?),
+                Some(op) => {
+                    let res = ready!(Pin::new(op).poll(cx));
+                    this.op = None;
+                    return Poll::Ready(res);
+                }
+            }
+        }
+    }
+}
+
+fn submit(deadline: Duration, clock: TimeoutFlags) -> io::Result<Op<Timeout>> {
+    driver::with_current(|_| Op::timeout_at(deadline, clock))
+        .unwrap_or_else(|| Err(driver::outside_runtime()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Waker;
+
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn fails_outside_runtime() {
+        let mut delay = sleep_for_boottime(Duration::from_millis(10));
+        let cx = &mut Context::from_waker(Waker::noop());
+        assert!(matches!(Pin::new(&mut delay).poll(cx), Poll::Ready(Err(_))));
+    }
+
+    #[test]
+    fn past_system_time_completes_immediately() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let start = std::time::Instant::now();
+            sleep_until_system_time(SystemTime::now() - Duration::from_secs(60))
+                .await
+                .unwrap();
+            sleep_until_system_time(SystemTime::UNIX_EPOCH - Duration::from_secs(1))
+                .await
+                .unwrap();
+            assert!(start.elapsed() < Duration::from_secs(1));
+        });
+    }
+
+    #[test]
+    fn boottime_sleep_fires() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let start = std::time::Instant::now();
+            sleep_for_boottime(Duration::from_millis(20)).await.unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(20));
+
+            let deadline = clock_gettime(libc::CLOCK_BOOTTIME) + Duration::from_millis(20);
+            sleep_until_boottime(deadline).await.unwrap();
+            assert!(clock_gettime(libc::CLOCK_BOOTTIME) >= deadline);
+        });
+    }
+}
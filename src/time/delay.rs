@@ -1,17 +1,24 @@
 use std::future::Future;
+use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use super::Timer;
+use super::{Instant, Timer};
 
+/// Completes once its deadline is reached.
+///
+/// It fails if it is polled outside of a runtime context, or after the runtime
+/// it was first polled on has been dropped.
 pub struct Delay {
     inner: Timer,
 }
 
-pub fn delay_until(deadline: Instant) -> Delay {
+/// Waits until `deadline`, either a `slings::time::Instant` or a
+/// `std::time::Instant`.
+pub fn delay_until(deadline: impl Into<Instant>) -> Delay {
     Delay {
-        inner: Timer::new(deadline),
+        inner: Timer::new(deadline.into()),
     }
 }
 
@@ -28,21 +35,64 @@ impl Delay {
         self.inner.is_elapsed()
     }
 
-    pub fn reset(&mut self, deadline: Instant) {
-        self.inner.reset(deadline);
+    pub fn reset(&mut self, deadline: Instant) -> io::Result<()> {
+        self.inner.reset(deadline)
     }
 }
 
 impl Future for Delay {
-    type Output = ();
+    type Output = io::Result<()>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match self.inner.poll_timeout(cx) {
-            Poll::Ready(v) => match v {
-                Ok(_) => Poll::Ready(()),
-                Err(e) => panic!("timer err: {}", e),
-            },
-            Poll::Pending => Poll::Pending,
-        }
+        self.inner.poll_timeout(cx).map_ok(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::task::Waker;
+
+    use super::*;
+    use crate::runtime::Runtime;
+    use crate::time::interval;
+
+    #[test]
+    fn fails_outside_runtime() {
+        let mut delay = delay_for(Duration::from_millis(10));
+        let cx = &mut Context::from_waker(Waker::noop());
+        assert!(matches!(Pin::new(&mut delay).poll(cx), Poll::Ready(Err(_))));
+
+        let mut interval = interval(Duration::from_millis(10));
+        // the first tick completes right away, the second needs a timer.
+        assert!(matches!(interval.poll_tick(cx), Poll::Ready(Ok(_))));
+        assert!(matches!(interval.poll_tick(cx), Poll::Ready(Err(_))));
+    }
+
+    #[test]
+    fn fails_once_runtime_dropped() {
+        let rt = Runtime::new().unwrap();
+        let mut delay = delay_for(Duration::from_secs(10));
+        rt.block_on(poll_fn(|cx| {
+            assert!(Pin::new(&mut delay).poll(cx).is_pending());
+            Poll::Ready(())
+        }));
+        drop(rt);
+
+        let cx = &mut Context::from_waker(Waker::noop());
+        assert!(matches!(Pin::new(&mut delay).poll(cx), Poll::Ready(Err(_))));
+        assert!(delay.reset(Instant::now()).is_err());
+    }
+
+    #[test]
+    fn accepts_std_instant() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let deadline = std::time::Instant::now() + Duration::from_millis(1);
+            delay_until(deadline).await.unwrap();
+            assert!(std::time::Instant::now() >= deadline);
+            let res = crate::time::timeout_at(deadline, std::future::pending::<()>()).await;
+            assert!(res.is_err());
+        });
     }
 }
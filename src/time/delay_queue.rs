@@ -0,0 +1,357 @@
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll, Waker};
+use std::time::Duration;
+
+use futures_core::stream::Stream;
+use slab::Slab;
+
+use super::{delay_until, Delay, Instant};
+use crate::coop;
+
+/// A queue of values yielded once their deadline is reached.
+///
+/// Entries are kept ordered by deadline and only the earliest one is backed by
+/// a timer, so inserting, resetting and removing entries is `O(log n)` and does
+/// not touch the kernel.
+pub struct DelayQueue<T> {
+    slab: Slab<Data<T>>,
+    expirations: BTreeSet<(Instant, usize)>,
+    delay: Option<Delay>,
+    // woken by `insert` once `poll_expired` returned `None`.
+    waker: Option<Waker>,
+}
+
+struct Data<T> {
+    value: T,
+    when: Instant,
+}
+
+/// The key of an entry in a `DelayQueue`.
+///
+/// Keys are reused once their entry expired or was removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    index: usize,
+}
+
+/// An entry yielded by a `DelayQueue` once expired.
+#[derive(Debug)]
+pub struct Expired<T> {
+    data: T,
+    deadline: Instant,
+    key: Key,
+}
+
+impl<T> DelayQueue<T> {
+    pub fn new() -> DelayQueue<T> {
+        DelayQueue::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> DelayQueue<T> {
+        DelayQueue {
+            slab: Slab::with_capacity(capacity),
+            expirations: BTreeSet::new(),
+            delay: None,
+            waker: None,
+        }
+    }
+
+    /// Insert `value`, yielded once `timeout` has elapsed.
+    pub fn insert(&mut self, value: T, timeout: Duration) -> Key {
+        self.insert_at(value, Instant::now() + timeout)
+    }
+
+    /// Insert `value`, yielded once `when` is reached.
+    pub fn insert_at(&mut self, value: T, when: Instant) -> Key {
+        let index = self.slab.insert(Data { value, when });
+        self.expirations.insert((when, index));
+        self.fire_before(when);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+        Key { index }
+    }
+
+    /// Move the entry of `key` to expire once `timeout` has elapsed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn reset(&mut self, key: &Key, timeout: Duration) {
+        self.reset_at(key, Instant::now() + timeout);
+    }
+
+    /// Move the entry of `key` to expire at `when`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn reset_at(&mut self, key: &Key, when: Instant) {
+        let data = self.slab.get_mut(key.index).expect("invalid key");
+        self.expirations.remove(&(data.when, key.index));
+        data.when = when;
+        self.expirations.insert((when, key.index));
+        self.fire_before(when);
+    }
+
+    /// Remove the entry of `key` before it expires.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn remove(&mut self, key: &Key) -> Expired<T> {
+        let data = self.slab.try_remove(key.index).expect("invalid key");
+        self.expirations.remove(&(data.when, key.index));
+        Expired {
+            data: data.value,
+            deadline: data.when,
+            key: *key,
+        }
+    }
+
+    /// The deadline of the entry of `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the queue.
+    pub fn deadline(&self, key: &Key) -> Instant {
+        self.slab.get(key.index).expect("invalid key").when
+    }
+
+    pub fn len(&self) -> usize {
+        self.slab.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slab.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.slab.clear();
+        self.expirations.clear();
+        self.delay = None;
+    }
+
+    // Move the timer the task may be waiting on to `when` if it is earlier.
+    fn fire_before(&mut self, when: Instant) {
+        if let Some(delay) = &mut self.delay {
+            if when < delay.deadline() {
+                // a failure is reported by the next `poll_expired`.
+                let _ = delay.reset(when);
+            }
+        }
+    }
+
+    /// Poll for the next expired entry.
+    ///
+    /// Returns `None` once the queue is empty, the task is woken up if an
+    /// entry is inserted afterwards. Fails if the timer of the queue fails,
+    /// see `Delay`.
+    pub fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Expired<T>>>> {
+        let coop = ready!(coop::poll_proceed(cx));
+        loop {
+            let (when, index) = match self.expirations.first() {
+                Some(&first) => first,
+                None => {
+                    self.waker = Some(cx.waker().clone());
+                    return Poll::Ready(None);
+                }
+            };
+            if when <= Instant::now() {
+                self.expirations.pop_first();
+                let data = self.slab.remove(index);
+                coop.made_progress();
+                return Poll::Ready(Some(Ok(Expired {
+                    data: data.value,
+                    deadline: when,
+                    key: Key { index },
+                })));
+            }
+
+            let delay = match &mut self.delay {
+                Some(delay) => {
+                    if delay.deadline() != when {
+                        if let Err(e) = delay.reset(when) {
+                            return Poll::Ready(Some(Err(e)));
+                        }
+                    }
+                    delay
+                }
+                None => self.delay.insert(delay_until(when)),
+            };
+            if let Err(e) = ready!(Pin::new(delay).poll(cx)) {
+                return Poll::Ready(Some(Err(e)));
+            }
+        }
+    }
+}
+
+// values are never pinned.
+impl<T> Unpin for DelayQueue<T> {}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> DelayQueue<T> {
+        DelayQueue::new()
+    }
+}
+
+impl<T> Stream for DelayQueue<T> {
+    type Item = io::Result<Expired<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_expired(cx)
+    }
+}
+
+impl<T> Expired<T> {
+    pub fn get_ref(&self) -> &T {
+        &self.data
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn key(&self) -> Key {
+        self.key
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use std::cell::RefCell;
+    use std::future::poll_fn;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::runtime::Runtime;
+    use crate::task::{spawn_local, yield_now};
+    use crate::time;
+
+    type Queue = Rc<RefCell<DelayQueue<&'static str>>>;
+
+    fn run(f: impl Future<Output = ()>) {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            time::pause();
+            f.await
+        });
+    }
+
+    async fn expire_next(queue: Queue) -> (&'static str, Instant) {
+        let expired = poll_fn(|cx| queue.borrow_mut().poll_expired(cx)).await;
+        (expired.unwrap().unwrap().into_inner(), Instant::now())
+    }
+
+    // a task waiting on `queue` has armed the timer of its head.
+    async fn waiting(queue: &Queue) -> async_task::Task<(&'static str, Instant)> {
+        let task = spawn_local(expire_next(queue.clone()));
+        yield_now().await;
+        assert!(queue.borrow().delay.is_some());
+        task
+    }
+
+    fn assert_elapsed(start: Instant, at: Instant, ms: u64) {
+        // the wheel has a resolution of one millisecond.
+        let elapsed = at - start;
+        assert!(
+            elapsed >= Duration::from_millis(ms) && elapsed <= Duration::from_millis(ms + 1),
+            "expired after {:?}, expected {}ms",
+            elapsed,
+            ms
+        );
+    }
+
+    #[test]
+    fn expires_in_order() {
+        run(async {
+            let start = Instant::now();
+            let queue: Queue = Rc::default();
+            queue.borrow_mut().insert("b", Duration::from_millis(20));
+            queue.borrow_mut().insert("a", Duration::from_millis(10));
+            let (value, at) = expire_next(queue.clone()).await;
+            assert_eq!(value, "a");
+            assert_elapsed(start, at, 10);
+            let (value, at) = expire_next(queue.clone()).await;
+            assert_eq!(value, "b");
+            assert_elapsed(start, at, 20);
+            assert!(poll_fn(|cx| queue.borrow_mut().poll_expired(cx))
+                .await
+                .is_none());
+        });
+    }
+
+    #[test]
+    fn insert_earlier_than_head() {
+        run(async {
+            let start = Instant::now();
+            let queue: Queue = Rc::default();
+            queue
+                .borrow_mut()
+                .insert("late", Duration::from_millis(100));
+            let task = waiting(&queue).await;
+            queue
+                .borrow_mut()
+                .insert("early", Duration::from_millis(10));
+            let (value, at) = task.await;
+            assert_eq!(value, "early");
+            assert_elapsed(start, at, 10);
+        });
+    }
+
+    #[test]
+    fn reset_earlier_than_head() {
+        run(async {
+            let start = Instant::now();
+            let queue: Queue = Rc::default();
+            let key = queue.borrow_mut().insert("a", Duration::from_millis(100));
+            queue.borrow_mut().insert("b", Duration::from_millis(50));
+            let task = waiting(&queue).await;
+            queue.borrow_mut().reset(&key, Duration::from_millis(10));
+            let (value, at) = task.await;
+            assert_eq!(value, "a");
+            assert_elapsed(start, at, 10);
+        });
+    }
+
+    #[test]
+    fn remove_head() {
+        run(async {
+            let start = Instant::now();
+            let queue: Queue = Rc::default();
+            let key = queue.borrow_mut().insert("a", Duration::from_millis(10));
+            queue.borrow_mut().insert("b", Duration::from_millis(50));
+            let task = waiting(&queue).await;
+            assert_eq!(queue.borrow_mut().remove(&key).into_inner(), "a");
+            let (value, at) = task.await;
+            assert_eq!(value, "b");
+            assert_elapsed(start, at, 50);
+        });
+    }
+
+    #[test]
+    fn insert_into_empty_queue_wakes() {
+        run(async {
+            let start = Instant::now();
+            let queue: Queue = Rc::default();
+            assert!(poll_fn(|cx| queue.borrow_mut().poll_expired(cx))
+                .await
+                .is_none());
+            queue.borrow_mut().insert("a", Duration::from_millis(10));
+            let (value, at) = expire_next(queue.clone()).await;
+            assert_eq!(value, "a");
+            assert_elapsed(start, at, 10);
+        });
+    }
+}
@@ -0,0 +1,244 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use io_uring::types::TimeoutFlags;
+use scoped_tls::scoped_thread_local;
+
+use super::clock::{self, Clock};
+use super::wheel::Wheel;
+use super::Instant;
+use crate::driver::{self, Op};
+
+scoped_thread_local!(static CURRENT: TimeDriver);
+
+/// Drives the timers of a runtime.
+///
+/// All timers share one wheel with a resolution of one millisecond, and a
+/// single kernel timeout is armed for the nearest deadline before the runtime
+/// blocks in the driver.
+pub(crate) struct TimeDriver {
+    clock: Clock,
+    inner: Rc<RefCell<Inner>>,
+    // set once the kernel rejected a multishot timeout, it requires 6.4+.
+    multishot_unsupported: Cell<bool>,
+}
+
+struct Inner {
+    start: Instant,
+    wheel: Wheel,
+    // the kernel timeout and the tick it fires at.
+    armed: Option<(u64, Op<driver::Timeout>)>,
+}
+
+impl TimeDriver {
+    pub(crate) fn new() -> TimeDriver {
+        let clock = Clock::new();
+        TimeDriver {
+            inner: Rc::new(RefCell::new(Inner {
+                start: clock.now(),
+                wheel: Wheel::new(),
+                armed: None,
+            })),
+            clock,
+            multishot_unsupported: Cell::new(false),
+        }
+    }
+
+    pub(crate) fn with<T>(&self, f: impl FnOnce() -> T) -> T {
+        CURRENT.set(self, f)
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// Whether the clock is paused and should be advanced rather than waited on.
+    pub(crate) fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    /// Whether intervals may be driven by a multishot kernel timeout.
+    pub(crate) fn multishot_supported(&self) -> bool {
+        !self.multishot_unsupported.get() && !self.clock.is_paused()
+    }
+
+    pub(crate) fn set_multishot_unsupported(&self) {
+        self.multishot_unsupported.set(true);
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn pause(&self) {
+        self.clock.pause();
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn resume(&self) {
+        self.clock.resume();
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn advance(&self, duration: Duration) {
+        self.clock.advance(duration);
+        self.process();
+    }
+
+    /// Move the paused clock to the nearest deadline and fire the timers there,
+    /// returns `false` if there is no timer pending.
+    #[cfg(feature = "test-util")]
+    pub(crate) fn auto_advance(&self) -> bool {
+        let next = {
+            let inner = self.inner.borrow();
+            match inner.wheel.next_expiration() {
+                Some(next) => inner.tick_to_instant(next),
+                None => return false,
+            }
+        };
+        let now = self.clock.now();
+        if next > now {
+            self.clock.advance(next - now);
+        }
+        self.process();
+        true
+    }
+
+    #[cfg(not(feature = "test-util"))]
+    pub(crate) fn auto_advance(&self) -> bool {
+        false
+    }
+
+    /// Make sure the kernel timeout fires no later than the nearest deadline.
+    pub(crate) fn arm(&self) -> io::Result<()> {
+        if self.clock.is_paused() {
+            return Ok(());
+        }
+        let mut inner = self.inner.borrow_mut();
+        let next = match inner.wheel.next_expiration() {
+            Some(next) => next,
+            None => return Ok(()),
+        };
+        if let Some((when, _)) = inner.armed {
+            if when <= next {
+                return Ok(());
+            }
+        }
+        // the timeout is absolute, so the time spent until it is submitted does
+        // not delay it.
+        let remaining = inner
+            .tick_to_instant(next)
+            .saturating_duration_since(self.clock.now());
+        let deadline = clock::clock_gettime(libc::CLOCK_MONOTONIC) + remaining;
+        // replacing the previous timeout cancels it.
+        inner.armed = Some((next, Op::timeout_at(deadline, TimeoutFlags::empty())?));
+        Ok(())
+    }
+
+    /// Fire the timers whose deadline has elapsed.
+    pub(crate) fn process(&self) {
+        let mut wakers = Vec::new();
+        {
+            let mut inner = self.inner.borrow_mut();
+            let now = inner.instant_to_tick(self.clock.now(), false);
+            inner.wheel.advance(now, &mut wakers);
+            // the timeout may also have fired while the clock was paused.
+            if matches!(&inner.armed, Some((when, op)) if *when <= now || op.is_completed()) {
+                inner.armed = None;
+            }
+        }
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl Inner {
+    fn instant_to_tick(&self, t: Instant, round_up: bool) -> u64 {
+        let duration = t.saturating_duration_since(self.start);
+        let ms = duration.as_millis() as u64;
+        if round_up && !duration.subsec_nanos().is_multiple_of(1_000_000) {
+            ms + 1
+        } else {
+            ms
+        }
+    }
+
+    fn tick_to_instant(&self, tick: u64) -> Instant {
+        self.start + Duration::from_millis(tick)
+    }
+}
+
+/// Run `f` with the time driver of the current runtime, if any.
+pub(crate) fn with_current<T>(f: impl FnOnce(&TimeDriver) -> T) -> Option<T> {
+    if CURRENT.is_set() {
+        Some(CURRENT.with(f))
+    } else {
+        None
+    }
+}
+
+/// The error of a timer polled outside of a runtime context.
+pub(crate) fn outside_runtime() -> io::Error {
+    io::Error::other("timer polled outside of a runtime context")
+}
+
+/// A timer entry in the wheel of the runtime it was first polled on.
+pub(crate) struct Registration {
+    inner: Weak<RefCell<Inner>>,
+    key: usize,
+}
+
+impl Registration {
+    /// Register a timer firing at `deadline` on the current runtime.
+    pub(crate) fn new(deadline: Instant) -> io::Result<Registration> {
+        if !CURRENT.is_set() {
+            return Err(outside_runtime());
+        }
+        Ok(CURRENT.with(|driver| {
+            let mut inner = driver.inner.borrow_mut();
+            let when = inner.instant_to_tick(deadline, true);
+            Registration {
+                key: inner.wheel.insert(when),
+                inner: Rc::downgrade(&driver.inner),
+            }
+        }))
+    }
+
+    pub(crate) fn poll_elapsed(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let inner = self.upgrade()?;
+        let mut inner = inner.borrow_mut();
+        if inner.wheel.is_fired(self.key) {
+            return Poll::Ready(Ok(()));
+        }
+        inner.wheel.set_waker(self.key, cx.waker());
+        Poll::Pending
+    }
+
+    pub(crate) fn reset(&self, deadline: Instant) -> io::Result<()> {
+        let inner = self.upgrade()?;
+        let waker = {
+            let mut inner = inner.borrow_mut();
+            let when = inner.instant_to_tick(deadline, true);
+            inner.wheel.reset(self.key, when)
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn upgrade(&self) -> io::Result<Rc<RefCell<Inner>>> {
+        self.inner
+            .upgrade()
+            .ok_or_else(|| io::Error::other("timer driver has been dropped"))
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade() {
+            inner.borrow_mut().wheel.remove(self.key);
+        }
+    }
+}
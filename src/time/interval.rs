@@ -1,52 +1,240 @@
 use std::future::poll_fn;
 use std::future::Future;
+use std::io;
 use std::pin::Pin;
 use std::task::{ready, Context, Poll};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use super::{delay_until, Delay};
+use super::{delay_until, driver, Delay, Instant};
+use crate::driver::{Op, TimeoutMulti};
 
 use futures_core::stream::Stream;
 
+// A tick is considered missed once it is late by more than this.
+const MISSED_TICK_THRESHOLD: Duration = Duration::from_millis(5);
+// How late a tick may be for the following ones to be driven by a multishot
+// timeout, which fires in phase with the moment it was armed. A bit more than
+// the resolution of the timer wheel, so a tick fired by the wheel can arm it.
+const MULTISHOT_MAX_LAG: Duration = Duration::from_millis(2);
+
 pub fn interval(period: Duration) -> Interval {
     assert!(period > Duration::new(0, 0), "`period` must be non-zero.");
 
     interval_at(Instant::now(), period)
 }
 
-pub fn interval_at(start: Instant, period: Duration) -> Interval {
+/// Ticks every `period` from `start`, either a `slings::time::Instant` or a
+/// `std::time::Instant`.
+pub fn interval_at(start: impl Into<Instant>, period: Duration) -> Interval {
     assert!(period > Duration::new(0, 0), "`period` must be non-zero.");
 
+    let start = start.into();
     Interval {
-        delay: delay_until(start),
+        deadline: start,
         period,
+        missed_tick_behavior: MissedTickBehavior::default(),
+        state: State::Delay(delay_until(start)),
+    }
+}
+
+/// Defines the behavior of an `Interval` when it misses a tick.
+///
+/// A tick is missed when the task polling the interval was busy past its
+/// deadline, e.g. `tick` was awaited 25ms after a 10ms interval started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissedTickBehavior {
+    /// Ticks as fast as possible until caught up, then keeps the original
+    /// schedule.
+    #[default]
+    Burst,
+    /// Ticks right away, then schedules the next tick `period` from now.
+    Delay,
+    /// Ticks right away, then skips the missed ticks and resumes on the
+    /// original schedule.
+    Skip,
+}
+
+impl MissedTickBehavior {
+    // The deadline of the tick following the missed `timeout`.
+    fn next_timeout(&self, timeout: Instant, now: Instant, period: Duration) -> Instant {
+        match self {
+            MissedTickBehavior::Burst => timeout + period,
+            MissedTickBehavior::Delay => now + period,
+            MissedTickBehavior::Skip => {
+                let late = (now - timeout).as_nanos() % period.as_nanos();
+                now + period - Duration::from_nanos(late as u64)
+            }
+        }
     }
 }
 
+/// Ticks every `period`.
+///
+/// While ticks stay on time, the interval is backed by a single multishot
+/// kernel timeout where supported, rather than re-arming a timer every period.
+/// Like `Delay`, it fails if it is polled outside of a runtime context or after
+/// its runtime has been dropped.
 pub struct Interval {
-    delay: Delay,
+    deadline: Instant,
     period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+    state: State,
+}
+
+enum State {
+    /// Waiting on a timer of the runtime.
+    Delay(Delay),
+    /// Woken by a timeout firing every period, in phase with `deadline`.
+    Multishot(Op<TimeoutMulti>),
 }
 
 impl Interval {
-    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
-        ready!(Pin::new(&mut self.delay).poll(cx));
-        let now = self.delay.deadline();
-        let next = now + self.period;
-        self.delay.reset(next);
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Instant>> {
+        ready!(self.poll_deadline(cx))?;
 
-        Poll::Ready(now)
+        let timeout = self.deadline;
+        let now = Instant::now();
+        let next = if now > timeout + MISSED_TICK_THRESHOLD {
+            self.missed_tick_behavior
+                .next_timeout(timeout, now, self.period)
+        } else {
+            timeout + self.period
+        };
+        self.deadline = next;
+
+        // the kernel re-arms a multishot timeout from each expiration, so it
+        // slowly drifts behind and is given up once it lags too much.
+        let on_schedule = next == timeout + self.period && now - timeout <= MULTISHOT_MAX_LAG;
+        match &mut self.state {
+            State::Multishot(_) if on_schedule => {}
+            State::Delay(_) if on_schedule => match multishot(self.period) {
+                Some(op) => self.state = State::Multishot(op),
+                None => self.reset_delay(next)?,
+            },
+            _ => self.reset_delay(next)?,
+        }
+
+        Poll::Ready(Ok(timeout))
     }
 
-    pub async fn tick(&mut self) -> Instant {
+    pub async fn tick(&mut self) -> io::Result<Instant> {
         poll_fn(|cx| self.poll_tick(cx)).await
     }
+
+    /// Resets the interval to complete one period after the current time.
+    pub fn reset(&mut self) -> io::Result<()> {
+        self.reset_at(Instant::now() + self.period)
+    }
+
+    /// Resets the interval to complete at `deadline`, the following ticks are
+    /// scheduled from there.
+    pub fn reset_at(&mut self, deadline: Instant) -> io::Result<()> {
+        self.deadline = deadline;
+        self.reset_delay(deadline)
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    fn poll_deadline(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match &mut self.state {
+                State::Delay(delay) => return Pin::new(delay).poll(cx),
+                State::Multishot(op) => {
+                    // expirations are consumed before the deadline is checked,
+                    // so that the next tick is not woken by a stale one.
+                    let polled = op.poll_multishot(cx);
+                    if let Poll::Ready(Some(res)) = polled {
+                        if let Err(e) = res {
+                            if e.raw_os_error() == Some(libc::EINVAL) {
+                                driver::with_current(|driver| driver.set_multishot_unsupported());
+                            }
+                        }
+                        self.reset_delay(self.deadline)?;
+                        continue;
+                    }
+                    if Instant::now() >= self.deadline {
+                        return Poll::Ready(Ok(()));
+                    }
+                    // a paused clock is not followed by the kernel.
+                    if is_paused() {
+                        self.reset_delay(self.deadline)?;
+                        continue;
+                    }
+                    if polled.is_pending() {
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
+    }
+
+    // Wait for `deadline` on a timer of the runtime, cancelling the multishot
+    // timeout if any.
+    fn reset_delay(&mut self, deadline: Instant) -> io::Result<()> {
+        match &mut self.state {
+            State::Delay(delay) => delay.reset(deadline)?,
+            State::Multishot(_) => self.state = State::Delay(delay_until(deadline)),
+        }
+        Ok(())
+    }
+}
+
+fn is_paused() -> bool {
+    driver::with_current(|driver| driver.is_paused()).unwrap_or(false)
+}
+
+// The multishot timeout is probed on first use, once per runtime.
+fn multishot(period: Duration) -> Option<Op<TimeoutMulti>> {
+    driver::with_current(|driver| {
+        if !driver.multishot_supported() {
+            return None;
+        }
+        Op::timeout_multi(period).ok()
+    })
+    .flatten()
 }
 
 impl Stream for Interval {
-    type Item = Instant;
+    type Item = io::Result<Instant>;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Instant>> {
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         Poll::Ready(Some(ready!(self.poll_tick(cx))))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    // a tick takes one poll to wait for the timer and one to complete, the
+    // expirations of a multishot timeout do not cause an extra wakeup.
+    #[test]
+    fn ticks_without_spurious_polls() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut interval = interval(Duration::from_millis(5));
+            interval.tick().await.unwrap();
+            for _ in 0..5 {
+                let mut polls = 0;
+                poll_fn(|cx| {
+                    polls += 1;
+                    interval.poll_tick(cx)
+                })
+                .await
+                .unwrap();
+                assert!(polls <= 2, "tick polled {} times", polls);
+            }
+        });
+    }
+}
@@ -1,38 +1,40 @@
-use std::future::Future;
 use std::io;
-use std::ops::Sub;
-use std::pin::Pin;
-use std::task::{ready, Context, Poll, Waker};
-use std::time::Instant;
+use std::task::{ready, Context, Poll};
 
-use crate::driver::{self, Op};
+mod clock;
+pub(crate) mod driver;
+mod instant;
+mod wheel;
 
+pub mod clock_delay;
 pub mod delay;
+pub mod delay_queue;
 pub mod interval;
 pub mod timeout;
 
+#[cfg(feature = "test-util")]
+pub use clock::{advance, pause, resume};
+pub use clock_delay::{
+    sleep_for_boottime, sleep_until_boottime, sleep_until_system_time, ClockDelay,
+};
 pub use delay::{delay_for, delay_until, Delay};
-pub use interval::{interval, interval_at, Interval};
+pub use delay_queue::DelayQueue;
+pub use instant::Instant;
+pub use interval::{interval, interval_at, Interval, MissedTickBehavior};
 pub use timeout::{timeout, timeout_at, Timeout};
 
-enum TimeoutState {
-    Idle,
-    Waiting(Op<driver::Timeout>),
-    Done,
-}
+use driver::Registration;
 
 pub struct Timer {
     deadline: Instant,
-    state: TimeoutState,
-    waker: Option<Waker>,
+    registration: Option<Registration>,
 }
 
 impl Timer {
     pub fn new(deadline: Instant) -> Timer {
         Timer {
             deadline,
-            state: TimeoutState::Idle,
-            waker: None,
+            registration: None,
         }
     }
 
@@ -44,15 +46,15 @@ impl Timer {
         self.deadline < Instant::now()
     }
 
-    pub fn reset(&mut self, when: Instant) {
-        self.state = TimeoutState::Idle;
+    /// Reset the timer to fire at `when`.
+    ///
+    /// This only moves the timer in the wheel of the runtime, it fails if that
+    /// runtime has been dropped.
+    pub fn reset(&mut self, when: Instant) -> io::Result<()> {
         self.deadline = when;
-        if let Some(waker) = self.waker.as_ref() {
-            let duration = self.deadline.sub(Instant::now());
-            let op = Op::timeout(duration.as_secs(), duration.subsec_nanos())
-                .expect("fail to submit timeout sqe");
-            op.reset(waker.clone());
-            self.state = TimeoutState::Waiting(op);
+        match &self.registration {
+            Some(registration) => registration.reset(when),
+            None => Ok(()),
         }
     }
 
@@ -62,28 +64,17 @@ impl Timer {
         }
 
         loop {
-            match &mut self.state {
-                TimeoutState::Idle => {
-                    let duration = self.deadline.sub(Instant::now());
-                    let op = Op::timeout(duration.as_secs(), duration.subsec_nanos())?;
-                    self.state = TimeoutState::Waiting(op);
+            match &self.registration {
+                None => {
+                    self.registration = Some(Registration::new(self.deadline)?);
                 }
-                TimeoutState::Waiting(op) => {
-                    match &self.waker {
-                        Some(waker) if !waker.will_wake(cx.waker()) => {
-                            self.waker = Some(cx.waker().clone());
-                        }
-                        None => {
-                            self.waker = Some(cx.waker().clone());
-                        }
-                        _ => {}
+                Some(registration) => {
+                    ready!(registration.poll_elapsed(cx))?;
+                    if self.deadline <= Instant::now() {
+                        return Poll::Ready(Ok(self.deadline));
                     }
-                    ready!(Pin::new(op).poll(cx))?;
-                    self.state = TimeoutState::Done;
-                }
-                TimeoutState::Done => {
-                    self.waker = None;
-                    return Poll::Ready(Ok(self.deadline));
+                    // deadlines beyond the range of the wheel fire early.
+                    registration.reset(self.deadline)?;
                 }
             }
         }
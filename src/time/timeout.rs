@@ -3,9 +3,9 @@ use std::future::Future;
 use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use super::{delay_until, Delay};
+use super::{delay_until, Delay, Instant};
 
 use pin_project_lite::pin_project;
 
@@ -16,7 +16,9 @@ where
     timeout_at(Instant::now() + duration, future)
 }
 
-pub fn timeout_at<T>(deadline: Instant, future: T) -> Timeout<T>
+/// Requires `future` to complete before `deadline`, either a
+/// `slings::time::Instant` or a `std::time::Instant`.
+pub fn timeout_at<T>(deadline: impl Into<Instant>, future: T) -> Timeout<T>
 where
     T: Future,
 {
@@ -51,20 +53,27 @@ impl<T> Timeout<T> {
     }
 }
 
+/// The deadline of a `Timeout` has elapsed, or its timer failed.
 #[derive(Debug)]
-pub struct Elapsed(());
+pub struct Elapsed(Option<io::Error>);
 
 impl std::error::Error for Elapsed {}
 
 impl fmt::Display for Elapsed {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "deadline has elapsed".fmt(fmt)
+        match &self.0 {
+            Some(err) => write!(fmt, "timer error: {}", err),
+            None => "deadline has elapsed".fmt(fmt),
+        }
     }
 }
 
 impl From<Elapsed> for io::Error {
-    fn from(_err: Elapsed) -> std::io::Error {
-        io::ErrorKind::TimedOut.into()
+    fn from(err: Elapsed) -> std::io::Error {
+        match err.0 {
+            Some(err) => err,
+            None => io::ErrorKind::TimedOut.into(),
+        }
     }
 }
 
@@ -81,7 +90,7 @@ where
         }
 
         match this.delay.poll(cx) {
-            Poll::Ready(()) => Poll::Ready(Err(Elapsed(()))),
+            Poll::Ready(res) => Poll::Ready(Err(Elapsed(res.err()))),
             Poll::Pending => Poll::Pending,
         }
     }
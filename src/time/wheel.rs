@@ -0,0 +1,343 @@
+use std::array;
+use std::task::Waker;
+
+use slab::Slab;
+
+// Each level has 64 slots, a slot of level `n` covers `64^n` ticks.
+const LEVEL_BITS: usize = 6;
+const NUM_SLOTS: usize = 1 << LEVEL_BITS;
+const NUM_LEVELS: usize = 6;
+
+/// The furthest tick, relative to `elapsed`, an entry can be scheduled at.
+pub(crate) const MAX_DURATION: u64 = (1 << (LEVEL_BITS * NUM_LEVELS)) - 1;
+
+/// A hierarchical timing wheel.
+///
+/// Entries are kept in the slot of the coarsest level that still tells them
+/// apart from `elapsed`, and cascade to finer levels as time advances. Slots
+/// are intrusive doubly linked lists so insertion and removal are `O(1)`.
+pub(crate) struct Wheel {
+    elapsed: u64,
+    levels: [Level; NUM_LEVELS],
+    entries: Slab<Entry>,
+}
+
+struct Level {
+    // bit `n` is set if slot `n` is not empty.
+    occupied: u64,
+    heads: [Option<usize>; NUM_SLOTS],
+}
+
+struct Entry {
+    when: u64,
+    waker: Option<Waker>,
+    // `(level, slot)` the entry is linked in, `None` once fired.
+    slot: Option<(usize, usize)>,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+impl Wheel {
+    pub(crate) fn new() -> Wheel {
+        Wheel {
+            elapsed: 0,
+            levels: array::from_fn(|_| Level {
+                occupied: 0,
+                heads: [None; NUM_SLOTS],
+            }),
+            entries: Slab::new(),
+        }
+    }
+
+    /// Insert an entry firing at tick `when`, it is fired right away if
+    /// `when` has already elapsed.
+    pub(crate) fn insert(&mut self, when: u64) -> usize {
+        let key = self.entries.insert(Entry {
+            when,
+            waker: None,
+            slot: None,
+            prev: None,
+            next: None,
+        });
+        self.schedule(key, when);
+        key
+    }
+
+    /// Move the entry to tick `when`, returns its waker if it fired right away.
+    pub(crate) fn reset(&mut self, key: usize, when: u64) -> Option<Waker> {
+        self.unlink(key);
+        if self.schedule(key, when) {
+            return self.entries[key].waker.take();
+        }
+        None
+    }
+
+    pub(crate) fn remove(&mut self, key: usize) {
+        self.unlink(key);
+        self.entries.remove(key);
+    }
+
+    pub(crate) fn is_fired(&self, key: usize) -> bool {
+        self.entries[key].slot.is_none()
+    }
+
+    pub(crate) fn set_waker(&mut self, key: usize, waker: &Waker) {
+        let entry = &mut self.entries[key];
+        match entry.waker.as_ref() {
+            Some(current) if current.will_wake(waker) => {}
+            _ => entry.waker = Some(waker.clone()),
+        }
+    }
+
+    /// The tick of the earliest slot to process, entries of higher levels may
+    /// be cascaded down instead of fired when it is reached.
+    pub(crate) fn next_expiration(&self) -> Option<u64> {
+        self.next_slot().map(|(_, _, deadline)| deadline)
+    }
+
+    /// Advance the wheel to tick `now`, fired entries wakers are pushed to `wakers`.
+    pub(crate) fn advance(&mut self, now: u64, wakers: &mut Vec<Waker>) {
+        while let Some((level, slot, deadline)) = self.next_slot() {
+            if deadline > now {
+                break;
+            }
+            self.elapsed = deadline;
+
+            let mut next = self.levels[level].heads[slot].take();
+            self.levels[level].occupied &= !(1 << slot);
+            while let Some(key) = next {
+                let entry = &mut self.entries[key];
+                next = entry.next.take();
+                entry.prev = None;
+                entry.slot = None;
+                let when = entry.when;
+                if when <= self.elapsed {
+                    if let Some(waker) = entry.waker.take() {
+                        wakers.push(waker);
+                    }
+                } else {
+                    self.link(key, when);
+                }
+            }
+        }
+        self.elapsed = self.elapsed.max(now);
+    }
+
+    // Link the entry in the wheel, or mark it fired if `when` has elapsed.
+    fn schedule(&mut self, key: usize, when: u64) -> bool {
+        let when = when.min(self.elapsed + MAX_DURATION);
+        self.entries[key].when = when;
+        if when <= self.elapsed {
+            return true;
+        }
+        self.link(key, when);
+        false
+    }
+
+    fn link(&mut self, key: usize, when: u64) {
+        let level = level_for(self.elapsed, when);
+        let slot = slot_for(when, level);
+        let head = self.levels[level].heads[slot].replace(key);
+        if let Some(head) = head {
+            self.entries[head].prev = Some(key);
+        }
+        self.levels[level].occupied |= 1 << slot;
+
+        let entry = &mut self.entries[key];
+        entry.slot = Some((level, slot));
+        entry.prev = None;
+        entry.next = head;
+    }
+
+    fn unlink(&mut self, key: usize) {
+        let entry = &mut self.entries[key];
+        let (level, slot) = match entry.slot.take() {
+            Some(v) => v,
+            None => return,
+        };
+        let prev = entry.prev.take();
+        let next = entry.next.take();
+
+        match prev {
+            Some(prev) => self.entries[prev].next = next,
+            None => self.levels[level].heads[slot] = next,
+        }
+        if let Some(next) = next {
+            self.entries[next].prev = prev;
+        }
+        if self.levels[level].heads[slot].is_none() {
+            self.levels[level].occupied &= !(1 << slot);
+        }
+    }
+
+    fn next_slot(&self) -> Option<(usize, usize, u64)> {
+        self.levels
+            .iter()
+            .enumerate()
+            .find_map(|(level, l)| l.next_slot(level, self.elapsed))
+    }
+}
+
+impl Level {
+    // The next occupied slot at or after `now`, along with the tick it starts at.
+    fn next_slot(&self, level: usize, now: u64) -> Option<(usize, usize, u64)> {
+        if self.occupied == 0 {
+            return None;
+        }
+        let slot_range = 1u64 << (LEVEL_BITS * level);
+        let level_range = slot_range << LEVEL_BITS;
+
+        let now_slot = slot_for(now, level);
+        let zeros = self.occupied.rotate_right(now_slot as u32).trailing_zeros() as usize;
+        let slot = (zeros + now_slot) % NUM_SLOTS;
+
+        let level_start = now & !(level_range - 1);
+        let mut deadline = level_start + slot as u64 * slot_range;
+        if deadline < now {
+            deadline += level_range;
+        }
+        Some((level, slot, deadline))
+    }
+}
+
+fn level_for(elapsed: u64, when: u64) -> usize {
+    const SLOT_MASK: u64 = (1 << LEVEL_BITS) - 1;
+
+    // the highest bit `elapsed` and `when` differ in picks the level.
+    let mut masked = (elapsed ^ when) | SLOT_MASK;
+    if masked >= MAX_DURATION {
+        masked = MAX_DURATION - 1;
+    }
+    let significant = 63 - masked.leading_zeros() as usize;
+    significant / LEVEL_BITS
+}
+
+fn slot_for(when: u64, level: usize) -> usize {
+    ((when >> (LEVEL_BITS * level)) as usize) & (NUM_SLOTS - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Waker;
+
+    use super::*;
+
+    fn insert(wheel: &mut Wheel, when: u64) -> usize {
+        let key = wheel.insert(when);
+        wheel.set_waker(key, Waker::noop());
+        key
+    }
+
+    fn advance(wheel: &mut Wheel, now: u64) -> usize {
+        let mut wakers = Vec::new();
+        wheel.advance(now, &mut wakers);
+        wakers.len()
+    }
+
+    #[test]
+    fn fires_at_deadline() {
+        let mut wheel = Wheel::new();
+        let key = insert(&mut wheel, 10);
+        assert_eq!(wheel.next_expiration(), Some(10));
+        assert_eq!(advance(&mut wheel, 9), 0);
+        assert!(!wheel.is_fired(key));
+        assert_eq!(advance(&mut wheel, 10), 1);
+        assert!(wheel.is_fired(key));
+        assert_eq!(wheel.next_expiration(), None);
+    }
+
+    #[test]
+    fn cascades_through_levels() {
+        let mut wheel = Wheel::new();
+        // levels 1, 2 and 3.
+        let deadlines = [100, 5_000, 300_000];
+        let keys: Vec<_> = deadlines
+            .iter()
+            .map(|&when| insert(&mut wheel, when))
+            .collect();
+        for (&when, &key) in deadlines.iter().zip(&keys) {
+            // the slot of a higher level starts before the deadline, the entry
+            // is moved down instead of fired.
+            while let Some(next) = wheel.next_expiration() {
+                if next >= when {
+                    break;
+                }
+                assert_eq!(advance(&mut wheel, next), 0);
+                assert!(!wheel.is_fired(key));
+            }
+            assert_eq!(advance(&mut wheel, when), 1);
+            assert!(wheel.is_fired(key));
+        }
+    }
+
+    #[test]
+    fn jump_past_several_deadlines() {
+        let mut wheel = Wheel::new();
+        for when in [1, 70, 4_100, 262_200] {
+            insert(&mut wheel, when);
+        }
+        assert_eq!(advance(&mut wheel, 300_000), 4);
+        assert_eq!(wheel.next_expiration(), None);
+    }
+
+    #[test]
+    fn clamps_deadline_beyond_range() {
+        let mut wheel = Wheel::new();
+        // 64^6 ms, one past the last tick the wheel can tell apart.
+        let key = insert(&mut wheel, 1 << 36);
+        assert_eq!(advance(&mut wheel, MAX_DURATION - 1), 0);
+        assert!(!wheel.is_fired(key));
+        assert_eq!(advance(&mut wheel, MAX_DURATION), 1);
+        assert!(wheel.is_fired(key));
+    }
+
+    #[test]
+    fn reset_to_earlier() {
+        let mut wheel = Wheel::new();
+        let key = insert(&mut wheel, 5_000);
+        assert!(wheel.reset(key, 20).is_none());
+        assert_eq!(wheel.next_expiration(), Some(20));
+        assert_eq!(advance(&mut wheel, 20), 1);
+        assert!(wheel.is_fired(key));
+    }
+
+    #[test]
+    fn reset_to_elapsed_fires_right_away() {
+        let mut wheel = Wheel::new();
+        let key = insert(&mut wheel, 5_000);
+        advance(&mut wheel, 100);
+        assert!(wheel.reset(key, 50).is_some());
+        assert!(wheel.is_fired(key));
+        assert_eq!(advance(&mut wheel, 10_000), 0);
+    }
+
+    #[test]
+    fn reset_fired_entry() {
+        let mut wheel = Wheel::new();
+        let key = insert(&mut wheel, 10);
+        assert_eq!(advance(&mut wheel, 10), 1);
+        wheel.set_waker(key, Waker::noop());
+        assert!(wheel.reset(key, 30).is_none());
+        assert!(!wheel.is_fired(key));
+        assert_eq!(advance(&mut wheel, 30), 1);
+    }
+
+    #[test]
+    fn remove_cancels() {
+        let mut wheel = Wheel::new();
+        let a = insert(&mut wheel, 100);
+        let b = insert(&mut wheel, 100);
+        let c = insert(&mut wheel, 100);
+        // unlinked from the middle and the head of a slot.
+        wheel.remove(b);
+        wheel.remove(c);
+        assert_eq!(advance(&mut wheel, 100), 1);
+        assert!(wheel.is_fired(a));
+        wheel.remove(a);
+
+        let key = insert(&mut wheel, 5_000);
+        wheel.remove(key);
+        assert_eq!(wheel.next_expiration(), None);
+        assert_eq!(advance(&mut wheel, 10_000), 0);
+    }
+}